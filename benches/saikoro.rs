@@ -0,0 +1,100 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, SeedableRng};
+use saikoro::{Expression, Script, __private::TokenStream};
+
+const SHORT: &str = "4d6kh3 + 2d20adv * (1d4 + 1) - 3";
+/// A large pool of dice which passes through several operators
+const POOL: &str = "1000d6 .+ 1 kh500 > 3";
+/// A pool large enough for its total to be sampled rather than rolled
+const HUGE_POOL: &str = "1000000d6 + 1";
+
+/// An expression of roughly 10,000 characters
+fn long_input() -> String
+{
+	let mut input = String::from("1d6");
+	while input.len() < 10_000
+	{
+		input.push_str(" + 2d6kh1 - 3 * 1d4");
+	}
+	input
+}
+
+fn inputs() -> [(&'static str, String); 2]
+{
+	[("short", String::from(SHORT)), ("10k", long_input())]
+}
+
+fn tokenize(c: &mut Criterion)
+{
+	let mut group = c.benchmark_group("tokenize");
+	for (name, input) in inputs()
+	{
+		group.bench_with_input(BenchmarkId::from_parameter(name), &input, |b, input| {
+			b.iter(|| TokenStream::new(black_box(input)).count());
+		});
+	}
+	group.finish();
+}
+
+fn parse(c: &mut Criterion)
+{
+	let mut group = c.benchmark_group("parse");
+	for (name, input) in inputs()
+	{
+		group.bench_with_input(BenchmarkId::from_parameter(name), &input, |b, input| {
+			b.iter(|| Script::parse(black_box(input)).unwrap());
+		});
+	}
+	group.finish();
+}
+
+fn evaluate(c: &mut Criterion)
+{
+	let mut group = c.benchmark_group("evaluate");
+	let [short, long] = inputs();
+	for (name, input) in [short, long, ("pool", String::from(POOL))]
+	{
+		let script = Script::parse(&input).unwrap();
+		let mut rng = StdRng::seed_from_u64(2024);
+		group.bench_with_input(BenchmarkId::from_parameter(name), &script, |b, script| {
+			b.iter(|| script.eval_with_rand(&mut rng).unwrap());
+		});
+	}
+	group.finish();
+}
+
+/// Evaluating without recording rolls, as a simulation would
+fn evaluate_value(c: &mut Criterion)
+{
+	let expression = Expression::parse(SHORT).unwrap();
+	let mut rng = StdRng::seed_from_u64(2024);
+	c.bench_function("evaluate_value/short", |b| {
+		b.iter(|| expression.eval_value_with_rand(&mut rng).unwrap());
+	});
+
+	let compiled = expression.compile();
+	c.bench_function("evaluate_value/short_compiled", |b| {
+		b.iter(|| compiled.eval_value_with_rand(&mut rng).unwrap());
+	});
+}
+
+/// A huge pool whose total is sampled, compared with rolling every die
+fn huge_pool(c: &mut Criterion)
+{
+	let mut group = c.benchmark_group("huge_pool");
+	let sampled = Expression::parse(HUGE_POOL).unwrap();
+	for (name, expression) in [
+		("rolled", sampled.with_large_pool_threshold(u32::MAX)),
+		("sampled", sampled),
+	]
+	{
+		let mut rng = StdRng::seed_from_u64(2024);
+		group.bench_with_input(BenchmarkId::from_parameter(name), &expression, |b, expression| {
+			b.iter(|| expression.eval_value_with_rand(&mut rng).unwrap());
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, tokenize, parse, evaluate, evaluate_value, huge_pool);
+criterion_main!(benches);
@@ -0,0 +1,52 @@
+//! Compile-time dice notation expressions for [saikoro](https://docs.rs/saikoro)
+//! # Basic Usage example
+//! ```rust
+//! use saikoro_macros::dice;
+//!
+//! // parsed at compile time, so a typo here is a compile error rather than a runtime one
+//! let ability_score = dice!("4d6kh3");
+//! let result = ability_score.evaluate().unwrap();
+//! assert!(result.value >= 3.0 && result.value <= 18.0);
+//! ```
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, LitStr};
+
+/// Parses a dice notation string literal at compile time and expands to code which builds the
+/// resulting [`saikoro::Expression`] directly, without parsing it again at runtime. Any syntax
+/// error in the literal is a compile error
+///
+/// Rust variables can be interpolated into the expression by writing their name in braces (eg.
+/// `{count}d6`). Each interpolated variable is used as a number, and must be convertible to an
+/// [`f64`] with [`f64::from`]
+/// # Examples
+/// ```rust
+/// use saikoro_macros::dice;
+///
+/// let count = 3;
+/// let bonus = 2;
+/// let damage = dice!("{count}d6 + {bonus}");
+/// assert_eq!(damage, saikoro::Expression::parse("(3)d6 + 2").unwrap());
+/// ```
+/// Invalid expressions do not compile
+/// ```compile_fail
+/// let broken = saikoro_macros::dice!("2d6 +");
+/// ```
+#[proc_macro]
+pub fn dice(input: TokenStream) -> TokenStream
+{
+	let literal = parse_macro_input!(input as LitStr);
+
+	match saikoro::__private::expression_source(&literal.value())
+	{
+		Ok(source) => source
+			.parse()
+			.expect("generated expression source should always be valid Rust"),
+		Err(message) =>
+		{
+			syn::Error::new(literal.span(), format!("invalid dice expression: {message}"))
+				.to_compile_error()
+				.into()
+		}
+	}
+}
@@ -0,0 +1,37 @@
+use saikoro::Expression;
+use saikoro_macros::dice;
+
+#[test]
+fn matches_runtime_parsing()
+{
+	assert_eq!(dice!("4d6kh3 + 2"), Expression::parse("4d6kh3 + 2").unwrap());
+	assert_eq!(
+		dice!("if(1d20 >= 15, sort(2d6)[-1], 0) .+ 1"),
+		Expression::parse("if(1d20 >= 15, sort(2d6)[-1], 0) .+ 1").unwrap()
+	);
+}
+
+#[test]
+fn evaluates()
+{
+	let evaluation = dice!("2d6 + 3").eval_with_seed(2024).unwrap();
+	assert_eq!(evaluation.value, 12.0);
+	assert_eq!(evaluation.annotate("2d6 + 3").to_string(), "2d6 [4, 5] + 3 = 12");
+}
+
+#[test]
+fn interpolation()
+{
+	let count = 3_u8;
+	let faces = 8;
+	let bonus = 1.5;
+	assert_eq!(
+		dice!("{count}d{ faces } + {bonus} * {count}"),
+		Expression::parse("3d8 + 1.5 * 3").unwrap()
+	);
+
+	// spans refer to the literal as written
+	let input = "{count}d6 + 1";
+	let evaluation = dice!("{count}d6 + 1").eval_with_seed(2024).unwrap();
+	assert_eq!(&input[evaluation.roll_groups[0].span().unwrap()], "{count}d6");
+}
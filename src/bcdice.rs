@@ -0,0 +1,378 @@
+//! Commands following [BCDice](https://bcdice.org), the dice bot used by Japanese TRPG
+//! communities, along with its result output (eg. `(2D6+3>=10) ＞ 8[3,5]+3 ＞ 11 ＞ 成功`)
+//!
+//! A command is an expression in the [`Dialect::BCDICE`] dialect, optionally followed by a
+//! judgement which compares its total with a target (`>=`, `<=`, `>`, `<`, `=`, or `<>`). The
+//! total of a command is judged as a success (成功) or a failure (失敗), rather than filtering
+//! its dice, and `D66` reads two six-sided dice as the tens and units digits. Full-width input
+//! (eg. `２Ｄ６＞＝７`) is read the same as its ASCII form
+//! # Examples
+//! ```rust
+//! # fn main() -> Result<(), saikoro::error::ParsingError> {
+//! let command = saikoro::bcdice::Command::parse("2d6+3 >= 10")?;
+//! let result = command.evaluate()?;
+//! assert_eq!(result.success, Some(result.evaluation.value >= 10.0));
+//! assert!(result.to_string().starts_with("(2D6+3>=10) ＞ "));
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+	error::ParsingError,
+	evaluation::{DiceEvaluation, RollGroup},
+	operator::CompOperator,
+	tokenization::ascii_alias,
+	Dialect, Expression, RangeRng,
+};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt::Display;
+
+/// A parsed BCDice command, which can be evaluated any number of times without being parsed again
+#[derive(Debug, Clone, PartialEq)]
+pub struct Command
+{
+	/// The command as BCDice echoes it, in uppercase ASCII without whitespace
+	text: Box<str>,
+	/// The input of the judged expression, which the spans of its roll groups refer to
+	expression_input: Box<str>,
+	expression: Expression,
+	judgement: Option<(CompOperator, Expression)>,
+}
+impl Command
+{
+	/// Parses a BCDice command (eg. `2D6+3>=10`). The first comparison outside of parentheses
+	/// separates the expression from the target its total is judged against. A comment, starting
+	/// with `#` or after whitespace which follows a complete command, is ignored, and left out of
+	/// the command BCDice echoes
+	/// # Errors
+	/// An error variant will be returned if the expression or the target is unable to be parsed
+	/// in the [`Dialect::BCDICE`] dialect
+	pub fn parse(input: &str) -> Result<Self, ParsingError>
+	{
+		let input = strip_comment(input);
+		let (expression_input, judgement) = match find_judgement(input)
+		{
+			Some((comp_op, range)) =>
+			{
+				let target = Expression::parse_with_dialect(&input[range.end..], Dialect::BCDICE)?;
+				(&input[..range.start], Some((comp_op, target)))
+			}
+			None => (input, None),
+		};
+
+		Ok(Self {
+			text: normalize(input).into(),
+			expression_input: expression_input.into(),
+			expression: Expression::parse_with_dialect(expression_input, Dialect::BCDICE)?,
+			judgement,
+		})
+	}
+
+	/// Evaluates the command with [`rand::thread_rng`]
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	#[cfg(feature = "std")]
+	pub fn evaluate(&self) -> Result<CommandResult, ParsingError>
+	{
+		self.eval_with_rand(&mut rand::thread_rng())
+	}
+
+	/// Evaluates the command with the given [`RangeRng`]. The expression is rolled before the
+	/// target
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	pub fn eval_with_rand<R>(&self, rand: &mut R) -> Result<CommandResult, ParsingError>
+	where
+		R: RangeRng,
+	{
+		let evaluation = self.expression.eval_with_rand(rand)?;
+		let success = match &self.judgement
+		{
+			Some((comp_op, target)) =>
+			{
+				let target = target.eval_value_with_rand(rand)?;
+				Some(comp_op.compare(evaluation.value, target))
+			}
+			None => None,
+		};
+
+		Ok(CommandResult {
+			text: self.text.clone(),
+			expression_input: self.expression_input.clone(),
+			evaluation,
+			success,
+		})
+	}
+}
+
+/// The result of evaluating a BCDice [`Command`], which displays as BCDice's result output: the
+/// command, the expression with each dice term's rolls written in its place, the total, and the
+/// judgement (eg. `(2D6+3>=10) ＞ 8[3,5]+3 ＞ 11 ＞ 成功`)
+#[derive(Debug)]
+pub struct CommandResult
+{
+	text: Box<str>,
+	expression_input: Box<str>,
+	/// The evaluation of the judged expression, whose value is the total
+	pub evaluation: DiceEvaluation,
+	/// Whether the total satisfies the judgement, or [`None`] if the command has no judgement
+	pub success: Option<bool>,
+}
+impl Display for CommandResult
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		let input = &*self.expression_input;
+		let mut groups = self
+			.evaluation
+			.roll_groups
+			.iter()
+			.filter_map(|group| {
+				group
+					.span()
+					.filter(|span| input.get(span.clone()).is_some())
+					.map(|span| (span, group))
+			})
+			.collect::<Vec<_>>();
+		groups.sort_by_key(|(span, _)| span.start);
+
+		// groups within another group's span (eg. the `1D4` in `(1D4)D6`) are part of its text
+		let mut rolled = String::new();
+		let mut cursor = 0;
+		for (span, group) in groups
+		{
+			if span.start < cursor
+			{
+				continue;
+			}
+			rolled.push_str(&normalize(&input[cursor..span.start]));
+			write_group(&mut rolled, group);
+			cursor = span.end;
+		}
+		rolled.push_str(&normalize(&input[cursor..]));
+
+		let total = alloc::format!("{}", self.evaluation.value);
+		write!(f, "({}) ＞ {rolled}", self.text)?;
+		if rolled != total
+		{
+			write!(f, " ＞ {total}")?;
+		}
+		match self.success
+		{
+			Some(true) => write!(f, " ＞ 成功"),
+			Some(false) => write!(f, " ＞ 失敗"),
+			None => Ok(()),
+		}
+	}
+}
+
+/// Writes a group as BCDice does, with its rolls in brackets after its total unless it has only
+/// one die or its dice are digits
+fn write_group(out: &mut String, group: &RollGroup)
+{
+	use core::fmt::Write;
+
+	let _ = write!(out, "{}", group.total());
+	if group.len() > 1 && !group.is_concatenated() && !group.is_sampled()
+	{
+		let rolls = group
+			.iter()
+			.map(|it| alloc::format!("{}", it.original_value))
+			.collect::<Vec<_>>();
+		let _ = write!(out, "[{}]", rolls.join(","));
+	}
+}
+
+/// Returns `input` as BCDice echoes it, in uppercase ASCII without whitespace
+fn normalize(input: &str) -> String
+{
+	input
+		.chars()
+		.map(|it| ascii_alias(it).unwrap_or(it).to_ascii_uppercase())
+		.filter(|it| !it.is_whitespace())
+		.collect()
+}
+
+/// Returns `input` without the comment at its end, which starts from the first `#`, or from the
+/// first whitespace after a complete command. Whitespace between an operator and its operands is
+/// kept, so `2D6 + 3 >= 10` is one command, while `2D6+3>=10 攻撃` ends before `攻撃`
+fn strip_comment(input: &str) -> &str
+{
+	// operators (and delimiters) which join the text on either side of them into one command
+	let joins_left = |char: char| char.is_ascii_punctuation() && char != ')';
+	let joins_right = |char: char| char.is_ascii_punctuation() && char != '(';
+
+	let mut depth = 0_usize;
+	let mut last = None;
+	let mut chars = input
+		.char_indices()
+		.map(|(index, char)| (index, ascii_alias(char).unwrap_or(char)))
+		.peekable();
+	while let Some((index, char)) = chars.next()
+	{
+		match char
+		{
+			'#' => return &input[..index],
+			'(' => depth += 1,
+			')' => depth = depth.saturating_sub(1),
+			_ if char.is_whitespace() =>
+			{
+				while chars.next_if(|(_, it)| it.is_whitespace()).is_some() {}
+				let next = chars.peek().map(|(_, it)| *it);
+				if depth == 0
+					&& last.is_some_and(|it| !joins_left(it))
+					&& next.is_some_and(|it| !joins_right(it))
+				{
+					return &input[..index];
+				}
+				continue;
+			}
+			_ => (),
+		}
+		last = Some(char);
+	}
+
+	input
+}
+
+/// Returns the first comparison in `input` outside of parentheses, along with its byte range
+fn find_judgement(input: &str) -> Option<(CompOperator, core::ops::Range<usize>)>
+{
+	let mut depth = 0_usize;
+	let mut chars = input
+		.char_indices()
+		.map(|(index, char)| (index, char, ascii_alias(char).unwrap_or(char)))
+		.peekable();
+	while let Some((index, char, ascii)) = chars.next()
+	{
+		match ascii
+		{
+			'(' => depth += 1,
+			')' => depth = depth.saturating_sub(1),
+			'<' | '>' | '=' | '!' if depth == 0 =>
+			{
+				let next = chars.peek().map(|(_, _, ascii)| *ascii);
+				let (comp_op, two_chars) = match (ascii, next)
+				{
+					('>', Some('=')) => (CompOperator::GreaterOrEqual, true),
+					('<', Some('=')) => (CompOperator::LessOrEqual, true),
+					('<', Some('>')) | ('!', Some('=')) => (CompOperator::NotEquals, true),
+					('=', Some('=')) => (CompOperator::Equals, true),
+					('>', _) => (CompOperator::GreaterThan, false),
+					('<', _) => (CompOperator::LessThan, false),
+					('=', _) => (CompOperator::Equals, false),
+					_ => continue,
+				};
+				let mut end = index + char.len_utf8();
+				if two_chars
+				{
+					end += chars.next().map_or(0, |(_, char, _)| char.len_utf8());
+				}
+				return Some((comp_op, index..end));
+			}
+			_ => (),
+		}
+	}
+
+	None
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::test_helpers::RiggedRandom;
+	use alloc::string::ToString;
+
+	fn output(input: &str, rolls: &[u32]) -> String
+	{
+		Command::parse(input)
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new(rolls.iter().copied()))
+			.unwrap()
+			.to_string()
+	}
+
+	/// Common commands, with the rolls they are given and BCDice's output for those rolls
+	#[test]
+	fn common_commands()
+	{
+		for (input, rolls, expected) in [
+			("1D6", &[3][..], "(1D6) ＞ 3"),
+			("2D6", &[3, 4], "(2D6) ＞ 7[3,4] ＞ 7"),
+			("2D6+1", &[3, 4], "(2D6+1) ＞ 7[3,4]+1 ＞ 8"),
+			("2d6 + 3 >= 10", &[3, 5], "(2D6+3>=10) ＞ 8[3,5]+3 ＞ 11 ＞ 成功"),
+			("2D6>=7", &[3, 4], "(2D6>=7) ＞ 7[3,4] ＞ 7 ＞ 成功"),
+			("2D6>=8", &[3, 4], "(2D6>=8) ＞ 7[3,4] ＞ 7 ＞ 失敗"),
+			("2D6>7", &[3, 4], "(2D6>7) ＞ 7[3,4] ＞ 7 ＞ 失敗"),
+			("2D6<7", &[1, 2], "(2D6<7) ＞ 3[1,2] ＞ 3 ＞ 成功"),
+			("2D6=7", &[3, 4], "(2D6=7) ＞ 7[3,4] ＞ 7 ＞ 成功"),
+			("2D6<>7", &[3, 4], "(2D6<>7) ＞ 7[3,4] ＞ 7 ＞ 失敗"),
+			("1D100<=50", &[74], "(1D100<=50) ＞ 74 ＞ 失敗"),
+			("1D100<=50", &[23], "(1D100<=50) ＞ 23 ＞ 成功"),
+			("1D20+5", &[12], "(1D20+5) ＞ 12+5 ＞ 17"),
+			("1D6+1D4", &[3, 2], "(1D6+1D4) ＞ 3+2 ＞ 5"),
+			("2D6*2", &[3, 4], "(2D6*2) ＞ 7[3,4]*2 ＞ 14"),
+			("3D6-2", &[1, 1, 2], "(3D6-2) ＞ 4[1,1,2]-2 ＞ 2"),
+			("D66", &[3, 5], "(D66) ＞ 35"),
+			("D66>=30", &[2, 6], "(D66>=30) ＞ 26 ＞ 失敗"),
+			("D66S", &[5, 3], "(D66S) ＞ 35"),
+			("D66A", &[5, 3], "(D66A) ＞ 35"),
+			("D66D", &[3, 5], "(D66D) ＞ 53"),
+			("D66N", &[5, 3], "(D66N) ＞ 53"),
+			("D666+1", &[1, 2, 3], "(D666+1) ＞ 123+1 ＞ 124"),
+			("(2+1)D6", &[1, 2, 3], "((2+1)D6) ＞ 6[1,2,3] ＞ 6"),
+			("２Ｄ６＞＝７", &[6, 6], "(2D6>=7) ＞ 12[6,6] ＞ 12 ＞ 成功"),
+			("2D6>=5+3", &[4, 4], "(2D6>=5+3) ＞ 8[4,4] ＞ 8 ＞ 成功"),
+			("2D6/2", &[3, 4], "(2D6/2) ＞ 7[3,4]/2 ＞ 3"),
+			("(1D6+4)/3", &[1], "((1D6+4)/3) ＞ (1+4)/3 ＞ 1"),
+			("2D6>=7 # hi", &[3, 4], "(2D6>=7) ＞ 7[3,4] ＞ 7 ＞ 成功"),
+			("1D20＃攻撃", &[11], "(1D20) ＞ 11"),
+			("2D6+3>=10 攻撃", &[3, 5], "(2D6+3>=10) ＞ 8[3,5]+3 ＞ 11 ＞ 成功"),
+			("1D100<=50　命中判定 (1D6)", &[23], "(1D100<=50) ＞ 23 ＞ 成功"),
+			("(1D6 + 1) * 2 x", &[3], "((1D6+1)*2) ＞ (3+1)*2 ＞ 8"),
+		]
+		{
+			assert_eq!(output(input, rolls), expected, "{input}");
+		}
+	}
+
+	#[test]
+	fn judgements()
+	{
+		let command = Command::parse("1D20+4 >= 15").unwrap();
+		let result = command.eval_with_rand(&mut RiggedRandom::new([11])).unwrap();
+		assert_eq!(result.evaluation.value, 15.0);
+		assert_eq!(result.success, Some(true));
+
+		let result = Command::parse("1D20")
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new([11]))
+			.unwrap();
+		assert_eq!(result.success, None);
+	}
+
+	#[test]
+	fn dialect()
+	{
+		let value = |input: &str, rolls: &[u32]| {
+			Expression::parse_with_dialect(input, Dialect::BCDICE)
+				.unwrap()
+				.eval_with_rand(&mut RiggedRandom::new(rolls.iter().copied()))
+				.unwrap()
+				.value
+		};
+		// comparisons judge totals rather than filtering dice
+		assert_eq!(value("2D6>=7", &[3, 4]), 1.0);
+		assert_eq!(value("4D6>3", &[1, 1, 1, 1]), 1.0);
+		assert_eq!(value("D66", &[6, 1]), 61.0);
+		assert_eq!(value("D666", &[1, 2, 3]), 123.0);
+		// only a repeated digit without a count is read as digits
+		assert_eq!(value("D65", &[40]), 40.0);
+		assert_eq!(value("1D66", &[40]), 40.0);
+		// quotients are rounded down, including negative ones
+		assert_eq!(value("7/2", &[]), 3.0);
+		assert_eq!(value("-7/2", &[]), -4.0);
+		assert_eq!(value("1+5/2*2", &[]), 5.0);
+		assert_eq!(value("4D6./2", &[1, 2, 3, 4]), 5.0);
+	}
+}
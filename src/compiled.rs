@@ -0,0 +1,820 @@
+use crate::{
+	error::{IndexOutOfRangeError, ParsingError, UndefinedVariableError, UnknownTableError},
+	evaluation::{self, Operand},
+	math,
+	operator::{
+		function, BinaryOperator, CompOperator, DiceModifier, ListFunction, LogicOperator, OpToken,
+		UnaryOpToken,
+	},
+	parsing::Node,
+	RangeRng,
+};
+use alloc::{boxed::Box, vec::Vec};
+
+/// An [`Expression`][crate::Expression] lowered into a flat list of instructions for a stack
+/// machine, for evaluating the same expression a very large number of times (eg. in a simulation)
+///
+/// Evaluating a [`CompiledExpression`] produces exactly the same value as
+/// [`Expression::eval_value_with_rand`][crate::Expression::eval_value_with_rand] given the same
+/// [`RangeRng`], as it rolls the same dice in the same order, but it does not walk the parse tree
+/// to do so. Like value-only evaluation, it does not record any rolls. Numbers are kept on a stack
+/// of their own, so arithmetic and comparisons work directly on them, and only rolls and lists
+/// are [`Operand`]s
+///
+/// Obtained from [`Expression::compile`][crate::Expression::compile]
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// use rand::{rngs::StdRng, SeedableRng};
+/// use saikoro::Expression;
+///
+/// let expression = Expression::parse("4d6kh3 + if(1d20 >= 10, 2, 0)")?;
+/// let compiled = expression.compile();
+/// assert_eq!(
+///     compiled.eval_value_with_rand(&mut StdRng::seed_from_u64(2024))?,
+///     expression.eval_value_with_rand(&mut StdRng::seed_from_u64(2024))?,
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct CompiledExpression
+{
+	instructions: Box<[Instruction]>,
+	/// The most numbers and operands on their stacks at once, so the stacks never need to grow
+	max_numbers: usize,
+	max_operands: usize,
+	large_pool_threshold: u32,
+}
+impl CompiledExpression
+{
+	pub(crate) fn new(tree: &Node, large_pool_threshold: u32) -> Self
+	{
+		let mut compiler = Compiler::default();
+		compiler.settled(tree);
+
+		Self {
+			instructions: compiler.instructions.into_boxed_slice(),
+			max_numbers: compiler.numbers.max,
+			max_operands: compiler.operands.max,
+			large_pool_threshold,
+		}
+	}
+
+	/// Evaluates the compiled expression with [`rand::thread_rng`], returning only its value.
+	/// Equivalent to [`eval_value_with_rand`][CompiledExpression::eval_value_with_rand] called with
+	/// `&mut rand::thread_rng()`
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	#[cfg(feature = "std")]
+	pub fn evaluate_value(&self) -> Result<f64, ParsingError>
+	{
+		self.eval_value_with_rand(&mut rand::thread_rng())
+	}
+
+	/// Evaluates the compiled expression with the given [`RangeRng`], returning only its value
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	pub fn eval_value_with_rand<R>(&self, rand: &mut R) -> Result<f64, ParsingError>
+	where
+		R: RangeRng,
+	{
+		let mut numbers = Vec::with_capacity(self.max_numbers);
+		let mut operands = Vec::with_capacity(self.max_operands);
+		let mut counter = 0;
+		while let Some(instruction) = self.instructions.get(counter)
+		{
+			counter += 1;
+			match instruction
+			{
+				Instruction::Push(value) => numbers.push(*value),
+				Instruction::Undefined(name) =>
+				{
+					return Err(UndefinedVariableError(name.clone()).into());
+				}
+				Instruction::UnknownTable(name) =>
+				{
+					return Err(UnknownTableError(name.clone()).into());
+				}
+				Instruction::Add => arithmetic(&mut numbers, |lhs, rhs| lhs + rhs),
+				Instruction::Subtract => arithmetic(&mut numbers, |lhs, rhs| lhs - rhs),
+				Instruction::Multiply => arithmetic(&mut numbers, |lhs, rhs| lhs * rhs),
+				Instruction::Divide => arithmetic(&mut numbers, |lhs, rhs| lhs / rhs),
+				Instruction::FloorDivide =>
+				{
+					arithmetic(&mut numbers, |lhs, rhs| math::floor(lhs / rhs));
+				}
+				Instruction::Modulo => arithmetic(&mut numbers, |lhs, rhs| lhs % rhs),
+				Instruction::Power => arithmetic(&mut numbers, math::powf),
+				Instruction::Negate =>
+				{
+					let value = pop(&mut numbers);
+					numbers.push(-value);
+				}
+				Instruction::Not =>
+				{
+					let value = pop(&mut numbers);
+					numbers.push(truth(value == 0.0));
+				}
+				Instruction::Truthy =>
+				{
+					let value = pop(&mut numbers);
+					numbers.push(truth(value != 0.0));
+				}
+				Instruction::Compare(comp_op) =>
+				{
+					let right = pop(&mut numbers);
+					let left = pop(&mut numbers);
+					numbers.push(truth(comp_op.compare(left, right)));
+				}
+				Instruction::Dice { totalled } =>
+				{
+					let faces = Operand::Number(pop(&mut numbers));
+					let count = Operand::Number(pop(&mut numbers));
+					function::check_faces(&faces)?;
+					operands.push(if *totalled
+					{
+						function::totalled_dice(count, faces, self.large_pool_threshold, rand)
+					}
+					else
+					{
+						function::dice(count, faces, rand)
+					});
+				}
+				Instruction::UnaryDice =>
+				{
+					let faces = Operand::Number(pop(&mut numbers));
+					function::check_faces(&faces)?;
+					operands.push(function::unary_dice(faces, rand));
+				}
+				Instruction::ElementWise(operator) =>
+				{
+					let right = Operand::Number(pop(&mut numbers));
+					let left = pop(&mut operands);
+					operands.push(operator.eval(left, right, rand));
+				}
+				Instruction::Filter(comp_op) =>
+				{
+					let compare_to = Operand::Number(pop(&mut numbers));
+					let dice = pop(&mut operands);
+					operands.push(comp_op.filter(dice, compare_to));
+				}
+				Instruction::Modify {
+					modifier,
+					has_argument,
+				} =>
+				{
+					let argument = has_argument.then(|| Operand::Number(pop(&mut numbers)));
+					let dice = pop(&mut operands);
+					if *modifier == DiceModifier::Concatenate
+					{
+						function::check_digits(&dice)?;
+					}
+					operands.push(modifier.eval(dice, argument, rand));
+				}
+				Instruction::Reroll { conditions, once } =>
+				{
+					let dice = pop(&mut operands);
+					operands.push(function::reroll_any(dice, conditions, *once, rand));
+				}
+				Instruction::PlusMaximum =>
+				{
+					let dice = pop(&mut operands);
+					operands.push(function::plus_maximum(dice));
+				}
+				Instruction::ToOperand =>
+				{
+					let value = pop(&mut numbers);
+					operands.push(Operand::Number(value));
+				}
+				Instruction::Settle =>
+				{
+					let operand = pop(&mut operands);
+					numbers.push(operand.into_value());
+				}
+				Instruction::SettleToList =>
+				{
+					let operand = pop(&mut operands);
+					operands.push(match operand
+					{
+						Operand::Roll { data, .. } => Operand::List(data.list_values().into()),
+						other => other,
+					});
+				}
+				Instruction::Function {
+					function,
+					arguments,
+				} =>
+				{
+					let arguments = operands.split_off(operands.len() - arguments);
+					operands.push(function.eval(arguments)?);
+				}
+				Instruction::Index =>
+				{
+					let index = pop(&mut numbers);
+					let values = pop(&mut operands).values();
+					numbers.push(evaluation::list_index(&values, index).ok_or(
+						IndexOutOfRangeError {
+							index,
+							len: values.len(),
+						},
+					)?);
+				}
+				Instruction::Jump(target) => counter = *target,
+				Instruction::JumpUnless(target) =>
+				{
+					if pop(&mut numbers) == 0.0
+					{
+						counter = *target;
+					}
+				}
+				Instruction::ShortCircuit { operator, end } =>
+				{
+					let left = pop(&mut numbers) != 0.0;
+					// `and` is decided by a falsy left-hand side, and `or` by a truthy one
+					if left == (*operator == LogicOperator::Or)
+					{
+						numbers.push(truth(left));
+						counter = *end;
+					}
+				}
+			}
+		}
+
+		Ok(pop(&mut numbers))
+	}
+}
+
+fn pop<T>(stack: &mut Vec<T>) -> T
+{
+	stack
+		.pop()
+		.expect("compiled instructions should never pop from an empty stack")
+}
+
+/// Replaces the top two numbers of `numbers` with the result of `op` called with them
+fn arithmetic<F>(numbers: &mut Vec<f64>, op: F)
+where
+	F: FnOnce(f64, f64) -> f64,
+{
+	let right = pop(numbers);
+	let left = pop(numbers);
+	numbers.push(op(left, right));
+}
+
+/// Converts `true` to 1 and `false` to 0, as [`Operand`]'s `From<bool>` does
+fn truth(value: bool) -> f64
+{
+	if value { 1.0 } else { 0.0 }
+}
+
+/// An instruction of a [`CompiledExpression`]. Numbers are pushed to and popped from the number
+/// stack, and rolls and lists from the operand stack
+#[derive(Debug, Clone)]
+enum Instruction
+{
+	Push(f64),
+	/// Fails with an [`UndefinedVariableError`], as compiled expressions have no variables
+	Undefined(Box<str>),
+	/// Fails with an [`UnknownTableError`], as compiled expressions have no tables
+	UnknownTable(Box<str>),
+	Add,
+	Subtract,
+	Multiply,
+	Divide,
+	FloorDivide,
+	Modulo,
+	Power,
+	Negate,
+	Not,
+	/// Replaces a number with 1 if it is truthy, and 0 otherwise
+	Truthy,
+	Compare(CompOperator),
+	/// Pops the count and faces of a dice term, and pushes its roll. If `totalled`, its rolls are
+	/// only totalled (see [`function::totalled_dice`])
+	Dice
+	{
+		totalled: bool,
+	},
+	UnaryDice,
+	/// An element-wise operator, whose left-hand side is an operand and right-hand side a number
+	ElementWise(BinaryOperator),
+	Filter(CompOperator),
+	Modify
+	{
+		modifier: DiceModifier,
+		has_argument: bool,
+	},
+	Reroll
+	{
+		conditions: Box<[(CompOperator, f64)]>,
+		once: bool,
+	},
+	PlusMaximum,
+	/// Moves a number to the operand stack
+	ToOperand,
+	/// Moves an operand to the number stack as its value, replacing a [`Roll`][Operand::Roll]
+	/// with its total
+	Settle,
+	/// Replaces a [`Roll`][Operand::Roll] with the list of its kept values
+	SettleToList,
+	Function
+	{
+		function: ListFunction,
+		arguments: usize,
+	},
+	Index,
+	Jump(usize),
+	/// Pops a number, and jumps if it is not truthy
+	JumpUnless(usize),
+	/// Pops the left-hand side of a logical operator. If it decides the result, pushes the result
+	/// and jumps to `end`, skipping the right-hand side
+	ShortCircuit
+	{
+		operator: LogicOperator,
+		end: usize,
+	},
+}
+impl Instruction
+{
+	/// Returns the instruction for the arithmetic operator `token`
+	fn arithmetic(token: OpToken) -> Self
+	{
+		match token
+		{
+			OpToken::Plus => Self::Add,
+			OpToken::Minus => Self::Subtract,
+			OpToken::Multiply => Self::Multiply,
+			OpToken::Divide => Self::Divide,
+			OpToken::FloorDivide => Self::FloorDivide,
+			OpToken::Modulus => Self::Modulo,
+			OpToken::Power => Self::Power,
+			token => unreachable!("`{token}` is not an arithmetic operator"),
+		}
+	}
+
+	/// Returns how many more numbers and operands are on their stacks after the instruction than
+	/// before it, when it does not jump
+	fn stack_effect(&self) -> (isize, isize)
+	{
+		match self
+		{
+			Self::Push(_) | Self::Undefined(_) | Self::UnknownTable(_) => (1, 0),
+			Self::Add
+			| Self::Subtract
+			| Self::Multiply
+			| Self::Divide
+			| Self::FloorDivide
+			| Self::Modulo
+			| Self::Power
+			| Self::Compare(_) => (-1, 0),
+			Self::Negate
+			| Self::Not
+			| Self::Truthy
+			| Self::Reroll { .. }
+			| Self::PlusMaximum
+			| Self::SettleToList
+			| Self::Jump(_) => (0, 0),
+			Self::Dice { .. } => (-2, 1),
+			Self::UnaryDice | Self::ElementWise(_) | Self::Filter(_) | Self::ToOperand => (-1, 1),
+			Self::Modify { has_argument, .. } => (-isize::from(*has_argument), 0),
+			Self::Settle => (1, -1),
+			Self::Function { arguments, .. } => (0, 1 - *arguments as isize),
+			Self::Index => (0, -1),
+			Self::JumpUnless(_) | Self::ShortCircuit { .. } => (-1, 0),
+		}
+	}
+}
+
+/// Which stack a compiled node leaves its result on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind
+{
+	Number,
+	Operand,
+}
+impl Kind
+{
+	/// Returns the stack `node` leaves its result on when compiled
+	fn of(node: &Node) -> Self
+	{
+		match node
+		{
+			Node::Binary { operator, .. }
+				if operator.token == OpToken::Dice || operator.token.is_element_wise() =>
+			{
+				Self::Operand
+			}
+			Node::Unary { operator, .. } if operator.token == UnaryOpToken::Dice => Self::Operand,
+			Node::ComparisonTernary { .. }
+			| Node::Modifier { .. }
+			| Node::Reroll { .. }
+			| Node::PlusMaximum { .. }
+			| Node::Function { .. } => Self::Operand,
+			// both branches are left on the same stack, so that the code after them does not
+			// depend on which was taken
+			Node::Conditional {
+				if_true, if_false, ..
+			} => match (Self::of(if_true), Self::of(if_false))
+			{
+				(Self::Number, Self::Number) => Self::Number,
+				_ => Self::Operand,
+			},
+			Node::Tagged { node, .. } => Self::of(node),
+			Node::Binary { .. }
+			| Node::Unary { .. }
+			| Node::Comparison { .. }
+			| Node::Logic { .. }
+			| Node::Index { .. }
+			| Node::Variable { .. }
+			| Node::Table { .. }
+			| Node::Leaf(_) => Self::Number,
+		}
+	}
+}
+
+/// The depth of one of the stacks while compiling, and the deepest it has been
+#[derive(Debug, Default)]
+struct Depth
+{
+	current: usize,
+	max: usize,
+}
+impl Depth
+{
+	fn change(&mut self, by: isize)
+	{
+		self.current = self
+			.current
+			.checked_add_signed(by)
+			.expect("compiled instructions should never pop from an empty stack");
+		self.max = self.max.max(self.current);
+	}
+}
+
+/// Lowers a parse tree into [`Instruction`]s in the order the tree evaluator would evaluate it,
+/// settling the same operands, so that both roll the same dice in the same order
+#[derive(Default)]
+struct Compiler
+{
+	instructions: Vec<Instruction>,
+	numbers: Depth,
+	operands: Depth,
+}
+impl Compiler
+{
+	fn emit(&mut self, instruction: Instruction)
+	{
+		let (numbers, operands) = instruction.stack_effect();
+		self.numbers.change(numbers);
+		self.operands.change(operands);
+		self.instructions.push(instruction);
+	}
+
+	/// Emits a jump whose target is filled in later by [`patch`][Compiler::patch], and returns its
+	/// index
+	fn emit_jump(&mut self, instruction: Instruction) -> usize
+	{
+		self.emit(instruction);
+		self.instructions.len() - 1
+	}
+
+	/// Points the jump at `jump` to the next instruction emitted
+	fn patch(&mut self, jump: usize)
+	{
+		let next = self.instructions.len();
+		match &mut self.instructions[jump]
+		{
+			Instruction::Jump(target)
+			| Instruction::JumpUnless(target)
+			| Instruction::ShortCircuit { end: target, .. } => *target = next,
+			_ => unreachable!("only jumps are patched"),
+		}
+	}
+
+	/// Lowers `node` so that it leaves its result on the stack of `kind`
+	fn node_as(&mut self, node: &Node, totalled: bool, kind: Kind)
+	{
+		self.node(node, totalled);
+		match (Kind::of(node), kind)
+		{
+			(Kind::Number, Kind::Operand) => self.emit(Instruction::ToOperand),
+			(Kind::Operand, Kind::Number) => self.emit(Instruction::Settle),
+			_ => (),
+		}
+	}
+
+	/// Lowers `node` so that it leaves its value on the number stack
+	fn settled(&mut self, node: &Node)
+	{
+		self.node_as(node, true, Kind::Number);
+	}
+
+	/// Lowers `node` so that it leaves its result on the operand stack
+	fn operand(&mut self, node: &Node)
+	{
+		self.node_as(node, false, Kind::Operand);
+	}
+
+	/// Lowers `node`, which leaves its result on the stack of [`Kind::of`] it
+	fn node(&mut self, node: &Node, totalled: bool)
+	{
+		match node
+		{
+			Node::Leaf(value) => self.emit(Instruction::Push(*value)),
+			Node::Variable { name, .. } => self.emit(Instruction::Undefined(name.clone())),
+			Node::Table { name, .. } => self.emit(Instruction::UnknownTable(name.clone())),
+			Node::Binary {
+				operator,
+				left,
+				right,
+				..
+			} if operator.token == OpToken::Dice =>
+			{
+				self.settled(left);
+				self.settled(right);
+				self.emit(Instruction::Dice { totalled });
+			}
+			Node::Binary {
+				operator,
+				left,
+				right,
+				..
+			} if operator.token.is_element_wise() =>
+			{
+				self.operand(left);
+				self.settled(right);
+				self.emit(Instruction::ElementWise(*operator));
+			}
+			Node::Binary { .. } | Node::Comparison { .. } | Node::Logic { .. } => self.chain(node),
+			Node::Unary {
+				operator, argument, ..
+			} =>
+			{
+				self.settled(argument);
+				match operator.token
+				{
+					// the argument is already a number
+					UnaryOpToken::Plus => (),
+					UnaryOpToken::Minus => self.emit(Instruction::Negate),
+					UnaryOpToken::Not => self.emit(Instruction::Not),
+					UnaryOpToken::Dice => self.emit(Instruction::UnaryDice),
+				}
+			}
+			Node::ComparisonTernary {
+				comp_op,
+				dice,
+				compare_to,
+				..
+			} =>
+			{
+				self.operand(dice);
+				self.settled(compare_to);
+				self.emit(Instruction::Filter(*comp_op));
+			}
+			Node::Modifier {
+				modifier,
+				dice,
+				argument,
+				..
+			} =>
+			{
+				self.operand(dice);
+				if let Some(argument) = argument
+				{
+					self.settled(argument);
+				}
+				self.emit(Instruction::Modify {
+					modifier: *modifier,
+					has_argument: argument.is_some(),
+				});
+			}
+			Node::Reroll {
+				dice,
+				conditions,
+				once,
+				..
+			} =>
+			{
+				self.operand(dice);
+				self.emit(Instruction::Reroll {
+					conditions: conditions.clone(),
+					once: *once,
+				});
+			}
+			Node::Conditional {
+				condition,
+				if_true,
+				if_false,
+				..
+			} =>
+			{
+				let kind = Kind::of(node);
+				self.settled(condition);
+				let if_false_start = self.emit_jump(Instruction::JumpUnless(0));
+				self.node_as(if_true, totalled, kind);
+				let end = self.emit_jump(Instruction::Jump(0));
+				// only one branch is taken, so the other starts from the same depth
+				match kind
+				{
+					Kind::Number => self.numbers.change(-1),
+					Kind::Operand => self.operands.change(-1),
+				}
+				self.patch(if_false_start);
+				self.node_as(if_false, totalled, kind);
+				self.patch(end);
+			}
+			Node::Function {
+				function,
+				arguments,
+				..
+			} =>
+			{
+				for argument in arguments
+				{
+					self.operand(argument);
+					self.emit(Instruction::SettleToList);
+				}
+				self.emit(Instruction::Function {
+					function: *function,
+					arguments: arguments.len(),
+				});
+			}
+			Node::Index { list, index, .. } =>
+			{
+				self.operand(list);
+				self.settled(index);
+				self.emit(Instruction::Index);
+			}
+			Node::PlusMaximum { dice, .. } =>
+			{
+				self.operand(dice);
+				self.emit(Instruction::PlusMaximum);
+			}
+			// tags only mark roll groups, which compiled expressions do not record
+			Node::Tagged { node, .. } => self.node(node, totalled),
+		}
+	}
+
+	/// Lowers `node` along with the operations down its left-hand side, which the tree evaluator
+	/// evaluates as one chain (see [`evaluation::chain_left`]). Chains can be arbitrarily long, so
+	/// they are lowered in a loop
+	fn chain(&mut self, node: &Node)
+	{
+		let mut chain = Vec::new();
+		let mut first = node;
+		while let Some(left) = evaluation::chain_left(first)
+		{
+			chain.push(first);
+			first = left;
+		}
+
+		// every link leaves a number, which is the settled left-hand side of the next
+		self.settled(first);
+		for link in chain.into_iter().rev()
+		{
+			match link
+			{
+				Node::Binary {
+					operator, right, ..
+				} =>
+				{
+					self.settled(right);
+					self.emit(Instruction::arithmetic(operator.token));
+				}
+				Node::Comparison { comp_op, right, .. } =>
+				{
+					self.settled(right);
+					self.emit(Instruction::Compare(*comp_op));
+				}
+				Node::Logic {
+					operator, right, ..
+				} =>
+				{
+					let end = self.emit_jump(Instruction::ShortCircuit {
+						operator: *operator,
+						end: 0,
+					});
+					self.settled(right);
+					self.emit(Instruction::Truthy);
+					self.patch(end);
+				}
+				_ => unreachable!("every link of a chain should be an operation with a left-hand side"),
+			}
+		}
+	}
+}
+
+
+#[cfg(test)]
+mod tests
+{
+	use crate::{error::ParsingError, Expression};
+	use alloc::{format, string::String};
+	use rand::{rngs::StdRng, Rng, SeedableRng};
+
+	/// Generates a random expression of at most `depth` levels, covering every kind of node. Dice
+	/// always have at least one face, as rolling a die with no faces is an error
+	fn random_expression(rng: &mut StdRng, depth: u32) -> String
+	{
+		let dice = |rng: &mut StdRng| format!("{}d{}", rng.gen_range(0..6), rng.gen_range(1..10));
+		if depth == 0
+		{
+			return match rng.gen_range(0..4)
+			{
+				0 => dice(rng),
+				1 => format!("d{}", rng.gen_range(1..20)),
+				2 => String::from(if rng.gen_bool(0.9) { "200d6" } else { "bonus" }),
+				_ => format!("{}", rng.gen_range(-3..10)),
+			};
+		}
+
+		let mut inner = || random_expression(rng, depth - 1);
+		let (a, b, c) = (inner(), inner(), inner());
+		match rng.gen_range(0..16)
+		{
+			0 => format!("({a}) + ({b})"),
+			1 => format!("({a}) - ({b}) * ({c})"),
+			2 => format!("({a}) / ({b}) % ({c})"),
+			3 => format!("({a}) ^ 2"),
+			4 => format!("(({a}) % 8)d(1d{})", rng.gen_range(1..10)),
+			5 => format!("-({a})"),
+			6 => format!("{} .+ ({a}) ./ ({b})", dice(rng)),
+			7 => format!("{} kh({a})", dice(rng)),
+			8 => format!("{}adv > ({a})", dice(rng)),
+			9 => format!("({a}) >= ({b})"),
+			10 => format!("({a}) and ({b}) or not ({c})"),
+			11 => format!("if({a}, {b}, {c})"),
+			12 => format!("sum({a}) + count({b})"),
+			13 => format!("highest(sort({a}), {b})"),
+			14 => format!("sort({a})[{b}]"),
+			_ => format!("({a}) + d(2d{})", rng.gen_range(1..4)),
+		}
+	}
+
+	/// Compares the compiled and tree evaluators on random expressions, each evaluated with the
+	/// same seed. Either both succeed with the same value and leave the random number generator in
+	/// the same state, or both fail with the same error
+	#[test]
+	fn differential()
+	{
+		let mut generator = StdRng::seed_from_u64(42);
+		let mut compared = 0;
+		for _ in 0..3000
+		{
+			let depth = generator.gen_range(0..4);
+			let input = random_expression(&mut generator, depth);
+			// a low threshold, so that pools are both sampled and rolled
+			let Ok(expression) =
+				Expression::parse(&input).map(|it| it.with_large_pool_threshold(100))
+			else
+			{
+				continue;
+			};
+			let compiled = expression.compile();
+
+			for seed in 0..3
+			{
+				let (mut tree_rng, mut compiled_rng) =
+					(StdRng::seed_from_u64(seed), StdRng::seed_from_u64(seed));
+				let tree = expression.eval_value_with_rand(&mut tree_rng);
+				let bytecode = compiled.eval_value_with_rand(&mut compiled_rng);
+				match (tree, bytecode)
+				{
+					(Ok(tree), Ok(bytecode)) =>
+					{
+						let same = tree.to_bits() == bytecode.to_bits()
+							|| (tree.is_nan() && bytecode.is_nan());
+						assert!(same, "`{input}` with seed {seed}: {tree} != {bytecode}");
+						assert_eq!(tree_rng.gen::<u64>(), compiled_rng.gen::<u64>(), "`{input}`");
+					}
+					(Err(tree), Err(bytecode)) => assert_eq!(
+						format!("{tree}"),
+						format!("{bytecode}"),
+						"`{input}` with seed {seed}"
+					),
+					(tree, bytecode) =>
+					{
+						panic!("`{input}` with seed {seed}: {tree:?} != {bytecode:?}")
+					}
+				}
+			}
+			compared += 1;
+		}
+		// most generated expressions should parse, or the comparison is not worth much
+		assert!(compared > 2000, "only {compared} expressions parsed");
+	}
+
+	#[test]
+	fn undefined_variable()
+	{
+		let compiled = Expression::parse("if(0, bonus, 2)").unwrap().compile();
+		assert_eq!(compiled.eval_value_with_rand(&mut StdRng::seed_from_u64(0)).unwrap(), 2.0);
+
+		let compiled = Expression::parse("1 + bonus").unwrap().compile();
+		assert!(matches!(
+			compiled.eval_value_with_rand(&mut StdRng::seed_from_u64(0)),
+			Err(ParsingError::UndefinedVariable(_))
+		));
+	}
+}
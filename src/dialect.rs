@@ -0,0 +1,357 @@
+use crate::operator::{BinaryOperator, BindingPower, OpToken, UnaryOpToken, UnaryOperator};
+use alloc::boxed::Box;
+use core::str::FromStr;
+
+/// The number of [`OpToken`] variants, for indexing by [`OpToken`]
+const OP_TOKENS: usize = OpToken::FloorDivide as usize + 1;
+/// The number of [`UnaryOpToken`] variants, for indexing by [`UnaryOpToken`]
+const UNARY_OP_TOKENS: usize = UnaryOpToken::Not as usize + 1;
+
+/// A set of syntax rules which the tokenizer and parser follow. Different communities expect
+/// different things from dice notation, so a [`Dialect`] decides which operators are enabled and
+/// how tightly they bind, whether juxtaposition multiplies (eg. `2(1d6)`), whether `D` is a dice
+/// operator as well as `d`, what a comparison directly after a dice term means, and whether dice
+/// such as `D66` are read as digits
+///
+/// [`Dialect::SAIKORO`] is the default, and is what every parsing function uses unless given
+/// another [`Dialect`]
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// use saikoro::{Dialect, Expression};
+///
+/// // unary minus binds more tightly than `^` by default, but not in conventional mathematics
+/// assert_eq!(Expression::parse("-2^2")?.evaluate()?.value, 4.0);
+/// let expression = Expression::parse_with_dialect("-2^2", Dialect::MATHEMATICAL)?;
+/// assert_eq!(expression.evaluate()?.value, -4.0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Dialect
+{
+	/// Overridden binding powers of binary operators, indexed by [`OpToken`]
+	binary_binding_powers: [Option<BindingPower>; OP_TOKENS],
+	/// Overridden binding powers of unary operators, indexed by [`UnaryOpToken`]
+	unary_binding_powers: [Option<u8>; UNARY_OP_TOKENS],
+	/// Whether each [`OpToken`] is disabled, as a bit set indexed by [`OpToken`]
+	disabled_operators: u16,
+	implicit_multiplication: bool,
+	case_sensitive_dice: bool,
+	comparisons: ComparisonMode,
+	digit_dice: bool,
+	floor_division: bool,
+}
+impl Dialect
+{
+	/// The default syntax, as described in the README
+	pub const SAIKORO: Self = Self {
+		binary_binding_powers: [None; OP_TOKENS],
+		unary_binding_powers: [None; UNARY_OP_TOKENS],
+		disabled_operators: 0,
+		implicit_multiplication: true,
+		case_sensitive_dice: false,
+		comparisons: ComparisonMode::Filter,
+		digit_dice: false,
+		floor_division: false,
+	};
+
+	/// Syntax closer to conventional mathematical notation. Unary `+` and `-` bind less tightly
+	/// than `^` (so `-2^2` is -4), juxtaposition does not multiply, only a lowercase `d` is a dice
+	/// operator, and every comparison compares two values (so `3d6 > 10` is 1 or 0)
+	pub const MATHEMATICAL: Self = Self::SAIKORO
+		.with_unary_binding_power(UnaryOpToken::Plus, 13)
+		.with_unary_binding_power(UnaryOpToken::Minus, 13)
+		.with_implicit_multiplication(false)
+		.with_case_sensitive_dice(true)
+		.with_comparisons(ComparisonMode::Boolean);
+
+	/// Syntax following [BCDice](https://bcdice.org), which Japanese TRPG communities expect. A
+	/// comparison judges the total (so `2D6+3>=10` is 1 or 0), `D66` reads two six-sided dice as
+	/// the tens and units digits, `/` rounds its quotient down (so `7/2` is 3), and juxtaposition
+	/// does not multiply. BCDice's result output is produced by [`bcdice`][crate::bcdice].
+	pub const BCDICE: Self = Self::SAIKORO
+		.with_implicit_multiplication(false)
+		.with_comparisons(ComparisonMode::Boolean)
+		.with_digit_dice(true)
+		.with_floor_division(true);
+
+	/// Returns a copy of the dialect with the binary operator `token` binding with `power`
+	/// instead of its usual binding power. A higher binding power binds more tightly, and an
+	/// operator whose right binding power is lower than its left is right-associative (eg. `^`)
+	#[must_use]
+	pub const fn with_binding_power(self, token: OpToken, power: BindingPower) -> Self
+	{
+		let mut dialect = self;
+		dialect.binary_binding_powers[token as usize] = Some(power);
+		dialect
+	}
+
+	/// Returns a copy of the dialect with the unary operator `token` binding with `power`
+	/// instead of its usual binding power
+	#[must_use]
+	pub const fn with_unary_binding_power(self, token: UnaryOpToken, power: u8) -> Self
+	{
+		let mut dialect = self;
+		dialect.unary_binding_powers[token as usize] = Some(power);
+		dialect
+	}
+
+	/// Returns a copy of the dialect in which `token` is not an operator, in either its binary or
+	/// unary form. Expressions using it fail to parse with a
+	/// [`DisabledOperatorError`][crate::error::DisabledOperatorError]
+	#[must_use]
+	pub const fn without_operator(self, token: OpToken) -> Self
+	{
+		Self {
+			disabled_operators: self.disabled_operators | (1 << token as usize),
+			..self
+		}
+	}
+
+	/// Returns a copy of the dialect in which an opening parenthesis directly after an expression
+	/// multiplies them (eg. `2(1d6)`) if `enabled`, and is an error otherwise
+	#[must_use]
+	pub const fn with_implicit_multiplication(self, enabled: bool) -> Self
+	{
+		Self {
+			implicit_multiplication: enabled,
+			..self
+		}
+	}
+
+	/// Returns a copy of the dialect in which only a lowercase `d` is a dice operator if
+	/// `case_sensitive`, and otherwise `D` is one as well
+	#[must_use]
+	pub const fn with_case_sensitive_dice(self, case_sensitive: bool) -> Self
+	{
+		Self {
+			case_sensitive_dice: case_sensitive,
+			..self
+		}
+	}
+
+	/// Returns a copy of the dialect with the given meaning for comparisons (see
+	/// [`ComparisonMode`])
+	#[must_use]
+	pub const fn with_comparisons(self, comparisons: ComparisonMode) -> Self
+	{
+		Self {
+			comparisons,
+			..self
+		}
+	}
+
+	/// Returns a copy of the dialect in which a dice term with no count whose faces are a digit
+	/// repeated (eg. `D66` or `D666`) rolls one die per digit, each with that many faces, and reads
+	/// them as the digits of one number if `enabled` (see
+	/// [`RollGroup::is_concatenated`][crate::evaluation::RollGroup::is_concatenated]). The digits
+	/// can be followed by the order they are read in: `A` or `S` for the lowest die first, `D` for
+	/// the highest die first, or `N` for the order they were rolled in (eg. `D66S`). Otherwise,
+	/// `D66` is a single die with 66 faces
+	#[must_use]
+	pub const fn with_digit_dice(self, enabled: bool) -> Self
+	{
+		Self {
+			digit_dice: enabled,
+			..self
+		}
+	}
+
+	/// Returns a copy of the dialect in which `/` rounds its quotient down to a whole number if
+	/// `enabled` (see [`OpToken::FloorDivide`]), rather than dividing exactly. `./` still divides
+	/// each roll exactly
+	#[must_use]
+	pub const fn with_floor_division(self, enabled: bool) -> Self
+	{
+		Self {
+			floor_division: enabled,
+			..self
+		}
+	}
+
+	/// Returns the binding power of the binary operator `token` in the dialect
+	pub fn binding_power(&self, token: OpToken) -> BindingPower
+	{
+		// floor division is written `/`, so it binds as `/` does
+		let token = match token
+		{
+			OpToken::FloorDivide => OpToken::Divide,
+			token => token,
+		};
+		self.binary_binding_powers[token as usize]
+			.unwrap_or_else(|| BinaryOperator::from(token).binding_power)
+	}
+
+	/// Returns the binding power of the unary operator `token` in the dialect
+	pub fn unary_binding_power(&self, token: UnaryOpToken) -> u8
+	{
+		self.unary_binding_powers[token as usize]
+			.unwrap_or_else(|| UnaryOperator::from(token).binding_power)
+	}
+
+	/// Returns whether `token` is an operator in the dialect
+	pub const fn allows(&self, token: OpToken) -> bool
+	{
+		self.disabled_operators & (1 << token as usize) == 0
+	}
+
+	/// Returns whether an opening parenthesis directly after an expression multiplies them
+	pub const fn implicit_multiplication(&self) -> bool
+	{
+		self.implicit_multiplication
+	}
+
+	/// Returns whether `word` is a dice operator in the dialect
+	pub fn is_dice_word(&self, word: &str) -> bool
+	{
+		word == "d" || (!self.case_sensitive_dice && word == "D")
+	}
+
+	/// Returns the meaning of comparisons in the dialect
+	pub const fn comparisons(&self) -> ComparisonMode
+	{
+		self.comparisons
+	}
+
+	/// Returns whether dice terms such as `D66` read their dice as digits
+	pub const fn digit_dice(&self) -> bool
+	{
+		self.digit_dice
+	}
+
+	/// Returns whether `/` rounds its quotient down
+	pub const fn floor_division(&self) -> bool
+	{
+		self.floor_division
+	}
+}
+impl FromStr for Dialect
+{
+	type Err = ParseDialectError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		match s
+		{
+			"saikoro" => Ok(Self::SAIKORO),
+			"mathematical" | "math" => Ok(Self::MATHEMATICAL),
+			"bcdice" => Ok(Self::BCDICE),
+			_ => Err(ParseDialectError(s.into())),
+		}
+	}
+}
+impl Default for Dialect
+{
+	fn default() -> Self
+	{
+		Self::SAIKORO
+	}
+}
+
+/// An error to be returned when failing to parse a [`Dialect`] from its name
+#[derive(Debug, thiserror::Error)]
+#[error("Unrecognized dialect: {}", .0)]
+pub struct ParseDialectError(Box<str>);
+
+/// What a comparison operator directly following a dice term means in a [`Dialect`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ComparisonMode
+{
+	/// A comparison directly following a dice term filters its rolls, removing those which do not
+	/// satisfy it (eg. `4d6 > 2`), and any other comparison compares two values
+	Filter,
+	/// Every comparison compares two values, producing 1 or 0 (eg. `3d6 > 10`)
+	Boolean,
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::{error::ParsingError, Expression};
+
+	fn value(input: &str, dialect: Dialect) -> f64
+	{
+		Expression::parse_with_dialect(input, dialect)
+			.unwrap()
+			.eval_with_rand(&mut crate::test_helpers::RiggedRandom::new([1, 5, 6]))
+			.unwrap()
+			.value
+	}
+
+	#[test]
+	fn unary_precedence()
+	{
+		assert_eq!(value("-2^2", Dialect::SAIKORO), 4.0);
+		assert_eq!(value("-2^2", Dialect::MATHEMATICAL), -4.0);
+		assert_eq!(value("2^-1", Dialect::MATHEMATICAL), 0.5);
+		assert_eq!(value("-2 * 3", Dialect::MATHEMATICAL), -6.0);
+	}
+
+	#[test]
+	fn binding_powers()
+	{
+		// `+` binding more tightly than `*`
+		let dialect = Dialect::SAIKORO.with_binding_power(OpToken::Plus, BindingPower::new(13, 14));
+		assert_eq!(value("2 * 3 + 4", dialect), 14.0);
+		assert_eq!(value("2 * 3 + 4", Dialect::SAIKORO), 10.0);
+	}
+
+	#[test]
+	fn disabled_operators()
+	{
+		let dialect = Dialect::SAIKORO.without_operator(OpToken::Modulus);
+		assert!(!dialect.allows(OpToken::Modulus));
+		assert!(dialect.allows(OpToken::Plus));
+		assert!(matches!(
+			Expression::parse_with_dialect("5 % 2", dialect),
+			Err(ParsingError::DisabledOperator(_))
+		));
+
+		let dialect = Dialect::SAIKORO.without_operator(OpToken::Minus);
+		assert!(matches!(
+			Expression::parse_with_dialect("-2", dialect),
+			Err(ParsingError::DisabledOperator(_))
+		));
+	}
+
+	#[test]
+	fn implicit_multiplication()
+	{
+		assert_eq!(value("2(3)", Dialect::SAIKORO), 6.0);
+		assert_eq!(value("2(3)+1", Dialect::SAIKORO), 7.0);
+		assert_eq!(value("2(3)^2", Dialect::SAIKORO), 18.0);
+		assert_eq!(value("1 + 2(3)(4) - 1", Dialect::SAIKORO), 24.0);
+		assert!(matches!(
+			Expression::parse_with_dialect("2(3)", Dialect::MATHEMATICAL),
+			Err(ParsingError::Tokenization(_))
+		));
+	}
+
+	#[test]
+	fn case_sensitive_dice()
+	{
+		assert_eq!(value("3D6", Dialect::SAIKORO), 12.0);
+		assert_eq!(value("3d6", Dialect::MATHEMATICAL), 12.0);
+		assert!(Expression::parse_with_dialect("3D6", Dialect::MATHEMATICAL).is_err());
+	}
+
+	#[test]
+	fn comparisons()
+	{
+		// rolls of 1, 5, and 6, where the 1 is filtered out
+		assert_eq!(value("3d6 > 2", Dialect::SAIKORO), 11.0);
+		assert_eq!(value("3d6 > 2", Dialect::MATHEMATICAL), 1.0);
+		assert_eq!(value("3d6 > 12", Dialect::MATHEMATICAL), 0.0);
+		assert_eq!(value("3d6kh2 >= 11", Dialect::MATHEMATICAL), 1.0);
+	}
+
+	#[test]
+	fn names()
+	{
+		assert_eq!("bcdice".parse::<Dialect>().unwrap(), Dialect::BCDICE);
+		assert_eq!("math".parse::<Dialect>().unwrap(), Dialect::MATHEMATICAL);
+		assert!("roll20".parse::<Dialect>().is_err());
+	}
+}
@@ -1,10 +1,14 @@
 use super::TokenizationError;
-use crate::operator::{CompOperator, OpToken, UnaryDirection, UnaryOperator};
+use crate::operator::{
+	CompOperator, DiceModifier, ListFunction, OpToken, UnaryDirection, UnaryOperator,
+};
+use alloc::{boxed::Box, format, string::String, string::ToString};
+use core::ops::RangeInclusive;
 use thiserror::Error;
 
 /// An error representing any error that can occur while parsing a dice string (including any
 /// errors which can occur during tokenization)
-#[derive(Debug, Error, Clone, Copy)]
+#[derive(Debug, Error, Clone)]
 pub enum ParsingError
 {
 	#[error("{}", .0)]
@@ -17,6 +21,44 @@ pub enum ParsingError
 	UnmatchedCloseDelimiter(#[from] UnmatchedCloseDelimiterError),
 	#[error("{}", .0)]
 	UnmatchedComparison(#[from] UnmatchedComparisonError),
+	#[error("{}", .0)]
+	UnmatchedModifier(#[from] UnmatchedModifierError),
+	#[error("{}", .0)]
+	InvalidRepetitionCount(#[from] InvalidRepetitionCountError),
+	#[error("{}", .0)]
+	UndefinedVariable(#[from] UndefinedVariableError),
+	#[error("{}", .0)]
+	ReservedLabel(#[from] ReservedLabelError),
+	#[error("{}", .0)]
+	UnknownFunction(#[from] UnknownFunctionError),
+	#[error("{}", .0)]
+	ArgumentCount(#[from] ArgumentCountError),
+	#[error("{}", .0)]
+	IndexOutOfRange(#[from] IndexOutOfRangeError),
+	#[error("{}", .0)]
+	InvalidValueCount(#[from] InvalidValueCountError),
+	#[error("{}", .0)]
+	InvalidFaces(#[from] InvalidFacesError),
+	#[error("{}", .0)]
+	InvalidDigit(#[from] InvalidDigitError),
+	#[error("{}", .0)]
+	NestingDepth(#[from] NestingDepthError),
+	#[error("{}", .0)]
+	DisabledOperator(#[from] DisabledOperatorError),
+	#[error("{}", .0)]
+	ExpectedSyntax(#[from] ExpectedSyntaxError),
+	#[error("{}", .0)]
+	UnsupportedSyntax(#[from] UnsupportedSyntaxError),
+	#[error("{}", .0)]
+	UnresolvedReference(#[from] UnresolvedReferenceError),
+	#[error("{}", .0)]
+	TableSyntax(#[from] TableSyntaxError),
+	#[error("{}", .0)]
+	UnknownTable(#[from] UnknownTableError),
+	#[error("{}", .0)]
+	MissingTableRow(#[from] MissingTableRowError),
+	#[error("{}", .0)]
+	TableDepth(#[from] TableDepthError),
 }
 
 // this actually shouldn't be possible at the moment? at least not until there's a postfix operator
@@ -48,3 +90,200 @@ pub struct UnmatchedCloseDelimiterError;
 #[derive(Debug, Error, Clone, Copy)]
 #[error("Found comparison operator `{}` without a matching dice operator!", .0)]
 pub struct UnmatchedComparisonError(pub CompOperator);
+
+/// An error representing a found dice modifier which does not directly follow a dice operator, or
+/// which follows `cat`, after which the rolls are read as one number rather than as dice (eg. the
+/// `kh1` in `2d6cat kh1`)
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Found dice modifier `{}` without a matching dice operator!", .0)]
+pub struct UnmatchedModifierError(pub DiceModifier);
+
+/// An error representing a repetition count (eg. the `6` in `6x 4d6`) which is not a whole number,
+/// or is more than [`MAX_COUNT`][InvalidRepetitionCountError::MAX_COUNT]
+#[derive(Debug, Error, Clone, Copy)]
+#[error(
+	"Expected a whole number of repetitions from 0 to {} before `x`/`#`!",
+	InvalidRepetitionCountError::MAX_COUNT
+)]
+pub struct InvalidRepetitionCountError;
+impl InvalidRepetitionCountError
+{
+	/// The most times an expression can be repeated, as every repetition is evaluated and kept
+	pub const MAX_COUNT: u32 = 10_000;
+}
+
+/// An error representing a variable which was not defined by the label of an earlier statement
+/// (eg. the `attack` in `damage: 2d6 + attack`, without any statement labelled `attack`). Unlike
+/// other variants of [`ParsingError`], this error occurs during evaluation rather than parsing
+#[derive(Debug, Error, Clone)]
+#[error("Found undefined variable `{}`!", .0)]
+pub struct UndefinedVariableError(pub Box<str>);
+
+/// An error representing a statement label which is read as a keyword rather than a variable (eg.
+/// the `d` in `d: 1d6`), so later statements could never refer to it
+#[derive(Debug, Error, Clone)]
+#[error("Found label `{}`, which is a keyword and cannot be used as a variable!", .0)]
+pub struct ReservedLabelError(pub Box<str>);
+
+/// An error representing a call to a function which does not exist (eg. `foo(1, 2)`)
+#[derive(Debug, Error, Clone)]
+#[error("Found call to unknown function `{}`!", .0)]
+pub struct UnknownFunctionError(pub Box<str>);
+
+/// An error representing a call to a function with the wrong number of arguments (eg. `if(1, 2)`)
+#[derive(Debug, Error, Clone)]
+#[error(
+	"Function `{}` expects {} arguments, found {}!",
+	.function,
+	display_range(.expected),
+	.found
+)]
+pub struct ArgumentCountError
+{
+	pub function: Box<str>,
+	pub expected: RangeInclusive<usize>,
+	pub found: usize,
+}
+fn display_range(range: &RangeInclusive<usize>) -> String
+{
+	if range.start() == range.end()
+	{
+		range.start().to_string()
+	}
+	else
+	{
+		format!("{} to {}", range.start(), range.end())
+	}
+}
+
+/// An error representing an index into a list which is past either end of the list (eg. the `4`
+/// in `(4d6)[4]`). Like [`UndefinedVariableError`], this error occurs during evaluation
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Index {} is out of range for a list of length {}!", .index, .len)]
+pub struct IndexOutOfRangeError
+{
+	pub index: f64,
+	pub len: usize,
+}
+
+/// An error representing a number of values to take from a list which is not a non-negative whole
+/// number (eg. the `-1` in `highest(5d10, -1)`). Like [`IndexOutOfRangeError`], this error occurs
+/// during evaluation
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Expected a whole number of values for `{}`, found {}!", .function, .count)]
+pub struct InvalidValueCountError
+{
+	pub function: ListFunction,
+	pub count: f64,
+}
+
+/// An error representing dice with fewer than one face (eg. the `0` in `1d0`), which cannot be
+/// rolled. Like [`IndexOutOfRangeError`], this error occurs during evaluation
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Expected dice to have at least 1 face, found {}!", .0)]
+pub struct InvalidFacesError(pub f64);
+
+/// An error representing dice whose rolls cannot be read as digits with `cat`, because they have
+/// more than 10 faces (eg. `2d12cat`) or a roll is modified to something other than a whole number
+/// from 0 to 9 (eg. `2d6 .+ 5 cat`). Like [`IndexOutOfRangeError`], this error occurs during
+/// evaluation
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Expected dice read as digits to roll from 0 to 9, found a possible roll of {}!", .0)]
+pub struct InvalidDigitError(pub f64);
+
+/// An error representing an expression which is nested more than
+/// [`MAX_DEPTH`][NestingDepthError::MAX_DEPTH] levels deep, whether by parentheses, function
+/// arguments, brackets, or unary operators (eg. 100,000 `(` characters). Expressions this deep are
+/// rejected while parsing, as parsing or evaluating them could overflow the stack. Chains of
+/// operators (eg. `1 + 1 + 1`) are not nested, and can be any length
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Expression is nested more than {} levels deep!", NestingDepthError::MAX_DEPTH)]
+pub struct NestingDepthError;
+impl NestingDepthError
+{
+	/// The deepest an expression can be nested
+	pub const MAX_DEPTH: usize = 128;
+}
+
+/// An error representing an operator which is not enabled in the [`Dialect`][crate::Dialect] the
+/// expression was parsed with (see [`Dialect::without_operator`][crate::Dialect::without_operator])
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Operator `{}` is not enabled in this dialect!", .0)]
+pub struct DisabledOperatorError(pub OpToken);
+
+/// An error representing input to a front end for another dice roller's syntax (eg.
+/// [`roll20`][crate::roll20]) which does not follow that syntax, such as a missing `]]`
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Expected {} at index {}!", .expected, .char_index)]
+pub struct ExpectedSyntaxError
+{
+	/// A description of what was expected (eg. ``"`]]`"``)
+	pub expected: &'static str,
+	/// The byte index in the input where it was expected
+	pub index: usize,
+	/// The same index counted in [`char`]s, which is the index displayed in the error message
+	pub char_index: usize,
+}
+
+/// An error representing syntax which a front end for another dice roller recognizes, but which
+/// saikoro cannot evaluate (eg. the fate dice in Roll20's `4dF`). These are rejected rather than
+/// evaluated as something else
+#[derive(Debug, Error, Clone)]
+#[error("Found unsupported syntax `{}` at index {}!", .syntax, .char_index)]
+pub struct UnsupportedSyntaxError
+{
+	pub syntax: Box<str>,
+	/// The byte index in the input of the start of the syntax
+	pub index: usize,
+	/// The same index counted in [`char`]s, which is the index displayed in the error message
+	pub char_index: usize,
+}
+
+/// An error representing a reference to an outside value which the caller could not provide (eg.
+/// the `@{str_mod}` in Roll20's `1d20 + @{str_mod}`, for a character without that attribute)
+#[derive(Debug, Error, Clone)]
+#[error("Could not resolve reference `{}`!", .0)]
+pub struct UnresolvedReferenceError(pub Box<str>);
+
+/// An error representing a definition of [`Tables`][crate::table::Tables] which does not follow
+/// their format (eg. a row with an overlapping range, or an unclosed `{` in an entry)
+#[derive(Debug, Error, Clone)]
+#[error("Invalid table definition on line {}: {}!", .line, .reason)]
+pub struct TableSyntaxError
+{
+	/// The line the error was found on, counting from 1
+	pub line: usize,
+	/// A description of what is wrong with the line
+	pub reason: Box<str>,
+}
+
+/// An error representing a roll on a table which does not exist (eg. the `loot` in
+/// `table(loot)`, without any table named `loot`). Like [`UndefinedVariableError`], this error
+/// occurs during evaluation
+#[derive(Debug, Error, Clone)]
+#[error("Found roll on unknown table `{}`!", .0)]
+pub struct UnknownTableError(pub Box<str>);
+
+/// An error representing a roll on a table which selects none of its rows (eg. a roll of 6 on a
+/// table rolled with `2d6kh1` which only has rows for 1 to 5). This error occurs during
+/// evaluation, and only for tables whose rolls cannot be checked against their rows when they are
+/// parsed
+#[derive(Debug, Error, Clone)]
+#[error("Table `{}` has no row for a roll of {}!", .table, .roll)]
+pub struct MissingTableRowError
+{
+	pub table: Box<str>,
+	pub roll: f64,
+}
+
+/// An error representing rolls on tables whose entries roll on other tables more than
+/// [`MAX_DEPTH`][TableDepthError::MAX_DEPTH] levels deep, such as a table with an entry which
+/// always rolls on the same table again. This error occurs during evaluation
+#[derive(Debug, Error, Clone, Copy)]
+#[error("Tables roll on each other more than {} levels deep!", TableDepthError::MAX_DEPTH)]
+pub struct TableDepthError;
+impl TableDepthError
+{
+	/// The deepest table rolls can be nested
+	pub const MAX_DEPTH: usize = 32;
+}
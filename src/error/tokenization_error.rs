@@ -13,11 +13,16 @@ pub enum TokenizationError
 
 /// An error representing an unsupported token
 #[derive(Debug, Error, Clone, Copy)]
-#[error("Found unknown token: '{}' at index {}", .unknown_char, .index)]
+#[error("Found unknown token: '{}' at index {}", .unknown_char, .char_index)]
 pub struct UnknownTokenError
 {
 	pub unknown_char: char,
+	/// The byte index of the unknown character in the input, like the spans of
+	/// [`RollGroup::span`][crate::evaluation::RollGroup::span]
 	pub index: usize,
+	/// The index of the unknown character in the input counted in [`char`]s, which is the index
+	/// displayed in the error message
+	pub char_index: usize,
 }
 
 /// An error representing a token in a position where a different token was expected
@@ -0,0 +1,207 @@
+use super::{DiceEvaluation, RollGroup};
+use alloc::vec::Vec;
+use core::{fmt::Display, ops::Range};
+
+/// A view of a [`DiceEvaluation`] which displays the input expression it was evaluated from, with
+/// the results of each dice term written inline after the term itself, followed by the final total
+/// (eg. `1d20 [17] + 5 = 22`)
+///
+/// Removed rolls are wrapped in `~~`, critical successes (see [`RollGroup::is_critical_success`])
+/// are wrapped in `**`, and critical failures (see [`RollGroup::is_critical_failure`]) are wrapped
+/// in `__`. If anything is tagged, the total is followed by its
+/// [breakdown by tag][DiceEvaluation::breakdown_by_tag] (eg. `2d6[fire] [3, 4] + 2 = 9 (fire: 7,
+/// untagged: 2)`), and the outcome of each roll on a table follows after a `;` (eg. `table(loot) =
+/// 3; loot (3): 11 silver pieces`)
+///
+/// Obtained from [`DiceEvaluation::annotate`]
+#[derive(Debug, Clone, Copy)]
+pub struct AnnotatedEvaluation<'a>
+{
+	evaluation: &'a DiceEvaluation,
+	input: &'a str,
+	start: usize,
+}
+impl DiceEvaluation
+{
+	/// Returns an [`AnnotatedEvaluation`] which displays `input` with the results of each dice term
+	/// written after it. `input` is expected to be the string the [`DiceEvaluation`] was evaluated
+	/// from, and any [`RollGroup`]s whose spans do not fit in `input` are not displayed
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use rand::{rngs::StdRng, SeedableRng};
+	///
+	/// // this seed will generate a 4 and a 5 from the first two rolls
+	/// let input = "2d6 + 3";
+	/// let evaluation = saikoro::eval_with_rand(input, &mut StdRng::seed_from_u64(2024))?;
+	/// assert_eq!(evaluation.annotate(input).to_string(), "2d6 [4, 5] + 3 = 12");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn annotate<'a>(&'a self, input: &'a str) -> AnnotatedEvaluation<'a>
+	{
+		AnnotatedEvaluation {
+			evaluation: self,
+			input,
+			start: 0,
+		}
+	}
+
+	/// Same as [`annotate`][DiceEvaluation::annotate], but only displays the part of `input`
+	/// within `range`. Used where only part of the input is the expression that was evaluated
+	pub(crate) fn annotate_range<'a>(
+		&'a self,
+		input: &'a str,
+		range: Range<usize>,
+	) -> AnnotatedEvaluation<'a>
+	{
+		AnnotatedEvaluation {
+			evaluation: self,
+			input: input.get(..range.end).unwrap_or(input),
+			start: range.start,
+		}
+	}
+}
+impl Display for AnnotatedEvaluation<'_>
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		let mut insertions = self
+			.evaluation
+			.roll_groups
+			.iter()
+			.filter_map(|group| {
+				group
+					.span()
+					.filter(|span| span.start >= self.start && self.input.is_char_boundary(span.end))
+					.map(|span| (span.end, group))
+			})
+			.collect::<Vec<_>>();
+		// stable sort keeps groups ending at the same place in evaluation order
+		insertions.sort_by_key(|(end, _)| *end);
+
+		let mut cursor = if self.input.is_char_boundary(self.start)
+		{
+			self.start
+		}
+		else
+		{
+			0
+		};
+		for (end, group) in insertions
+		{
+			write!(f, "{} ", &self.input[cursor..end])?;
+			write_group(f, group)?;
+			cursor = end;
+		}
+
+		write!(
+			f,
+			"{} = {}",
+			self.input[cursor..].trim_end(),
+			self.evaluation.value
+		)?;
+		self.evaluation.write_breakdown(f)?;
+		self.evaluation.write_table_rolls(f)
+	}
+}
+
+fn write_group(f: &mut core::fmt::Formatter<'_>, group: &RollGroup) -> core::fmt::Result
+{
+	let highlight = if group.is_critical_success()
+	{
+		"**"
+	}
+	else if group.is_critical_failure()
+	{
+		"__"
+	}
+	else
+	{
+		""
+	};
+
+	write!(f, "{highlight}")?;
+	group.write_rolls(f)?;
+	write!(f, "{highlight}")
+}
+
+#[cfg(test)]
+mod tests
+{
+	use crate::{eval_with_rand, test_helpers::RiggedRandom};
+
+	fn annotate(input: &str, rolls: &[u32]) -> String
+	{
+		eval_with_rand(input, &mut RiggedRandom::new(rolls.iter().copied()))
+			.unwrap()
+			.annotate(input)
+			.to_string()
+	}
+
+	#[test]
+	fn simple()
+	{
+		assert_eq!(annotate("1d20 + 5", &[17]), "1d20 [17] + 5 = 22");
+		assert_eq!(annotate("5 + d20", &[17]), "5 + d20 [17] = 22");
+		assert_eq!(annotate("3 * 2", &[]), "3 * 2 = 6");
+	}
+
+	#[test]
+	fn full_width()
+	{
+		assert_eq!(annotate("２Ｄ６＋３", &[4, 5]), "２Ｄ６ [4, 5]＋３ = 12");
+	}
+
+	#[test]
+	fn removed()
+	{
+		assert_eq!(annotate("3d6 > 2", &[1, 5, 3]), "3d6 [~~1~~, 5, 3] > 2 = 8");
+	}
+
+	#[test]
+	fn crits()
+	{
+		assert_eq!(annotate("1d20", &[20]), "1d20 **[20]** = 20");
+		assert_eq!(annotate("2d6+1", &[1, 1]), "2d6 __[1, 1]__+1 = 3");
+		assert_eq!(
+			annotate("1d20adv", &[1, 20]),
+			"1d20adv **[~~1~~, 20]** = 20"
+		);
+	}
+
+	#[test]
+	fn element_wise()
+	{
+		assert_eq!(annotate("2d6 .+ 1", &[3, 5]), "2d6 [3, 5] .+ 1 = 10");
+	}
+
+	#[test]
+	fn sampled()
+	{
+		let evaluation = crate::Expression::parse("3d6 + 1")
+			.unwrap()
+			.with_large_pool_threshold(2)
+			.eval_with_rand(&mut RiggedRandom::new([u32::MAX / 2, 1]))
+			.unwrap();
+		assert_eq!(evaluation.annotate("3d6 + 1").to_string(), "3d6 [Σ 14] + 1 = 15");
+	}
+
+	#[test]
+	fn tags()
+	{
+		assert_eq!(
+			annotate("2d6[fire] + 1d8[radiant] + 3", &[3, 4, 5]),
+			"2d6[fire] [3, 4] + 1d8[radiant] [5] + 3 = 15 (fire: 7, radiant: 5, untagged: 3)"
+		);
+	}
+
+	#[test]
+	fn nested()
+	{
+		assert_eq!(
+			annotate("(1d4)d6", &[2, 3, 5]),
+			"(1d4 [2])d6 [3, 5] = 8"
+		);
+	}
+}
@@ -0,0 +1,117 @@
+use super::DiceEvaluation;
+use alloc::vec::Vec;
+use core::fmt::Display;
+
+/// The part of a dice term which was decided by the result of another dice term
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DicePart
+{
+	/// The number of dice rolled (eg. the `1d4` in `(1d4)d6`)
+	Count,
+	/// The number of faces on each die (eg. the `1d3` in `2d(1d3*2)`)
+	Faces,
+}
+impl Display for DicePart
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		write!(
+			f,
+			"{}",
+			match self
+			{
+				Self::Count => "count",
+				Self::Faces => "faces",
+			}
+		)
+	}
+}
+
+/// A link between two roll groups of a [`DiceEvaluation`], recording that the result of the
+/// `parent` group decided the [`DicePart`] of the `child` group. Both `parent` and `child` are
+/// indices into [`DiceEvaluation::roll_groups`]
+///
+/// Only direct links are recorded, so in `((1d4)d6)d8` the `1d4` is a parent of the `d6` group,
+/// which is in turn a parent of the `d8` group
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RollDependency
+{
+	pub parent: usize,
+	pub child: usize,
+	pub part: DicePart,
+}
+
+impl DiceEvaluation
+{
+	/// Returns the [`RollDependency`]s whose child is the [`RollGroup`][super::RollGroup] at
+	/// `index` in [`roll_groups`][DiceEvaluation::roll_groups] (i.e. the groups which decided how
+	/// it was rolled)
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use saikoro::evaluation::DicePart;
+	///
+	/// let evaluation = saikoro::evaluate("(1d4)d6")?;
+	/// let dependency = evaluation.parents(1).next().unwrap();
+	/// assert_eq!(dependency.parent, 0);
+	/// assert_eq!(dependency.part, DicePart::Count);
+	/// assert_eq!(evaluation.roll_groups[1].len() as f64, evaluation.roll_groups[0].total());
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn parents(&self, index: usize) -> impl Iterator<Item = &RollDependency>
+	{
+		self.dependencies.iter().filter(move |it| it.child == index)
+	}
+
+	/// Returns the [`RollDependency`]s whose parent is the [`RollGroup`][super::RollGroup] at
+	/// `index` in [`roll_groups`][DiceEvaluation::roll_groups] (i.e. the groups it decided how to
+	/// roll)
+	pub fn children(&self, index: usize) -> impl Iterator<Item = &RollDependency>
+	{
+		self.dependencies.iter().filter(move |it| it.parent == index)
+	}
+
+	/// Writes the roll group at `index`, preceded by the groups it depends on (eg.
+	/// `1d4: [3] → 3d6: [2, 5, 6]`)
+	pub(super) fn write_with_parents(
+		&self,
+		f: &mut core::fmt::Formatter<'_>,
+		index: usize,
+	) -> core::fmt::Result
+	{
+		let parents = self.parents(index).map(|it| it.parent).collect::<Vec<_>>();
+		match parents.as_slice()
+		{
+			[] => (),
+			[parent] =>
+			{
+				self.write_with_parents(f, *parent)?;
+				write!(f, " → ")?;
+			}
+			parents =>
+			{
+				write!(f, "(")?;
+				for (i, parent) in parents.iter().enumerate()
+				{
+					if i > 0
+					{
+						write!(f, ", ")?;
+					}
+					self.write_with_parents(f, *parent)?;
+				}
+				write!(f, ") → ")?;
+			}
+		}
+
+		write!(f, "{}", self.roll_groups[index])
+	}
+
+	/// Returns the indices of the roll groups which no other group depends on, in the order they
+	/// were rolled
+	pub(super) fn final_groups(&self) -> impl Iterator<Item = usize> + '_
+	{
+		(0..self.roll_groups.len()).filter(|index| self.children(*index).next().is_none())
+	}
+}
+
@@ -0,0 +1,57 @@
+use super::{AnnotatedEvaluation, RepeatedEvaluation};
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::fmt::Display;
+
+/// The results of evaluating one statement of a [`Script`][crate::Script], along with the label
+/// it was given, if any (eg. `attack` for `attack: 1d20+7`)
+#[derive(Debug)]
+pub struct LabelledEvaluation
+{
+	pub label: Option<Box<str>>,
+	pub evaluations: RepeatedEvaluation,
+}
+impl LabelledEvaluation
+{
+	/// Returns the value of the statement, which is the value later statements refer to by its
+	/// label. For statements with a repetition count, this is the sum of every repetition
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let results = saikoro::evaluate_script("attack: 3x 5; damage: attack + 1")?;
+	/// assert_eq!(results[0].value(), 15.0);
+	/// assert_eq!(results[1].value(), 16.0);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn value(&self) -> f64
+	{
+		self.evaluations.total()
+	}
+
+	/// Returns an iterator of [`AnnotatedEvaluation`]s for each repetition of the statement (see
+	/// [`RepeatedEvaluation::annotate`]). Neither the label nor the repetition count is displayed
+	pub fn annotate<'a>(&'a self, input: &'a str)
+		-> impl Iterator<Item = AnnotatedEvaluation<'a>> + 'a
+	{
+		self.evaluations.annotate(input)
+	}
+}
+impl Display for LabelledEvaluation
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		let prefix = self
+			.label
+			.as_ref()
+			.map_or_else(String::new, |label| format!("{label}: "));
+		write!(
+			f,
+			"{}",
+			self.evaluations
+				.iter()
+				.map(|it| format!("{prefix}{it}"))
+				.collect::<Vec<_>>()
+				.join("\n")
+		)
+	}
+}
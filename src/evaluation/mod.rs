@@ -1,107 +1,707 @@
 //! Types used in the evaluation of dice expressions
 
+mod annotation;
+mod dependency;
+mod labelled;
 mod operand;
+mod repeated;
 mod roll_types;
 
+pub use annotation::*;
+pub use dependency::*;
+pub use labelled::*;
 pub use operand::*;
+pub use repeated::*;
 pub use roll_types::*;
 
-use crate::{error::ParsingError, parsing::Node, RangeRng};
-use std::{collections::HashMap, hash::Hash};
+use crate::{
+	error::{IndexOutOfRangeError, ParsingError, UndefinedVariableError, UnknownTableError},
+	math,
+	operator::{function, DiceModifier, LogicOperator, OpToken, UnaryOpToken},
+	parsing::Node,
+	table::{TableRoll, Tables},
+	RangeRng,
+};
+use alloc::{boxed::Box, collections::BTreeMap, vec::Vec};
+use core::ops::Range;
+
+/// The values of labelled statements, which later statements can refer to by label
+pub(crate) type Variables = BTreeMap<Box<str>, f64>;
+
+/// What the names in an expression refer to while it is evaluated
+#[derive(Clone, Copy)]
+pub(crate) struct Scope<'a>
+{
+	pub variables: &'a Variables,
+	/// The tables which `table(name)` can roll on, if any
+	pub tables: Option<&'a Tables>,
+	/// The number of table rolls the expression being evaluated is nested within (see
+	/// [`TableDepthError`][crate::error::TableDepthError])
+	pub table_depth: usize,
+}
+impl<'a> Scope<'a>
+{
+	pub const EMPTY: Scope<'static> = {
+		const NO_VARIABLES: &Variables = &Variables::new();
+		Scope::new(NO_VARIABLES)
+	};
+
+	pub const fn new(variables: &'a Variables) -> Self
+	{
+		Self {
+			variables,
+			tables: None,
+			table_depth: 0,
+		}
+	}
+
+	pub const fn with_tables(self, tables: &'a Tables) -> Self
+	{
+		Self {
+			tables: Some(tables),
+			..self
+		}
+	}
+}
 
 pub(super) fn evaluate_tree<R>(
-	parse_tree: Node,
+	parse_tree: &Node,
 	rng: &mut R,
+	scope: &Scope,
+	large_pool_threshold: u32,
 ) -> Result<DiceEvaluation, ParsingError>
 where
 	R: RangeRng,
 {
-	let mut rolls = OrderedMap::<RollId, RollGroup>::new();
+	let mut rolls = EvaluatedRolls::new(large_pool_threshold);
 
-	let value = evaluate_node(parse_tree, rng, &mut rolls)?.value();
+	let value = evaluate_settled(parse_tree, rng, &mut rolls, scope)?.value();
 
-	Ok(DiceEvaluation {
-		value,
-		roll_groups: rolls.values().cloned().collect(),
-	})
+	Ok(rolls.into_evaluation(value))
+}
+
+/// Evaluates `parse_tree` without recording any of the [`RollGroup`]s it rolls, returning only its
+/// value
+pub(super) fn evaluate_tree_value<R>(
+	parse_tree: &Node,
+	rng: &mut R,
+	scope: &Scope,
+	large_pool_threshold: u32,
+) -> Result<f64, ParsingError>
+where
+	R: RangeRng,
+{
+	let mut rolls = EvaluatedRolls::value_only(large_pool_threshold);
+	Ok(evaluate_settled(parse_tree, rng, &mut rolls, scope)?.into_value())
 }
+
+/// Evaluates `node`. A [`Roll`][Operand::Roll] result still owns its [`RollGroup`], as the node
+/// above may modify it further (eg. with a dice modifier). Nodes which only use the value of a
+/// result pass it through [`EvaluatedRolls::settle`] first, which stores its group
 fn evaluate_node<R>(
-	node: Node,
+	node: &Node,
+	rng: &mut R,
+	rolls: &mut EvaluatedRolls,
+	scope: &Scope,
+) -> Result<Operand, ParsingError>
+where
+	R: RangeRng,
+{
+	evaluate_for_use(node, false, rng, rolls, scope)
+}
+
+/// Evaluates `node` with [`evaluate_node`], settling its result
+fn evaluate_settled<R>(
+	node: &Node,
+	rng: &mut R,
+	rolls: &mut EvaluatedRolls,
+	scope: &Scope,
+) -> Result<Operand, ParsingError>
+where
+	R: RangeRng,
+{
+	evaluate_for_use(node, true, rng, rolls, scope).map(|it| rolls.settle(it))
+}
+
+/// Evaluates `node`, where `totalled` is whether its result will only be settled. The rolls of a
+/// dice term whose result is only totalled are never seen individually, so large pools can be
+/// sampled rather than rolled
+fn evaluate_for_use<R>(
+	node: &Node,
+	totalled: bool,
 	rng: &mut R,
-	rolls: &mut OrderedMap<RollId, RollGroup>,
+	rolls: &mut EvaluatedRolls,
+	scope: &Scope,
 ) -> Result<Operand, ParsingError>
 where
 	R: RangeRng,
 {
-	let operand = match node
+	let span = node.span();
+	// modifiers and tags are part of the dice term they follow, so they extend the span of its
+	// rolls
+	let extends_span =
+		matches!(node, Node::Modifier { .. } | Node::Reroll { .. } | Node::Tagged { .. });
+	let tags_start = rolls.tag_subtotals.len();
+	let mut operand = match node
 	{
-		Node::Leaf(n) => Operand::Number(n),
-		Node::Unary { operator, argument } =>
+		Node::Leaf(n) => Operand::Number(*n),
+		Node::Variable { name, .. } => Operand::Number(
+			*scope
+				.variables
+				.get(name)
+				.ok_or_else(|| UndefinedVariableError(name.clone()))?,
+		),
+		Node::Table { name, .. } =>
 		{
-			operator.eval(evaluate_node(*argument, rng, rolls)?, rng)
+			let tables = scope
+				.tables
+				.ok_or_else(|| UnknownTableError(name.clone()))?;
+			let table_roll = tables.roll_in_scope(name, rng, scope)?;
+			let value = table_roll.selection.value;
+			rolls.record_table_roll(table_roll);
+			Operand::Number(value)
 		}
 		Node::Binary {
 			operator,
 			left,
 			right,
-		} => operator.eval(
-			evaluate_node(*left, rng, rolls)?,
-			evaluate_node(*right, rng, rolls)?,
-			rng,
-		),
+			..
+		} if operator.token == OpToken::Dice =>
+		{
+			let count_start = rolls.len();
+			let count = evaluate_settled(left, rng, rolls, scope)?;
+			let faces_start = rolls.len();
+			let faces = evaluate_settled(right, rng, rolls, scope)?;
+			let faces_end = rolls.len();
+			function::check_faces(&faces)?;
+
+			let dice = if totalled
+			{
+				function::totalled_dice(count, faces, rolls.large_pool_threshold, rng)
+			}
+			else
+			{
+				operator.eval(count, faces, rng)
+			};
+			if let Operand::Roll { id, .. } = &dice
+			{
+				let child = rolls.register(*id);
+				rolls.link(child, count_start..faces_start, DicePart::Count);
+				rolls.link(child, faces_start..faces_end, DicePart::Faces);
+			}
+			dice
+		}
+		Node::Unary {
+			operator, argument, ..
+		} if operator.token == UnaryOpToken::Dice =>
+		{
+			let faces_start = rolls.len();
+			let faces = evaluate_settled(argument, rng, rolls, scope)?;
+			let faces_end = rolls.len();
+			function::check_faces(&faces)?;
+
+			let dice = operator.eval(faces, rng);
+			if let Operand::Roll { id, .. } = &dice
+			{
+				let child = rolls.register(*id);
+				rolls.link(child, faces_start..faces_end, DicePart::Faces);
+			}
+			dice
+		}
+		Node::Unary {
+			operator, argument, ..
+		} =>
+		{
+			let argument = evaluate_settled(argument, rng, rolls, scope)?;
+			if operator.token == UnaryOpToken::Minus
+			{
+				rolls.map_tag_subtotals(tags_start, |it| -it);
+			}
+			operator.eval(argument, rng)
+		}
+		// element-wise operators modify each roll of their left-hand side, so it is not settled
+		Node::Binary {
+			operator,
+			left,
+			right,
+			..
+		} if operator.token.is_element_wise() =>
+		{
+			let left = evaluate_node(left, rng, rolls, scope)?;
+			let right = evaluate_settled(right, rng, rolls, scope)?;
+			operator.eval(left, right, rng)
+		}
+		Node::Binary { .. } | Node::Comparison { .. } | Node::Logic { .. } =>
+		{
+			evaluate_chain(node, rng, rolls, scope)?
+		}
 		Node::ComparisonTernary {
-			comp_op: comp_operator,
-			dice_left,
-			dice_right,
+			comp_op,
+			dice,
 			compare_to,
-		} => comp_operator.eval(
-			evaluate_node(*dice_left, rng, rolls)?,
-			evaluate_node(*dice_right, rng, rolls)?,
-			evaluate_node(*compare_to, rng, rolls)?,
-			rng,
-		),
+			..
+		} =>
+		{
+			let dice = evaluate_node(dice, rng, rolls, scope)?;
+			comp_op.filter(dice, evaluate_settled(compare_to, rng, rolls, scope)?)
+		}
+		Node::Modifier {
+			modifier,
+			dice,
+			argument,
+			..
+		} =>
+		{
+			let dice = evaluate_node(dice, rng, rolls, scope)?;
+			if *modifier == DiceModifier::Concatenate
+			{
+				function::check_digits(&dice)?;
+			}
+			let argument = argument
+				.as_ref()
+				.map(|it| evaluate_settled(it, rng, rolls, scope))
+				.transpose()?;
+			modifier.eval(dice, argument, rng)
+		}
+		Node::Reroll {
+			dice,
+			conditions,
+			once,
+			..
+		} =>
+		{
+			let dice = evaluate_node(dice, rng, rolls, scope)?;
+			function::reroll_any(dice, conditions, *once, rng)
+		}
+		Node::Conditional {
+			condition,
+			if_true,
+			if_false,
+			..
+		} =>
+		{
+			let branch = if evaluate_settled(condition, rng, rolls, scope)?.is_truthy()
+			{
+				if_true
+			}
+			else
+			{
+				if_false
+			};
+			evaluate_for_use(branch, totalled, rng, rolls, scope)?
+		}
+		Node::Function {
+			function,
+			arguments,
+			..
+		} =>
+		{
+			let arguments = arguments
+				.iter()
+				.map(|it| {
+					evaluate_node(it, rng, rolls, scope).map(|it| rolls.settle_to_list(it))
+				})
+				.collect::<Result<Vec<_>, _>>()?;
+			function.eval(arguments)?
+		}
+		Node::Index { list, index, .. } =>
+		{
+			let values = evaluate_node(list, rng, rolls, scope)
+				.map(|it| rolls.settle_to_list(it))?
+				.values();
+			let index = evaluate_settled(index, rng, rolls, scope)?.value();
+			Operand::Number(list_index(&values, index).ok_or(IndexOutOfRangeError {
+				index,
+				len: values.len(),
+			})?)
+		}
+		Node::PlusMaximum { dice, .. } =>
+		{
+			function::plus_maximum(evaluate_node(dice, rng, rolls, scope)?)
+		}
+		// tags closer to a group take precedence, so only groups without a tag are given this one
+		Node::Tagged { node, tag, .. } =>
+		{
+			let first = rolls.len();
+			let mut operand = evaluate_for_use(node, totalled, rng, rolls, scope)?;
+			rolls.tag_from(first, tag);
+			if let Operand::Roll { data, .. } = &mut operand
+			{
+				if data.tag().is_none()
+				{
+					data.set_tag(tag.clone());
+				}
+			}
+			rolls.attribute_to_tag(tags_start, tag, operand.value());
+			operand
+		}
 	};
 
-	if let Operand::Roll { id, data } = &operand
+	// the value of anything other than arithmetic is not attributed to the tags within it (see
+	// `DiceEvaluation::breakdown_by_tag`)
+	let keeps_tags = match node
+	{
+		Node::Binary { operator, .. } =>
+		{
+			!(operator.token == OpToken::Dice || operator.token.is_element_wise())
+		}
+		Node::Unary { operator, .. } =>
+		{
+			matches!(operator.token, UnaryOpToken::Plus | UnaryOpToken::Minus)
+		}
+		Node::Conditional { .. } | Node::Tagged { .. } => true,
+		_ => false,
+	};
+	if !keeps_tags
 	{
-		rolls.insert(*id, data.clone());
+		rolls.tag_subtotals.truncate(tags_start);
+	}
+
+	if let Operand::Roll { id, data } = &mut operand
+	{
+		// the first node to produce a roll is the dice term which actually rolled it
+		if let Some(span) = span.filter(|_| extends_span || data.span().is_none())
+		{
+			data.set_span(span.into());
+		}
+		rolls.register(*id);
 	}
 
 	Ok(operand)
 }
 
-#[derive(Debug, Clone)]
-struct OrderedMap<K, V>
+/// Evaluates `node`, an arithmetic, comparison, or logic operation, along with every such
+/// operation down its left-hand side (eg. the whole of `1 + 2 - 3 == 0`). The parser does not limit
+/// how long these chains can be, so they are evaluated in a loop rather than recursively
+fn evaluate_chain<R>(
+	node: &Node,
+	rng: &mut R,
+	rolls: &mut EvaluatedRolls,
+	scope: &Scope,
+) -> Result<Operand, ParsingError>
+where
+	R: RangeRng,
 {
-	map: HashMap<K, V>,
-	insertion_order: Vec<K>,
+	let mut chain = Vec::new();
+	let mut first = node;
+	while let Some(left) = chain_left(first)
+	{
+		chain.push(first);
+		first = left;
+	}
+
+	let tags_start = rolls.tag_subtotals.len();
+	let mut value = evaluate_settled(first, rng, rolls, scope)?;
+	for link in chain.into_iter().rev()
+	{
+		value = match link
+		{
+			Node::Binary {
+				operator, right, ..
+			} =>
+			{
+				let right_tags_start = rolls.tag_subtotals.len();
+				let right = evaluate_settled(right, rng, rolls, scope)?;
+				rolls.attribute_binary(
+					operator.token,
+					tags_start..right_tags_start,
+					value.value(),
+					right.value(),
+				);
+				operator.eval(value, right, rng)
+			}
+			Node::Comparison { comp_op, right, .. } =>
+			{
+				let right = evaluate_settled(right, rng, rolls, scope)?;
+				rolls.tag_subtotals.truncate(tags_start);
+				Operand::from(comp_op.compare(value.value(), right.value()))
+			}
+			Node::Logic {
+				operator, right, ..
+			} =>
+			{
+				let left = value.is_truthy();
+				let result = match operator
+				{
+					LogicOperator::And =>
+					{
+						left && evaluate_settled(right, rng, rolls, scope)?.is_truthy()
+					}
+					LogicOperator::Or =>
+					{
+						left || evaluate_settled(right, rng, rolls, scope)?.is_truthy()
+					}
+				};
+				rolls.tag_subtotals.truncate(tags_start);
+				Operand::from(result)
+			}
+			_ => unreachable!("every link of a chain should be an operation with a left-hand side"),
+		};
+	}
+
+	Ok(value)
 }
-impl<K, V> OrderedMap<K, V>
+
+/// Returns the left-hand side of `node` if it is an operation which [`evaluate_chain`] evaluates
+pub(crate) fn chain_left(node: &Node) -> Option<&Node>
 {
-	fn new() -> Self
+	match node
 	{
-		Self {
-			map: HashMap::new(),
-			insertion_order: Vec::new(),
+		Node::Binary { operator, left, .. }
+			if !(operator.token == OpToken::Dice || operator.token.is_element_wise()) =>
+		{
+			Some(left)
 		}
+		Node::Comparison { left, .. } | Node::Logic { left, .. } => Some(left),
+		_ => None,
 	}
 }
-impl<K: Eq + Hash + Clone, V> OrderedMap<K, V>
+
+/// Returns the element of `values` at `index`, truncated to a whole number. Negative indices count
+/// back from the end of `values`
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+pub(crate) fn list_index(values: &[f64], index: f64) -> Option<f64>
 {
-	fn insert(&mut self, k: K, v: V) -> Option<V>
+	let index = math::trunc(index);
+	let position = if index < 0.0
+	{
+		values.len() as f64 + index
+	}
+	else
+	{
+		index
+	};
+
+	(position >= 0.0)
+		.then(|| values.get(position as usize).copied())
+		.flatten()
+}
+
+/// The [`RollGroup`]s produced while evaluating a tree, in the order they were first rolled, along
+/// with which groups decided the count or faces of which other groups. Each group has a slot,
+/// which stays empty while the group is still owned by an [`Operand`], and is filled once its
+/// final state is known, so no group is ever copied
+#[derive(Debug)]
+struct EvaluatedRolls
+{
+	/// Whether rolls are recorded at all. Evaluations which only need a value skip recording
+	recording: bool,
+	/// Pools of more dice than this whose rolls are only totalled are sampled rather than rolled
+	large_pool_threshold: u32,
+	slots: Vec<Option<RollGroup>>,
+	/// The index in `slots` of each group
+	positions: BTreeMap<RollId, usize>,
+	/// Each dependency as (parent, child, part), by index in `slots`
+	dependencies: Vec<(usize, usize, DicePart)>,
+	/// The part of the value attributed to each tagged subexpression evaluated so far, as (tag,
+	/// subtotal). Nodes which do not keep the attribution of their children truncate it
+	tag_subtotals: Vec<(Box<str>, f64)>,
+	table_rolls: Vec<TableRoll>,
+}
+impl EvaluatedRolls
+{
+	fn new(large_pool_threshold: u32) -> Self
+	{
+		Self {
+			recording: true,
+			large_pool_threshold,
+			slots: Vec::new(),
+			positions: BTreeMap::new(),
+			dependencies: Vec::new(),
+			tag_subtotals: Vec::new(),
+			table_rolls: Vec::new(),
+		}
+	}
+
+	fn value_only(large_pool_threshold: u32) -> Self
+	{
+		Self {
+			recording: false,
+			..Self::new(large_pool_threshold)
+		}
+	}
+
+	/// Gives the group with the given `id` a slot if it does not have one yet, and returns the
+	/// index of its slot
+	fn register(&mut self, id: RollId) -> usize
+	{
+		if !self.recording
+		{
+			return 0;
+		}
+
+		*self.positions.entry(id).or_insert_with(|| {
+			self.slots.push(None);
+			self.slots.len() - 1
+		})
+	}
+
+	/// Stores the group of `operand` if it is a [`Roll`][Operand::Roll], and returns its total as
+	/// a [`Number`][Operand::Number] in its place. Used for operands whose rolls will not be
+	/// modified any further
+	fn settle(&mut self, operand: Operand) -> Operand
+	{
+		match operand
+		{
+			Operand::Roll { id, data } =>
+			{
+				let total = data.total();
+				self.store(id, data);
+				Operand::Number(total)
+			}
+			other => other,
+		}
+	}
+
+	/// The same as [`settle`][EvaluatedRolls::settle], but returns the values of a
+	/// [`Roll`][Operand::Roll] as a [`List`][Operand::List], for operands used as lists
+	fn settle_to_list(&mut self, operand: Operand) -> Operand
+	{
+		match operand
+		{
+			Operand::Roll { id, data } =>
+			{
+				let values = data.list_values().into();
+				self.store(id, data);
+				Operand::List(values)
+			}
+			other => other,
+		}
+	}
+
+	fn store(&mut self, id: RollId, group: RollGroup)
+	{
+		if self.recording
+		{
+			let position = self.register(id);
+			self.slots[position] = Some(group);
+		}
+	}
+
+	/// Returns the number of distinct [`RollGroup`]s rolled so far
+	fn len(&self) -> usize
+	{
+		self.slots.len()
+	}
+
+	/// Gives `tag` to each stored group without a tag which was first rolled at or after position
+	/// `first`
+	fn tag_from(&mut self, first: usize, tag: &str)
+	{
+		for group in self.slots.iter_mut().skip(first).flatten()
+		{
+			if group.tag().is_none()
+			{
+				group.set_tag(tag.into());
+			}
+		}
+	}
+
+	/// Attributes `value`, the value of a subexpression tagged with `tag`, to the tag, less the
+	/// subtotals of any tags within it (those recorded from `start` onwards)
+	fn attribute_to_tag(&mut self, start: usize, tag: &str, value: f64)
+	{
+		if !self.recording
+		{
+			return;
+		}
+		let nested = self.tag_subtotals[start..]
+			.iter()
+			.map(|(_, subtotal)| subtotal)
+			.sum::<f64>();
+		self.tag_subtotals.push((tag.into(), value - nested));
+	}
+
+	/// Changes the tag subtotals of the operands of a binary operation, where `left` is the range
+	/// of those of its left-hand side, and those after it are of its right-hand side. Sums keep
+	/// them, differences negate the right-hand side's, and products and quotients with an
+	/// untagged operand scale the other operand's. Any other operation discards them all
+	fn attribute_binary(&mut self, token: OpToken, left: Range<usize>, lhs: f64, rhs: f64)
+	{
+		let left_tagged = !left.is_empty();
+		let right_tagged = self.tag_subtotals.len() > left.end;
+		match token
+		{
+			OpToken::Plus => (),
+			OpToken::Minus => self.map_tag_subtotals(left.end, |it| -it),
+			OpToken::Multiply if !right_tagged => self.map_tag_subtotals(left.start, |it| it * rhs),
+			OpToken::Multiply if !left_tagged => self.map_tag_subtotals(left.end, |it| lhs * it),
+			OpToken::Divide if !right_tagged => self.map_tag_subtotals(left.start, |it| it / rhs),
+			_ => self.tag_subtotals.truncate(left.start),
+		}
+	}
+
+	/// Applies `map` to each tag subtotal recorded from `start` onwards
+	fn map_tag_subtotals<F>(&mut self, start: usize, map: F)
+	where
+		F: Fn(f64) -> f64,
+	{
+		for (_, subtotal) in &mut self.tag_subtotals[start..]
+		{
+			*subtotal = map(*subtotal);
+		}
+	}
+
+	fn record_table_roll(&mut self, table_roll: TableRoll)
 	{
-		if !self.map.contains_key(&k)
+		if self.recording
 		{
-			self.insertion_order.push(k.clone());
+			self.table_rolls.push(table_roll);
 		}
+	}
+
+	/// Records that the `part` of the group at `child` was decided by the groups first rolled
+	/// within `range` (as positions in roll order). Groups which another group already depends on
+	/// are skipped, so only direct dependencies are recorded
+	fn link(&mut self, child: usize, range: Range<usize>, part: DicePart)
+	{
+		let parents = range
+			.filter(|position| {
+				*position != child
+					&& !self.dependencies.iter().any(|(parent, ..)| parent == position)
+			})
+			.collect::<Vec<_>>();
+		self.dependencies
+			.extend(parents.into_iter().map(|parent| (parent, child, part)));
+	}
 
-		self.map.insert(k, v)
+	fn into_evaluation(self, value: f64) -> DiceEvaluation
+	{
+		DiceEvaluation {
+			value,
+			roll_groups: self
+				.slots
+				.into_iter()
+				.map(|it| it.expect("every roll group should be settled by the end of evaluation"))
+				.collect(),
+			dependencies: self
+				.dependencies
+				.into_iter()
+				.map(|(parent, child, part)| RollDependency {
+					parent,
+					child,
+					part,
+				})
+				.collect(),
+			tag_subtotals: combine_tag_subtotals(self.tag_subtotals),
+			table_rolls: self.table_rolls.into(),
+		}
 	}
+}
 
-	fn values(&self) -> impl Iterator<Item = &V>
+/// Sums the subtotals of each tag, keeping the tags in the order they first appear
+fn combine_tag_subtotals(tag_subtotals: Vec<(Box<str>, f64)>) -> Box<[(Box<str>, f64)]>
+{
+	let mut combined: Vec<(Box<str>, f64)> = Vec::new();
+	for (tag, subtotal) in tag_subtotals
 	{
-		self.insertion_order.iter().map(|key| &self.map[key])
+		match combined.iter_mut().find(|(it, _)| *it == tag)
+		{
+			Some((_, total)) => *total += subtotal,
+			None => combined.push((tag, subtotal)),
+		}
 	}
+	combined.into_boxed_slice()
 }
 
 #[cfg(test)]
@@ -112,9 +712,12 @@ mod tests
 		parsing,
 		test_helpers::{assert_approx_eq, RiggedRandom},
 		tokenization::TokenStream,
+		Expression,
 	};
 	use rand::thread_rng;
 
+	const THRESHOLD: u32 = Expression::DEFAULT_LARGE_POOL_THRESHOLD;
+
 	#[test]
 	fn deterministic_evaluation()
 	{
@@ -135,6 +738,410 @@ mod tests
 		);
 	}
 
+	#[test]
+	fn advantage()
+	{
+		let evaluation = eval_expect_rand("1d20adv + 2", &mut RiggedRandom::new([8, 20]));
+		assert_approx_eq!(22.0, evaluation.value);
+		assert_eq!(1, evaluation.roll_groups.len());
+		assert!(evaluation.roll_groups[0].is_critical_success());
+		assert!(!evaluation.roll_groups[0].is_max_roll());
+
+		let evaluation = eval_expect_rand("d20dis", &mut RiggedRandom::new([1, 20]));
+		assert_approx_eq!(1.0, evaluation.value);
+		assert!(evaluation.roll_groups[0].is_critical_failure());
+	}
+
+	#[test]
+	fn keep_and_filter()
+	{
+		let evaluation = eval_expect_rand("4d6kh3 > 2", &mut RiggedRandom::new([2, 5, 1, 6]));
+		assert_approx_eq!(11.0, evaluation.value);
+		assert_eq!(
+			vec![true, false, true, false],
+			evaluation
+				.ungrouped_rolls()
+				.map(|it| it.is_removed())
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn concatenated()
+	{
+		let value = |input: &str, rolls: &[u32]| {
+			eval_expect_rand(input, &mut RiggedRandom::new(rolls.iter().copied())).value
+		};
+		let evaluation = eval_expect_rand("2d6cat + 1", &mut RiggedRandom::new([5, 3]));
+		assert_approx_eq!(54.0, evaluation.value);
+		assert!(evaluation.roll_groups[0].is_concatenated());
+		assert_eq!("Total: 54 [2d6: [5, 3] = 53]", evaluation.to_string());
+		assert_approx_eq!(35.0, value("2d6 sa cat", &[5, 3]));
+		assert_approx_eq!(53.0, value("2d6 sd cat", &[3, 5]));
+		assert_approx_eq!(63.0, value("3d6kh2cat", &[6, 1, 3]));
+
+		// a percentile roll, from a tens die and a units die
+		assert_approx_eq!(47.0, value("2d10cat", &[4, 7]));
+		assert_approx_eq!(7.0, value("2d10cat", &[10, 7]));
+		assert_approx_eq!(40.0, value("2d10cat", &[4, 10]));
+		assert_approx_eq!(100.0, value("2d10cat", &[10, 10]));
+
+		// filters and lists treat the digits as one number
+		assert_approx_eq!(53.0, value("2d6cat > 40", &[5, 3]));
+		assert_approx_eq!(0.0, value("2d6cat > 40", &[3, 5]));
+		assert_approx_eq!(53.0, value("highest(list(2d6cat, 2d6cat))", &[5, 3, 2, 6]));
+		assert_approx_eq!(2.0, value("count(list(2d6cat, 2d6cat))", &[5, 3, 2, 6]));
+
+		// rolls are only read as digits if each is one digit
+		assert_approx_eq!(96.0, value("2d6 .+ 3 cat", &[6, 3]));
+		for (input, rolls) in [
+			("2d100cat", &[65, 10][..]),
+			("2d12cat", &[3, 4]),
+			("2d6 .+ 5 cat", &[5, 3]),
+			("2d6 ./ 2 cat", &[5, 4]),
+		]
+		{
+			let invalid = |result: Result<f64, ParsingError>| {
+				matches!(result, Err(ParsingError::InvalidDigit(_)))
+			};
+			let expression = Expression::parse(input).unwrap();
+			let rand = || RiggedRandom::new(rolls.iter().copied());
+			assert!(invalid(expression.eval_with_rand(&mut rand()).map(|it| it.value)), "{input}");
+			assert!(invalid(expression.compile().eval_value_with_rand(&mut rand())), "{input}");
+		}
+		// nothing can modify the rolls once they are read as one number
+		assert!(matches!(
+			eval_str("2d6cat kh1"),
+			Err(ParsingError::UnmatchedModifier(_))
+		));
+	}
+
+	#[test]
+	fn tag_breakdown()
+	{
+		let breakdown = |input: &str, rolls: &[u32]| {
+			let evaluation =
+				eval_expect_rand(input, &mut RiggedRandom::new(rolls.iter().copied()));
+			evaluation
+				.breakdown_by_tag()
+				.into_iter()
+				.map(|(tag, subtotal)| (tag.map(Into::into), subtotal))
+				.collect::<Vec<(Option<String>, f64)>>()
+		};
+		let tag = |tag: &str, subtotal: f64| (Some(String::from(tag)), subtotal);
+
+		assert_eq!(
+			breakdown("2d6[fire] + 1d8[radiant] + 3", &[3, 4, 5]),
+			vec![tag("fire", 7.0), tag("radiant", 5.0), (None, 3.0)]
+		);
+		assert_eq!(
+			breakdown("1d6[fire] + 1d4[cold] + 1d6[fire]", &[2, 3, 4]),
+			vec![tag("fire", 6.0), tag("cold", 3.0)]
+		);
+		assert_eq!(breakdown("10 - 1d6[fire]", &[4]), vec![tag("fire", -4.0), (None, 10.0)]);
+		assert_eq!(breakdown("-(1d6[fire] + 1)", &[4]), vec![tag("fire", -4.0), (None, -1.0)]);
+		assert_eq!(breakdown("(2d6[fire] + 1) * 2", &[3, 4]), vec![tag("fire", 14.0), (None, 2.0)]);
+		assert_eq!(breakdown("2 * 1d6[fire] / 4", &[3]), vec![tag("fire", 1.5)]);
+		assert_eq!(
+			breakdown("(2d6[fire] + 3)[magic]", &[3, 4]),
+			vec![tag("fire", 7.0), tag("magic", 3.0)]
+		);
+		assert_eq!(
+			breakdown("if(1d20 >= 10, 2d6[fire], 0) + 1", &[15, 3, 4]),
+			vec![tag("fire", 7.0), (None, 1.0)]
+		);
+
+		// the value of other operations on tagged values is untagged
+		assert_eq!(breakdown("1d4[fire] * 1d6[cold]", &[3, 4]), vec![(None, 12.0)]);
+		assert_eq!(
+			breakdown("sum(2d6[fire]) + 1d4[cold]", &[3, 4, 2]),
+			vec![tag("cold", 2.0), (None, 7.0)]
+		);
+		assert_eq!(breakdown("(1d4[count])d6", &[2, 3, 4]), vec![(None, 7.0)]);
+
+		// the tag closest to a group is the one it keeps
+		let evaluation =
+			eval_expect_rand("(1d6 + 1d4[cold])[fire]", &mut RiggedRandom::new([2, 3]));
+		let tags = evaluation.roll_groups.iter().map(RollGroup::tag).collect::<Vec<_>>();
+		assert_eq!(tags, vec![Some("fire"), Some("cold")]);
+		assert_eq!(
+			evaluation.to_string(),
+			"Total: 5 (cold: 3, fire: 2) [1d6 (fire): [2], 1d4 (cold): [3]]"
+		);
+	}
+
+	#[test]
+	fn variables()
+	{
+		let tree = parsing::parse_tree_from(&mut TokenStream::new("2 * attack")).unwrap();
+		let variables = Variables::from([("attack".into(), 7.0)]);
+		let scope = Scope::new(&variables);
+		let evaluation = evaluate_tree(&tree, &mut thread_rng(), &scope, THRESHOLD).unwrap();
+		assert_approx_eq!(14.0, evaluation.value);
+
+		assert!(matches!(
+			evaluate_tree(&tree, &mut thread_rng(), &Scope::EMPTY, THRESHOLD),
+			Err(ParsingError::UndefinedVariable(_))
+		));
+	}
+
+	#[test]
+	fn element_wise()
+	{
+		let evaluation = eval_expect_rand("4d6 .+ 1 kh3", &mut RiggedRandom::new([2, 6, 1, 4]));
+		assert_approx_eq!(15.0, evaluation.value);
+		assert_eq!(1, evaluation.roll_groups.len());
+		assert_eq!(
+			vec![false, false, true, false],
+			evaluation
+				.ungrouped_rolls()
+				.map(|it| it.is_removed())
+				.collect::<Vec<_>>()
+		);
+
+		let evaluation = eval_expect_rand("3d8 .* 2 > 8 + 1", &mut RiggedRandom::new([4, 5, 8]));
+		assert_approx_eq!(26.0, evaluation.value);
+		assert_eq!(
+			vec![4, 5, 8],
+			evaluation
+				.ungrouped_rolls()
+				.map(|it| it.original_value)
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn lists()
+	{
+		let rolls = || RiggedRandom::new([3, 6, 1, 6, 2, 6]);
+		let evaluation = eval_expect_rand("count(6d6 == 6)", &mut rolls());
+		assert_approx_eq!(3.0, evaluation.value);
+		assert_eq!(1, evaluation.roll_groups.len());
+
+		assert_approx_eq!(12.0, eval_expect_rand("highest(6d6, 2)", &mut rolls()).value);
+		assert_approx_eq!(1.0, eval_expect_rand("sort(6d6)[0]", &mut rolls()).value);
+		assert_approx_eq!(6.0, eval_expect_rand("(6d6)[1]", &mut rolls()).value);
+		assert_approx_eq!(6.0, eval_expect_rand("sort(6d6)[-2]", &mut rolls()).value);
+		assert_approx_eq!(24.0, eval_expect_rand("sum(6d6) .+ 0", &mut rolls()).value);
+		assert_approx_eq!(5.0, eval_expect_rand("lowest(6d6, 2) .+ 1", &mut rolls()).value);
+
+		// implicit multiplication within arguments and indices
+		assert_approx_eq!(7.0, eval_expect("sum(list(2(3), 1))").value);
+		assert_approx_eq!(6.0, eval_expect("if(1, 2(3), 0)").value);
+		assert_approx_eq!(1.0, eval_expect_rand("(6d6)[2(1)]", &mut rolls()).value);
+
+		assert!(matches!(
+			eval_str_rand("(6d6)[6]", &mut rolls()),
+			Err(ParsingError::IndexOutOfRange(_))
+		));
+		assert!(matches!(
+			eval_str_rand("(6d6)[-7]", &mut rolls()),
+			Err(ParsingError::IndexOutOfRange(_))
+		));
+		assert_approx_eq!(0.0, eval_expect_rand("sum(highest(6d6, 0))", &mut rolls()).value);
+		for input in ["highest(6d6, -1)", "lowest(6d6, 0.5)", "lowest(6d6, 2 - 3)"]
+		{
+			assert!(
+				matches!(
+					eval_str_rand(input, &mut rolls()),
+					Err(ParsingError::InvalidValueCount(_))
+				),
+				"`{input}`"
+			);
+		}
+	}
+
+	#[test]
+	fn comparisons()
+	{
+		assert_approx_eq!(1.0, eval_expect("6 > 3").value);
+		assert_approx_eq!(0.0, eval_expect("(2 + 5) > 9").value);
+		assert_approx_eq!(1.0, eval_expect("1 < 2 and not 3 == 4").value);
+		assert_approx_eq!(0.0, eval_expect("0 or 2 - 2").value);
+
+		let evaluation = eval_expect_rand("(2d6 + 5) > 9", &mut RiggedRandom::new([3, 4]));
+		assert_approx_eq!(1.0, evaluation.value);
+		assert_eq!(1, evaluation.roll_groups.len());
+	}
+
+	#[test]
+	fn empty_totals()
+	{
+		// a group with no kept rolls totals 0 rather than -0
+		let evaluation = eval_expect("0d6");
+		assert_eq!(evaluation.to_string(), "Total: 0 [0d6: []]");
+		for (input, rolls) in [("2d6kh0", [3, 4]), ("2d20kh1 == 20", [3, 4])]
+		{
+			let evaluation = eval_expect_rand(input, &mut RiggedRandom::new(rolls));
+			assert!(evaluation.value.is_sign_positive(), "`{input}`");
+			assert!(evaluation.to_string().starts_with("Total: 0 "), "`{input}`");
+		}
+		assert!(eval_expect("sum(highest(list(1, 2), 0))").value.is_sign_positive());
+	}
+
+	#[test]
+	fn lazy_branches()
+	{
+		let evaluation =
+			eval_expect_rand("if(1d20+5 >= 15, 2d6+3, 0)", &mut RiggedRandom::new([7]));
+		assert_approx_eq!(0.0, evaluation.value);
+		assert_eq!(1, evaluation.roll_groups.len());
+
+		let evaluation =
+			eval_expect_rand("if(1d20+5 >= 15, 2d6+3, 0)", &mut RiggedRandom::new([12, 2, 6]));
+		assert_approx_eq!(11.0, evaluation.value);
+		assert_eq!(2, evaluation.roll_groups.len());
+
+		let evaluation = eval_expect_rand("0 and 1d20", &mut RiggedRandom::new([]));
+		assert!(evaluation.roll_groups.is_empty());
+	}
+
+	#[test]
+	fn deepest_expressions()
+	{
+		// the deepest expressions the parser accepts can be evaluated without overflowing the
+		// stack, even on a test thread
+		let depth = crate::error::NestingDepthError::MAX_DEPTH - 1;
+		assert_approx_eq!(-1.0, eval_expect(&format!("{}1", "-".repeat(depth))).value);
+		assert_approx_eq!(
+			1.0,
+			eval_expect(&format!("{}1{}", "(1*".repeat(depth / 2), ")".repeat(depth / 2))).value
+		);
+		assert!(matches!(
+			eval_str(&format!("{}1", "-".repeat(depth + 1))),
+			Err(ParsingError::NestingDepth(_))
+		));
+	}
+
+	#[test]
+	fn long_chains()
+	{
+		// chains of operators are evaluated in a loop, so their length is not limited
+		assert_approx_eq!(5_001.0, eval_expect(&format!("1{}", "+1".repeat(5_000))).value);
+		assert_approx_eq!(
+			1.0,
+			eval_expect(&format!("1{}", " * 2 - 1 >= 0 and 1".repeat(5_000))).value
+		);
+
+		let evaluation = eval_expect_rand(
+			&format!("1d6{}", " + 1d6".repeat(4_999)),
+			&mut RiggedRandom::new(core::iter::repeat_n(2, 5_000)),
+		);
+		assert_approx_eq!(10_000.0, evaluation.value);
+		assert_eq!(5_000, evaluation.roll_groups.len());
+	}
+
+	#[test]
+	fn dependencies()
+	{
+		let evaluation = eval_expect_rand("(1d4)d6", &mut RiggedRandom::new([3, 2, 5, 6]));
+		assert_eq!(
+			*evaluation.dependencies,
+			[RollDependency {
+				parent: 0,
+				child: 1,
+				part: DicePart::Count
+			}]
+		);
+		assert_eq!(evaluation.to_string(), "Total: 13 [1d4: [3] → 3d6: [2, 5, 6]]");
+
+		let evaluation =
+			eval_expect_rand("2d(1d3*2) + d(1d4)", &mut RiggedRandom::new([2, 3, 4, 1, 1]));
+		assert_eq!(
+			evaluation
+				.dependencies
+				.iter()
+				.map(|it| (it.parent, it.child, it.part))
+				.collect::<Vec<_>>(),
+			vec![(0, 1, DicePart::Faces), (2, 3, DicePart::Faces)]
+		);
+
+		// only direct dependencies are recorded
+		let evaluation =
+			eval_expect_rand("((1d2)d3)d4", &mut RiggedRandom::new([2, 1, 2, 3, 4, 1]));
+		assert_eq!(evaluation.dependencies.len(), 2);
+		assert_eq!(
+			evaluation.to_string(),
+			"Total: 8 [1d2: [2] → 2d3: [1, 2] → 3d4: [3, 4, 1]]"
+		);
+
+		let evaluation =
+			eval_expect_rand("(1d2)d(1d4) + 1d6", &mut RiggedRandom::new([2, 3, 1, 2, 5]));
+		assert_eq!(
+			evaluation.to_string(),
+			"Total: 8 [(1d2: [2], 1d4: [3]) → 2d3: [1, 2], 1d6: [5]]"
+		);
+	}
+
+	#[test]
+	fn value_only()
+	{
+		use rand::{rngs::StdRng, SeedableRng};
+
+		for input in [
+			"4d6kh3 + 2",
+			"(1d4)d6 > 2",
+			"2d6 .+ 1 kh1 * 3",
+			"sort(4d6)[-1] + count(3d8 < 4)",
+			"if(1d20 >= 10, 2d6, 0) and 1d4",
+			"1d20adv + d(1d4)",
+		]
+		{
+			let tree = parsing::parse_tree_from(&mut TokenStream::new(input)).unwrap();
+			let full =
+				evaluate_tree(&tree, &mut StdRng::seed_from_u64(7), &Scope::EMPTY, THRESHOLD);
+			let value_only = evaluate_tree_value(
+				&tree,
+				&mut StdRng::seed_from_u64(7),
+				&Scope::EMPTY,
+				THRESHOLD,
+			);
+			assert_approx_eq!(full.unwrap().value, value_only.unwrap());
+		}
+	}
+
+	#[test]
+	fn groups_stored_once()
+	{
+		// each group is recorded once, in its final state, however many nodes it passes through
+		let evaluation = eval_expect_rand(
+			"sum(4d6 .+ 1 kh3 > 3) + (2d8)[0]",
+			&mut RiggedRandom::new([1, 6, 4, 3, 5, 2]),
+		);
+		assert_eq!(evaluation.roll_groups.len(), 2);
+		assert_eq!(
+			evaluation.roll_groups[0]
+				.iter()
+				.map(|it| (it.modified_value(), it.is_removed()))
+				.collect::<Vec<_>>(),
+			vec![(2.0, true), (7.0, false), (5.0, false), (4.0, false)]
+		);
+		assert_approx_eq!(21.0, evaluation.value);
+	}
+
+	#[test]
+	fn large_pools()
+	{
+		let evaluation = eval_expect("20000d6 + 1");
+		let pool = &evaluation.roll_groups[0];
+		assert!(pool.is_sampled());
+		assert_eq!(pool.len(), 20_000);
+		assert_approx_eq!(pool.total() + 1.0, evaluation.value);
+		assert!(pool.total() >= 20_000.0 && pool.total() <= 120_000.0);
+
+		// the rolls of these pools are used individually, so they are rolled
+		for input in ["20000d6kh3", "20000d6 > 3", "20000d6 .+ 1", "sum(20000d6)", "20000d6adv"]
+		{
+			assert!(!eval_expect(input).roll_groups[0].is_sampled(), "{input}");
+		}
+		assert!(eval_expect("if(1, 20000d6, 0) * 2").roll_groups[0].is_sampled());
+
+		// sampled pools still record which groups decided them
+		let evaluation = eval_expect("(20000d6)d2");
+		assert!(evaluation.roll_groups.iter().all(RollGroup::is_sampled));
+		assert_eq!(evaluation.parents(1).next().map(|it| it.parent), Some(0));
+	}
+
 	fn eval_expect(input: &str) -> DiceEvaluation
 	{
 		eval_str(input).unwrap_or_else(|_| panic!("Could not evaluate `{input}`"))
@@ -153,6 +1160,6 @@ mod tests
 	{
 		let mut stream = TokenStream::new(input);
 		let tree = parsing::parse_tree_from(&mut stream)?;
-		evaluate_tree(tree, rand)
+		evaluate_tree(&tree, rand, &Scope::EMPTY, THRESHOLD)
 	}
 }
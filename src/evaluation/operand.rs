@@ -1,16 +1,19 @@
 use super::{RollGroup, RollId};
-use std::{fmt::Display, ops};
+use crate::math;
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::{fmt::Display, ops};
 
-/// An enum representing the two variants of [`Operand`]
+/// An enum representing the variants of [`Operand`]
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 pub enum OperandType
 {
 	Number,
 	Roll,
+	List,
 }
 impl Display for OperandType
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
 	{
 		write!(
 			f,
@@ -19,12 +22,13 @@ impl Display for OperandType
 			{
 				Self::Number => "Number",
 				Self::Roll => "Roll",
+				Self::List => "List",
 			}
 		)
 	}
 }
 
-/// An enum corresponding to the two types of operands that can be used as arguments in operator
+/// An enum corresponding to the types of operands that can be used as arguments in operator
 /// functions
 #[derive(Debug, PartialEq, PartialOrd)]
 pub enum Operand
@@ -35,6 +39,9 @@ pub enum Operand
 		id: RollId,
 		data: RollGroup,
 	},
+	/// A list of values produced by a list function (eg. `sort(4d6)`). Unlike a
+	/// [`Roll`][Operand::Roll], a list is not tied to the dice it was produced from
+	List(Box<[f64]>),
 }
 impl Operand
 {
@@ -52,11 +59,13 @@ impl Operand
 		{
 			Self::Number(_) => OperandType::Number,
 			Self::Roll { .. } => OperandType::Roll,
+			Self::List(_) => OperandType::List,
 		}
 	}
 
 	/// Returns the numerical value of the [`Operand`]. This is the underlying [`f64`] of a
-	/// [`Number`][`Operand::Number`] variant or the total of a [`Roll`][`Operand::Roll`] variant
+	/// [`Number`][`Operand::Number`] variant, the total of a [`Roll`][`Operand::Roll`] variant, or
+	/// the sum of a [`List`][`Operand::List`] variant
 	/// # Examples
 	/// ```rust
 	/// # use saikoro::evaluation::{Operand, Roll, RollGroup};
@@ -71,7 +80,8 @@ impl Operand
 		match self
 		{
 			Self::Number(n) => *n,
-			Self::Roll { data, .. } => f64::from(data.total()),
+			Self::Roll { data, .. } => data.total(),
+			Self::List(values) => math::sum(values.iter().copied()),
 		}
 	}
 
@@ -82,7 +92,31 @@ impl Operand
 		match self
 		{
 			Self::Number(n) => n,
-			Self::Roll { data, .. } => f64::from(data.total()),
+			Self::Roll { data, .. } => data.total(),
+			Self::List(values) => math::sum(values.iter().copied()),
+		}
+	}
+
+	/// Returns the individual values making up the [`Operand`]. These are the modified values of
+	/// every kept [`Roll`][super::Roll] of a [`Roll`][`Operand::Roll`] variant in the order they
+	/// were rolled (or the one number they make if they are
+	/// [concatenated][super::RollGroup::is_concatenated]), the elements of a
+	/// [`List`][`Operand::List`] variant, or the single value of a [`Number`][`Operand::Number`]
+	/// variant
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::evaluation::{Operand, Roll, RollGroup};
+	/// let roll = Operand::from(RollGroup::new(6, [Roll::new(4), Roll::new(2).into_removed()]));
+	/// assert_eq!(roll.values(), vec![4.0]);
+	/// assert_eq!(Operand::Number(3.0).values(), vec![3.0]);
+	/// ```
+	pub fn values(&self) -> Vec<f64>
+	{
+		match self
+		{
+			Self::Number(n) => vec![*n],
+			Self::Roll { data, .. } => data.list_values(),
+			Self::List(values) => values.to_vec(),
 		}
 	}
 
@@ -127,6 +161,19 @@ impl Operand
 	{
 		f64::abs(self.value() - rhs.value()) < f64::EPSILON
 	}
+
+	/// Returns whether or not the [`Operand`] counts as true for logical operators and conditions,
+	/// which is whenever its [`value`][`Operand::value`] is not 0
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::evaluation::Operand;
+	/// assert!(Operand::Number(-2.0).is_truthy());
+	/// assert!(!Operand::Number(0.0).is_truthy());
+	/// ```
+	pub fn is_truthy(&self) -> bool
+	{
+		self.value() != 0.0
+	}
 }
 impl ops::Neg for Operand
 {
@@ -179,6 +226,14 @@ impl ops::Rem for Operand
 	}
 }
 
+impl From<bool> for Operand
+{
+	/// Converts `true` to `Number(1.0)` and `false` to `Number(0.0)`
+	fn from(value: bool) -> Self
+	{
+		Self::Number(if value { 1.0 } else { 0.0 })
+	}
+}
 impl From<RollGroup> for Operand
 {
 	fn from(value: RollGroup) -> Self
@@ -211,6 +266,10 @@ mod tests
 			OperandType::Roll,
 			Operand::from(RollGroup::new(20, [13].map(Roll::new))).operand_type()
 		);
+		assert_eq!(
+			OperandType::List,
+			Operand::List(Box::new([1.0, 2.0])).operand_type()
+		);
 	}
 
 	mod ops
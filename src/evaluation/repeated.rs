@@ -0,0 +1,171 @@
+use super::{AnnotatedEvaluation, DiceEvaluation};
+use crate::math;
+use alloc::{boxed::Box, string::ToString, vec::Vec};
+use core::{fmt::Display, ops::Range};
+
+/// The results of evaluating the same expression some number of times, independently of one
+/// another (eg. `6x 4d6kh3` for generating ability scores). Each [`DiceEvaluation`] has its own
+/// value and [`RollGroup`][super::RollGroup]s
+#[derive(Debug)]
+pub struct RepeatedEvaluation
+{
+	pub evaluations: Box<[DiceEvaluation]>,
+	span: Range<usize>,
+}
+impl RepeatedEvaluation
+{
+	pub(crate) fn new<I>(evaluations: I, span: Range<usize>) -> Self
+	where
+		I: IntoIterator<Item = DiceEvaluation>,
+	{
+		Self {
+			evaluations: evaluations.into_iter().collect(),
+			span,
+		}
+	}
+
+	/// Returns the number of times the expression was evaluated
+	pub fn len(&self) -> usize
+	{
+		self.evaluations.len()
+	}
+
+	/// Returns `true` if the expression was evaluated 0 times
+	pub fn is_empty(&self) -> bool
+	{
+		self.len() == 0
+	}
+
+	/// Returns an iterator over each [`DiceEvaluation`] in the order they were evaluated
+	pub fn iter(&self) -> core::slice::Iter<'_, DiceEvaluation>
+	{
+		self.evaluations.iter()
+	}
+
+	/// Returns an iterator over the final value of each [`DiceEvaluation`] in the order they were
+	/// evaluated
+	pub fn values(&self) -> impl Iterator<Item = f64> + '_
+	{
+		self.iter().map(|it| it.value)
+	}
+
+	/// Returns the sum of the values of every [`DiceEvaluation`]
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let attacks = saikoro::evaluate_repeated("3x 5")?;
+	/// assert_eq!(attacks.total(), 15.0);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn total(&self) -> f64
+	{
+		math::sum(self.values())
+	}
+
+	/// Returns the [`DiceEvaluation`] with the highest value, or [`None`] if there are none. If
+	/// multiple have the highest value, the first one evaluated is returned
+	pub fn highest(&self) -> Option<&DiceEvaluation>
+	{
+		self.iter()
+			.reduce(|max, it| if it.value > max.value { it } else { max })
+	}
+
+	/// Returns the [`DiceEvaluation`] with the lowest value, or [`None`] if there are none. If
+	/// multiple have the lowest value, the first one evaluated is returned
+	pub fn lowest(&self) -> Option<&DiceEvaluation>
+	{
+		self.iter()
+			.reduce(|min, it| if it.value < min.value { it } else { min })
+	}
+
+	/// Returns each [`DiceEvaluation`] sorted by value from highest to lowest. Evaluations with
+	/// equal values are kept in the order they were evaluated
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let scores = saikoro::evaluate_repeated("6x 4d6kh3")?;
+	/// let sorted = scores.sorted();
+	/// assert!(sorted.windows(2).all(|pair| pair[0].value >= pair[1].value));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn sorted(&self) -> Vec<&DiceEvaluation>
+	{
+		let mut sorted = self.iter().collect::<Vec<_>>();
+		sorted.sort_by(|l, r| r.value.total_cmp(&l.value));
+		sorted
+	}
+
+	/// Returns an iterator of [`AnnotatedEvaluation`]s (see [`DiceEvaluation::annotate`]), one for
+	/// each [`DiceEvaluation`]. Only the repeated expression itself is displayed, not the
+	/// repetition count
+	pub fn annotate<'a>(&'a self, input: &'a str)
+		-> impl Iterator<Item = AnnotatedEvaluation<'a>> + 'a
+	{
+		self.iter()
+			.map(move |it| it.annotate_range(input, self.span.clone()))
+	}
+}
+impl Display for RepeatedEvaluation
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		write!(
+			f,
+			"{}",
+			self.iter()
+				.map(ToString::to_string)
+				.collect::<Vec<_>>()
+				.join("\n")
+		)
+	}
+}
+impl<'a> IntoIterator for &'a RepeatedEvaluation
+{
+	type Item = &'a DiceEvaluation;
+	type IntoIter = core::slice::Iter<'a, DiceEvaluation>;
+
+	fn into_iter(self) -> Self::IntoIter
+	{
+		self.iter()
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use crate::{eval_repeated_with_rand, test_helpers::RiggedRandom};
+
+	#[test]
+	fn independent_results()
+	{
+		let repeated =
+			eval_repeated_with_rand("3x 1d20+5", &mut RiggedRandom::new([4, 17, 9])).unwrap();
+
+		assert_eq!(repeated.len(), 3);
+		assert_eq!(repeated.values().collect::<Vec<_>>(), vec![9.0, 22.0, 14.0]);
+		assert!(repeated.iter().all(|it| it.roll_groups.len() == 1));
+		assert_eq!(repeated.total(), 45.0);
+		assert_eq!(repeated.highest().map(|it| it.value), Some(22.0));
+		assert_eq!(repeated.lowest().map(|it| it.value), Some(9.0));
+		assert_eq!(
+			repeated.sorted().iter().map(|it| it.value).collect::<Vec<_>>(),
+			vec![22.0, 14.0, 9.0]
+		);
+	}
+
+	#[test]
+	fn annotate()
+	{
+		let input = "2# d6 + 1";
+		let repeated = eval_repeated_with_rand(input, &mut RiggedRandom::new([3, 6])).unwrap();
+		assert_eq!(
+			repeated
+				.annotate(input)
+				.map(|it| it.to_string())
+				.collect::<Vec<_>>(),
+			vec!["d6 [3] + 1 = 4", "d6 **[6]** + 1 = 7"]
+		);
+	}
+}
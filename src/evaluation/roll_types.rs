@@ -1,13 +1,47 @@
 // TODO: i want to move this out of evaluation, but im not entirely sure where to put it -morgan
 // 2024-01-10
-use std::{cmp::Ordering, fmt::Display};
+use super::RollDependency;
+use crate::{math, operator::CompOperator, table::TableRoll};
+use alloc::{boxed::Box, string::ToString, vec, vec::Vec};
+use core::{
+	cmp::Ordering,
+	fmt::Display,
+	ops::Range,
+	sync::atomic::{self, AtomicUsize},
+};
 
 /// A group of [`Roll`]s and the number of faces on the dice they were originally rolled from
+///
+/// A very large pool whose rolls are only totalled may instead be
+/// [sampled][RollGroup::is_sampled], in which case only its total is known
 #[derive(Debug, Clone)]
 pub struct RollGroup
 {
 	rolls: Box<[Roll]>,
 	pub faces: u32,
+	span: Option<Range<usize>>,
+	/// The number of dice and their total, for a pool whose total was sampled directly. `rolls` is
+	/// empty for such a pool
+	sampled: Option<SampledPool>,
+	/// Details only set by some front ends, boxed so that groups without them stay small
+	details: Option<Box<GroupDetails>>,
+}
+#[derive(Debug, Clone, Default)]
+struct GroupDetails
+{
+	/// The comparisons a natural value must satisfy to count as a critical success or failure,
+	/// if not the maximum value or 1 (eg. from Roll20's `cs>19`)
+	critical_success: Option<(CompOperator, f64)>,
+	critical_failure: Option<(CompOperator, f64)>,
+	tag: Option<Box<str>>,
+	/// Whether the rolls are read as the digits of one number rather than summed
+	concatenated: bool,
+}
+#[derive(Debug, Clone, Copy)]
+struct SampledPool
+{
+	count: u32,
+	total: f64,
 }
 impl RollGroup
 {
@@ -20,28 +54,220 @@ impl RollGroup
 		Self {
 			rolls: rolls.into_iter().collect(),
 			faces,
+			span: None,
+			sampled: None,
+			details: None,
+		}
+	}
+
+	/// Creates a [`RollGroup`] for a pool of `count` dice whose `total` was sampled without
+	/// rolling each die
+	pub(crate) fn sampled(faces: u32, count: u32, total: f64) -> Self
+	{
+		Self {
+			sampled: Some(SampledPool { count, total }),
+			..Self::new(faces, [])
 		}
 	}
 
-	/// Gets the sum of all [`Roll`]s in the `self` [`RollGroup`], ignoring all [`Roll`]s whose
-	/// values were filtered out. Returns 0 for an empty [`RollGroup`], or one where every [`Roll`]
-	/// has been filtered out
+	/// Returns whether the total of the [`RollGroup`] was sampled directly from the distribution of
+	/// its sum, rather than by rolling each die. This happens for pools larger than the
+	/// [large pool threshold][crate::Expression::with_large_pool_threshold] whose rolls are only
+	/// totalled (eg. the `1000000d6` in `1000000d6 + 5`). A sampled [`RollGroup`] has no
+	/// individual [`Roll`]s, so [`iter`][RollGroup::iter] yields nothing, but its
+	/// [`len`][RollGroup::len] is still the number of dice in the pool
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let evaluation = saikoro::evaluate("1000000d6 + 5")?;
+	/// let pool = &evaluation.roll_groups[0];
+	/// assert!(pool.is_sampled());
+	/// assert_eq!(pool.len(), 1_000_000);
+	/// assert_eq!(pool.iter().count(), 0);
+	/// assert!(pool.total() > 3_400_000.0 && pool.total() < 3_600_000.0);
+	///
+	/// // keeping the highest rolls needs every roll, so they are rolled individually
+	/// let evaluation = saikoro::evaluate("20000d6kh3")?;
+	/// assert!(!evaluation.roll_groups[0].is_sampled());
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn is_sampled(&self) -> bool
+	{
+		self.sampled.is_some()
+	}
+
+	/// Returns the total of a [sampled][RollGroup::is_sampled] group, or [`None`] if it was rolled
+	pub(crate) fn sampled_total(&self) -> Option<f64>
+	{
+		self.sampled.map(|pool| pool.total)
+	}
+
+	/// Returns the range of byte indices in the input string of the dice term which produced the
+	/// [`RollGroup`], or [`None`] if the [`RollGroup`] was not produced from an input string
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let input = "5 + 2d6";
+	/// let evaluation = saikoro::evaluate(input)?;
+	///
+	/// let span = evaluation.roll_groups[0].span().unwrap();
+	/// assert_eq!(&input[span], "2d6");
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn span(&self) -> Option<Range<usize>>
+	{
+		self.span.clone()
+	}
+
+	pub(crate) fn set_span(&mut self, span: Range<usize>)
+	{
+		self.span = Some(span);
+	}
+
+	/// Returns the tag attached to the dice term which produced the [`RollGroup`] (eg. the `fire` in
+	/// `2d6[fire]`, or Foundry's flavor text), or [`None`] if it has none. A group is given the tag
+	/// closest to it, so the `1d4` in `(1d6 + 1d4[cold])[fire]` is tagged `cold`
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let data = saikoro::foundry::RollData::default();
+	/// let evaluation = saikoro::foundry::parse("2d6[fire] + 1d4", &data)?.evaluate()?;
+	/// assert_eq!(evaluation.roll_groups[0].tag(), Some("fire"));
+	/// assert_eq!(evaluation.roll_groups[1].tag(), None);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn tag(&self) -> Option<&str>
+	{
+		self.details.as_ref()?.tag.as_deref()
+	}
+
+	pub(crate) fn set_tag(&mut self, tag: Box<str>)
+	{
+		self.details_mut().tag = Some(tag);
+	}
+
+	/// Returns whether the kept rolls of the [`RollGroup`] are read as the decimal digits of one
+	/// number, from first to last, rather than summed (eg. the two d6 of BCDice's `D66`, where
+	/// rolling a 3 then a 5 is 35). Ten-sided dice read a 10 as the digit 0, and a number whose
+	/// digits are all 0 as the highest number instead, so `2d10cat` is a percentile roll of 1 to
+	/// 100 from a tens die and a units die
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use saikoro::{evaluation::{Roll, RollGroup}, operator::DiceModifier, Dialect, Expression};
+	///
+	/// let evaluation = Expression::parse_with_dialect("D66", Dialect::BCDICE)?.evaluate()?;
+	/// let group = &evaluation.roll_groups[0];
+	/// assert!(group.is_concatenated());
+	/// assert_eq!(group.len(), 2);
+	/// assert!((11.0..=66.0).contains(&group.total()));
+	///
+	/// // a tens die of 10 and a units die of 7 is 07
+	/// let percentile = RollGroup::new(10, [10, 7].map(Roll::new));
+	/// let rng = &mut rand::thread_rng();
+	/// let percentile = DiceModifier::Concatenate.eval(percentile.into(), None, rng);
+	/// assert_eq!(percentile.value(), 7.0);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn is_concatenated(&self) -> bool
+	{
+		self.details.as_ref().is_some_and(|it| it.concatenated)
+	}
+
+	pub(crate) fn set_concatenated(&mut self)
+	{
+		self.details_mut().concatenated = true;
+	}
+
+	pub(crate) fn set_critical_success(&mut self, comp_op: CompOperator, value: f64)
+	{
+		self.details_mut().critical_success = Some((comp_op, value));
+	}
+
+	pub(crate) fn set_critical_failure(&mut self, comp_op: CompOperator, value: f64)
+	{
+		self.details_mut().critical_failure = Some((comp_op, value));
+	}
+
+	pub(crate) fn critical_success(&self) -> Option<(CompOperator, f64)>
+	{
+		self.details.as_ref()?.critical_success
+	}
+
+	pub(crate) fn critical_failure(&self) -> Option<(CompOperator, f64)>
+	{
+		self.details.as_ref()?.critical_failure
+	}
+
+	fn details_mut(&mut self) -> &mut GroupDetails
+	{
+		self.details.get_or_insert_with(Box::default)
+	}
+
+	/// Gets the sum of the [`modified_value`][Roll::modified_value]s of all [`Roll`]s in the
+	/// `self` [`RollGroup`], ignoring all [`Roll`]s whose values were filtered out. Returns 0 for an
+	/// empty [`RollGroup`], or one where every [`Roll`] has been filtered out
 	/// # Examples
 	/// ```rust
 	/// # use saikoro::evaluation::{Roll, RollGroup};
 	/// let roll_group = RollGroup::new(6, [5, 3, 1].map(Roll::new));
-	/// assert_eq!(roll_group.total(), 9);
+	/// assert_eq!(roll_group.total(), 9.0);
 	///
 	/// let roll_group = RollGroup::new(6, [Roll::new(5), Roll::new(3).into_removed(), Roll::new(1)]);
-	/// assert_eq!(roll_group.total(), 6);
+	/// assert_eq!(roll_group.total(), 6.0);
 	/// ```
-	pub fn total(&self) -> u32
+	pub fn total(&self) -> f64
+	{
+		match self.sampled
+		{
+			Some(pool) => pool.total,
+			None if self.is_concatenated() => self.concatenated_total(),
+			None => math::sum(self.kept_values()),
+		}
+	}
+
+	fn concatenated_total(&self) -> f64
+	{
+		let mut digits = 0;
+		let number = self.kept_values().fold(0.0, |number, value| {
+			digits += 1;
+			let digit = if self.faces == 10 { value % 10.0 } else { value };
+			number * 10.0 + digit
+		});
+		// ten-sided dice read all zeroes as the highest number (eg. 00 as 100 for percentile dice)
+		if self.faces == 10 && digits > 0 && number == 0.0
+		{
+			math::powf(10.0, f64::from(digits))
+		}
+		else
+		{
+			number
+		}
+	}
+
+	/// Returns the values of the [`RollGroup`] when it is used as a list, which are its kept rolls,
+	/// or the one number they make if it is [concatenated][RollGroup::is_concatenated]
+	pub(crate) fn list_values(&self) -> Vec<f64>
+	{
+		if self.is_concatenated()
+		{
+			return vec![self.total()];
+		}
+		self.kept_values().collect()
+	}
+
+	/// Returns the [`modified_value`][Roll::modified_value]s of the [`Roll`]s which have not been
+	/// removed, in the order they were rolled
+	pub(crate) fn kept_values(&self) -> impl Iterator<Item = f64> + '_
 	{
 		self.rolls
 			.iter()
 			.filter(|it| !it.is_removed())
-			.map(|it| it.original_value)
-			.sum::<u32>()
+			.map(Roll::modified_value)
 	}
 
 	/// Returns the number of elements in the [`RollGroup`]
@@ -53,7 +279,8 @@ impl RollGroup
 	/// ```
 	pub fn len(&self) -> usize
 	{
-		self.rolls.len()
+		self.sampled
+			.map_or(self.rolls.len(), |pool| pool.count as usize)
 	}
 
 	/// Returns `true` if the [`RollGroup`] has a length of 0
@@ -78,37 +305,89 @@ impl RollGroup
 	/// assert_eq!(iter.next(), Some(&Roll::new(2)));
 	/// assert_eq!(iter.next(), None);
 	/// ```
-	pub fn iter(&self) -> std::slice::Iter<Roll>
+	pub fn iter(&self) -> core::slice::Iter<'_, Roll>
 	{
 		self.rolls.iter()
 	}
 
-	pub(crate) fn iter_mut(&mut self) -> std::slice::IterMut<Roll>
+	/// Returns a new [`RollGroup`] with the same dice as `self`, where the modified value of each
+	/// [`Roll`] is the result of `modify` called with its current modified value
+	#[must_use]
+	pub(crate) fn map_modified<F>(&self, modify: F) -> Self
+	where
+		F: Fn(f64) -> f64,
+	{
+		Self {
+			rolls: self.rolls.iter().map(|roll| roll.into_modified(&modify)).collect(),
+			faces: self.faces,
+			span: self.span.clone(),
+			sampled: self.sampled,
+			details: self.details.clone(),
+		}
+	}
+
+	pub(crate) fn iter_mut(&mut self) -> core::slice::IterMut<'_, Roll>
 	{
 		self.rolls.iter_mut()
 	}
+
+	pub(crate) fn sort_rolls_by<F>(&mut self, compare: F)
+	where
+		F: FnMut(&Roll, &Roll) -> Ordering,
+	{
+		self.rolls.sort_by(compare);
+	}
+
+	pub(crate) fn extend<I>(&mut self, rolls: I)
+	where
+		I: IntoIterator<Item = Roll>,
+	{
+		let mut all_rolls = core::mem::take(&mut self.rolls).into_vec();
+		all_rolls.extend(rolls);
+		self.rolls = all_rolls.into_boxed_slice();
+	}
+
+	/// Writes the rolls of the group in square brackets (eg. `[3, ~~1~~, 5]`). A sampled group
+	/// writes its total instead (eg. `[Σ 3500211]`)
+	pub(crate) fn write_rolls(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		match self.sampled
+		{
+			Some(pool) => write!(f, "[Σ {}]", pool.total),
+			None => write!(
+				f,
+				"[{}]",
+				self.rolls
+					.iter()
+					.map(ToString::to_string)
+					.collect::<Vec<_>>()
+					.join(", ")
+			),
+		}
+	}
 }
 impl Display for RollGroup
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
-	{
-		write!(
-			f,
-			"{}d{}: [{}]",
-			self.len(),
-			self.faces,
-			self.rolls
-				.iter()
-				.map(ToString::to_string)
-				.collect::<Vec<_>>()
-				.join(", ")
-		)
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		write!(f, "{}d{}", self.len(), self.faces)?;
+		if let Some(tag) = self.tag()
+		{
+			write!(f, " ({tag})")?;
+		}
+		write!(f, ": ")?;
+		self.write_rolls(f)?;
+		if self.is_concatenated()
+		{
+			write!(f, " = {}", self.total())?;
+		}
+		Ok(())
 	}
 }
 impl<'a> IntoIterator for &'a RollGroup
 {
 	type Item = &'a Roll;
-	type IntoIter = std::slice::Iter<'a, Roll>;
+	type IntoIter = core::slice::Iter<'a, Roll>;
 
 	fn into_iter(self) -> Self::IntoIter
 	{
@@ -118,7 +397,7 @@ impl<'a> IntoIterator for &'a RollGroup
 impl<'a> IntoIterator for &'a mut RollGroup
 {
 	type Item = &'a mut Roll;
-	type IntoIter = std::slice::IterMut<'a, Roll>;
+	type IntoIter = core::slice::IterMut<'a, Roll>;
 
 	fn into_iter(self) -> Self::IntoIter
 	{
@@ -142,11 +421,13 @@ impl PartialOrd for RollGroup
 }
 
 /// A value representing an individual die roll with information on whether or not it should count
-/// toward the value of its parent [`RollGroup`]
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+/// toward the value of its parent [`RollGroup`]. Along with the natural value the die rolled, a
+/// [`Roll`] has a modified value, which is changed by element-wise operators (eg. `4d6 .+ 1`)
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
 pub struct Roll
 {
 	pub original_value: u32,
+	modified_value: f64,
 	removed: bool,
 }
 impl Roll
@@ -157,12 +438,52 @@ impl Roll
 	{
 		Self {
 			original_value: value,
+			modified_value: f64::from(value),
 			removed: false,
 		}
 	}
 
-	/// Gets the value of the [`Roll`] as an [`Option`]. Returns [`None`] if the [`Roll`] has been
-	/// removed, and otherwise returns the underlying value as [`Some(u32)`]
+	/// Returns the value the [`Roll`] counts as toward its parent [`RollGroup`]'s total, and which
+	/// filters and keep/drop modifiers compare. This is the natural value the die rolled, changed
+	/// by any element-wise operators applied to it
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let evaluation = saikoro::evaluate("3d6 .* 2")?;
+	/// for roll in evaluation.ungrouped_rolls()
+	/// {
+	///     assert_eq!(roll.modified_value(), f64::from(roll.original_value) * 2.0);
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn modified_value(&self) -> f64
+	{
+		self.modified_value
+	}
+
+	/// Returns whether or not the [`modified_value`][Roll::modified_value] of the [`Roll`] differs
+	/// from its natural value
+	pub fn is_modified(&self) -> bool
+	{
+		(self.modified_value - f64::from(self.original_value)).abs() >= f64::EPSILON
+	}
+
+	/// Returns a copy of `self` whose modified value is the result of `modify` called with the
+	/// current modified value
+	#[must_use]
+	pub(crate) fn into_modified<F>(self, modify: F) -> Self
+	where
+		F: FnOnce(f64) -> f64,
+	{
+		Self {
+			modified_value: modify(self.modified_value),
+			..self
+		}
+	}
+
+	/// Gets the natural value of the [`Roll`] as an [`Option`]. Returns [`None`] if the [`Roll`]
+	/// has been removed, and otherwise returns the underlying value as [`Some(u32)`]
 	/// # Examples
 	/// ```rust
 	/// # use saikoro::evaluation::Roll;
@@ -228,25 +549,28 @@ impl Roll
 }
 impl Display for Roll
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
 	{
-		let wrap_str = self.is_removed().then_some("~~").unwrap_or_default();
+		let wrap_str = if self.is_removed() { "~~" } else { "" };
 		write!(f, "{}{}{}", wrap_str, self.original_value, wrap_str)
 	}
 }
 
 /// A value that can be used to uniquely identify a roll.
 /// # Technical Details
-/// Currently, [`RollId`] acts simply as a wrapper around a randomly generated [`u64`], and
-/// *technically* has no guarantees against collisions, though they should be rare.
+/// Currently, [`RollId`] acts simply as a wrapper around a [`u64`] taken from a global counter, so
+/// each [`RollId`] is distinct from every other one created while the program runs (up to
+/// overflow of the counter on targets where it is smaller than 64 bits)
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
 pub struct RollId(u64);
 impl RollId
 {
-	/// Generates a new [`RollId`] with a random value
+	/// Generates a new [`RollId`], distinct from any generated before it
 	pub fn new() -> Self
 	{
-		Self(rand::random())
+		// usize rather than u64, as not every target has 64-bit atomics
+		static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+		Self(NEXT_ID.fetch_add(1, atomic::Ordering::Relaxed) as u64)
 	}
 }
 impl Default for RollId
@@ -299,6 +623,15 @@ pub struct DiceEvaluation
 {
 	pub value: f64,
 	pub roll_groups: Box<[RollGroup]>,
+	/// Which of the [`roll_groups`][DiceEvaluation::roll_groups] decided the count or faces of
+	/// other groups (eg. the `1d4` in `(1d4)d6`)
+	pub dependencies: Box<[RollDependency]>,
+	/// The part of the [`value`][DiceEvaluation::value] attributed to each tag, in the order the
+	/// tags first appear (see [`breakdown_by_tag`][DiceEvaluation::breakdown_by_tag])
+	pub tag_subtotals: Box<[(Box<str>, f64)]>,
+	/// Each roll on a table, in the order they were rolled (see
+	/// [`Expression::eval_with_tables`][crate::Expression::eval_with_tables])
+	pub table_rolls: Box<[TableRoll]>,
 }
 impl DiceEvaluation
 {
@@ -306,20 +639,89 @@ impl DiceEvaluation
 	{
 		self.roll_groups.iter().flat_map(RollGroup::iter)
 	}
+
+	/// Returns the [`value`][DiceEvaluation::value] split by tag, as each tag with its subtotal in
+	/// the order the tags first appear, followed by the untagged rest of the value with a tag of
+	/// [`None`] unless it is 0. For example, `2d6[fire] + 1d8[radiant] + 3` is split into the
+	/// total of each dice term and an untagged 3
+	///
+	/// The subtotal of a tag is the value of each subexpression with that tag, less the value of
+	/// any tagged subexpressions within it (so `(2d6[fire] + 3)[magic]` attributes 3 to `magic`).
+	/// Sums and differences keep the subtotals of their operands (so `10 - 1d6[fire]` attributes
+	/// minus the roll to `fire`), as do products and quotients with an untagged operand, which
+	/// scale them (so all of `2d6[fire] * 2` is `fire`). The value of any other operation on
+	/// tagged values is untagged, which includes products of two tagged values (eg.
+	/// `1d4[fire] * 1d6[cold]`), functions, comparisons, and the count or faces of a dice term
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let evaluation = saikoro::evaluate("2d6[fire] + 1d8[radiant] + 3")?;
+	/// let breakdown = evaluation.breakdown_by_tag();
+	/// assert_eq!(breakdown[0], (Some("fire"), evaluation.roll_groups[0].total()));
+	/// assert_eq!(breakdown[1], (Some("radiant"), evaluation.roll_groups[1].total()));
+	/// assert_eq!(breakdown[2], (None, 3.0));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn breakdown_by_tag(&self) -> Vec<(Option<&str>, f64)>
+	{
+		let mut breakdown = self
+			.tag_subtotals
+			.iter()
+			.map(|(tag, subtotal)| (Some(&**tag), *subtotal))
+			.collect::<Vec<_>>();
+		let untagged = self.value - breakdown.iter().map(|(_, it)| it).sum::<f64>();
+		// anything smaller is floating point error from splitting the value, not an untagged part
+		if untagged.abs() > 1e-9 * self.value.abs().max(1.0)
+		{
+			breakdown.push((None, untagged));
+		}
+		breakdown
+	}
+
+	/// Writes the [breakdown by tag][DiceEvaluation::breakdown_by_tag] in parentheses after a
+	/// space (eg. ` (fire: 7, radiant: 5, untagged: 3)`), or nothing if nothing is tagged
+	pub(crate) fn write_breakdown(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		if self.tag_subtotals.is_empty()
+		{
+			return Ok(());
+		}
+		let breakdown = self
+			.breakdown_by_tag()
+			.into_iter()
+			.map(|(tag, subtotal)| alloc::format!("{}: {subtotal}", tag.unwrap_or("untagged")))
+			.collect::<Vec<_>>();
+		write!(f, " ({})", breakdown.join(", "))
+	}
+
+	/// Writes each of the [`table_rolls`][DiceEvaluation::table_rolls] after a `; ` (eg. `;
+	/// encounter (5): 3 goblins`)
+	pub(crate) fn write_table_rolls(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		for table_roll in &self.table_rolls
+		{
+			write!(f, "; {table_roll}")?;
+		}
+		Ok(())
+	}
 }
 impl Display for DiceEvaluation
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
-	{
-		write!(
-			f,
-			"Total: {} [{}]",
-			self.value,
-			self.roll_groups
-				.iter()
-				.map(ToString::to_string)
-				.collect::<Vec<_>>()
-				.join(", ")
-		)
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		write!(f, "Total: {}", self.value)?;
+		self.write_breakdown(f)?;
+		write!(f, " [")?;
+		for (i, index) in self.final_groups().enumerate()
+		{
+			if i > 0
+			{
+				write!(f, ", ")?;
+			}
+			self.write_with_parents(f, index)?;
+		}
+		write!(f, "]")?;
+		self.write_table_rolls(f)
 	}
 }
@@ -0,0 +1,758 @@
+use crate::{
+	error::ParsingError,
+	evaluation::{self, DiceEvaluation, RepeatedEvaluation, Scope},
+	operator::{ListFunction, OpToken, UnaryOpToken, UnaryOperator},
+	parsing::{self, Node, RepeatedTree},
+	table::Tables,
+	tokenization::{Span, TokenStream},
+	CompiledExpression, Dialect, RangeRng,
+};
+#[cfg(feature = "std")]
+use rand::SeedableRng;
+use alloc::{boxed::Box, vec::Vec};
+use core::str::FromStr;
+
+/// A parsed dice expression which can be evaluated any number of times without being parsed again
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// use saikoro::Expression;
+///
+/// let fireball = Expression::parse("8d6")?;
+/// for _ in 0..3
+/// {
+///     let damage = fireball.evaluate()?;
+///     assert!(damage.value >= 8.0 && damage.value <= 48.0);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression
+{
+	tree: Node,
+	large_pool_threshold: u32,
+}
+impl Expression
+{
+	/// The default for [`with_large_pool_threshold`][Expression::with_large_pool_threshold]
+	pub const DEFAULT_LARGE_POOL_THRESHOLD: u32 = 10_000;
+
+	/// Parses a string in format similar to [Standard Dice Notation](https://en.wikipedia.org/wiki/Dice_notation)
+	/// into an [`Expression`]
+	/// # Errors
+	/// An error variant will be returned if the expression is unable to be parsed
+	pub fn parse(input: &str) -> Result<Self, ParsingError>
+	{
+		Self::parse_with_dialect(input, Dialect::SAIKORO)
+	}
+
+	/// Parses a string into an [`Expression`] following the syntax rules of `dialect` rather than
+	/// the default ones
+	/// # Errors
+	/// An error variant will be returned if the expression is unable to be parsed
+	pub fn parse_with_dialect(input: &str, dialect: Dialect) -> Result<Self, ParsingError>
+	{
+		Ok(Self::from(parsing::parse_tree_from(
+			&mut TokenStream::with_dialect(input, dialect),
+		)?))
+	}
+
+	/// Evaluates the expression with [`rand::thread_rng`]. Equivalent to
+	/// [`eval_with_rand`][Expression::eval_with_rand] called with `&mut rand::thread_rng()`
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	#[cfg(feature = "std")]
+	pub fn evaluate(&self) -> Result<DiceEvaluation, ParsingError>
+	{
+		self.eval_with_rand(&mut rand::thread_rng())
+	}
+
+	/// Evaluates the expression seeded with the given u64 as the seed
+	/// (see [`eval_with_rand`][Expression::eval_with_rand] for more information)
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	#[cfg(feature = "std")]
+	pub fn eval_with_seed(&self, seed: u64) -> Result<DiceEvaluation, ParsingError>
+	{
+		self.eval_with_rand(&mut rand::rngs::StdRng::seed_from_u64(seed))
+	}
+
+	/// Evaluates the expression with the given [`RangeRng`] (see
+	/// [`saikoro::eval_with_rand`][crate::eval_with_rand] for more information)
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	pub fn eval_with_rand<R>(&self, rand: &mut R) -> Result<DiceEvaluation, ParsingError>
+	where
+		R: RangeRng,
+	{
+		self.eval_in_scope(rand, &Scope::EMPTY)
+	}
+
+	/// Evaluates the expression with the given [`RangeRng`], rolling on `tables` for each
+	/// `table(name)` in it. The outcome of each of those rolls is recorded in the
+	/// [`table_rolls`][DiceEvaluation::table_rolls] of the result
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use saikoro::{table::Tables, Expression};
+	///
+	/// let tables = Tables::parse("table weather: 1d2\n1: rain\n2: {1d4} hours of sun")?;
+	/// let evaluation = Expression::parse("table(weather)")?
+	///     .eval_with_tables(&mut rand::thread_rng(), &tables)?;
+	/// let outcome = &evaluation.table_rolls[0].outcome;
+	/// assert!(outcome == "rain" || outcome.ends_with("hours of sun"));
+	/// # Ok(())
+	/// # }
+	/// ```
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error, including
+	/// if the expression rolls on a table which is not in `tables`
+	pub fn eval_with_tables<R>(
+		&self,
+		rand: &mut R,
+		tables: &Tables,
+	) -> Result<DiceEvaluation, ParsingError>
+	where
+		R: RangeRng,
+	{
+		self.eval_in_scope(rand, &Scope::EMPTY.with_tables(tables))
+	}
+
+	pub(crate) fn eval_in_scope<R>(
+		&self,
+		rand: &mut R,
+		scope: &Scope,
+	) -> Result<DiceEvaluation, ParsingError>
+	where
+		R: RangeRng,
+	{
+		evaluation::evaluate_tree(&self.tree, rand, scope, self.large_pool_threshold)
+	}
+
+	/// Evaluates the expression with [`rand::thread_rng`], returning only its value. Equivalent to
+	/// [`eval_value_with_rand`][Expression::eval_value_with_rand] called with
+	/// `&mut rand::thread_rng()`
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	#[cfg(feature = "std")]
+	pub fn evaluate_value(&self) -> Result<f64, ParsingError>
+	{
+		self.eval_value_with_rand(&mut rand::thread_rng())
+	}
+
+	/// Evaluates the expression with the given [`RangeRng`], returning only its value. The rolls
+	/// are not recorded at all, which makes this faster than
+	/// [`eval_with_rand`][Expression::eval_with_rand] when evaluating an expression many times (eg.
+	/// for a simulation). The same [`RangeRng`] produces the same value either way
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use rand::{rngs::StdRng, SeedableRng};
+	/// use saikoro::Expression;
+	///
+	/// let ability_score = Expression::parse("4d6kh3")?;
+	/// let mut rng = StdRng::seed_from_u64(2024);
+	/// let mean = (0..1000)
+	///     .map(|_| ability_score.eval_value_with_rand(&mut rng))
+	///     .sum::<Result<f64, _>>()?
+	///     / 1000.0;
+	/// assert!(mean > 11.0 && mean < 13.5);
+	/// # Ok(())
+	/// # }
+	/// ```
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	pub fn eval_value_with_rand<R>(&self, rand: &mut R) -> Result<f64, ParsingError>
+	where
+		R: RangeRng,
+	{
+		evaluation::evaluate_tree_value(
+			&self.tree,
+			rand,
+			&Scope::EMPTY,
+			self.large_pool_threshold,
+		)
+	}
+
+	/// Returns whether the whole expression is a roll on a table (eg. `table(loot)`)
+	pub(crate) fn is_table_roll(&self) -> bool
+	{
+		matches!(self.tree, Node::Table { .. })
+	}
+
+	/// Returns the lowest and highest values the expression can evaluate to, if they can be found
+	/// without evaluating it (eg. 2 and 12 for `2d6`)
+	pub(crate) fn bounds(&self) -> Option<(f64, f64)>
+	{
+		self.tree.bounds()
+	}
+
+	/// Lowers the expression into a [`CompiledExpression`], which evaluates to the same value as
+	/// [`eval_value_with_rand`][Expression::eval_value_with_rand] without walking the parse tree.
+	/// Worth it when evaluating the same expression a very large number of times
+	pub fn compile(&self) -> CompiledExpression
+	{
+		CompiledExpression::new(&self.tree, self.large_pool_threshold)
+	}
+
+	/// Returns a copy of the expression transformed for rolling a critical hit according to the
+	/// given [`CriticalMode`]
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use saikoro::{CriticalMode, Expression};
+	///
+	/// let damage = Expression::parse("2d6 + 1d8 + 3")?;
+	/// let critical = damage.critical(CriticalMode::DoubleDice);
+	/// assert_eq!(critical, Expression::parse("4d6 + 2d8 + 3")?);
+	/// # Ok(())
+	/// # }
+	/// ```
+	#[must_use]
+	pub fn critical(&self, mode: CriticalMode) -> Self
+	{
+		let tree = self.tree.clone();
+		Self {
+			large_pool_threshold: self.large_pool_threshold,
+			tree: match mode
+			{
+				CriticalMode::DoubleTotal => Node::Binary {
+					operator: OpToken::Multiply.into(),
+					span: tree.span().unwrap_or_default(),
+					left: Box::new(tree),
+					right: Box::new(Node::Leaf(2.0)),
+				},
+				mode =>
+				{
+					let mut tree = tree;
+					transform_dice(&mut tree, mode);
+					tree
+				}
+			},
+		}
+	}
+
+	/// Returns a copy of the expression which samples the total of a pool of more than `threshold`
+	/// dice directly, instead of rolling every die, when only its total is used (eg. the
+	/// `1000000d6` in `1000000d6 + 5`, but not in `1000000d6kh3`). Defaults to
+	/// [`DEFAULT_LARGE_POOL_THRESHOLD`][Expression::DEFAULT_LARGE_POOL_THRESHOLD], and
+	/// [`u32::MAX`] rolls every die
+	///
+	/// Rolling takes time and memory in proportion to the number of dice, while sampling takes
+	/// constant time and memory. The trade-off is that a sampled total comes from a normal
+	/// approximation to the sum of the dice, which has the exact mean and variance and is very
+	/// close for large pools, but is not exact, and that a sampled [`RollGroup`] has no individual
+	/// rolls (see [`RollGroup::is_sampled`])
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use saikoro::Expression;
+	///
+	/// let horde = Expression::parse("500d6 + 10")?;
+	/// assert!(!horde.evaluate()?.roll_groups[0].is_sampled());
+	/// assert!(horde.with_large_pool_threshold(100).evaluate()?.roll_groups[0].is_sampled());
+	/// # Ok(())
+	/// # }
+	/// ```
+	///
+	/// [`RollGroup`]: crate::evaluation::RollGroup
+	/// [`RollGroup::is_sampled`]: crate::evaluation::RollGroup::is_sampled
+	#[must_use]
+	pub fn with_large_pool_threshold(&self, threshold: u32) -> Self
+	{
+		Self {
+			large_pool_threshold: threshold,
+			..self.clone()
+		}
+	}
+}
+impl From<Node> for Expression
+{
+	fn from(tree: Node) -> Self
+	{
+		Self {
+			tree,
+			large_pool_threshold: Self::DEFAULT_LARGE_POOL_THRESHOLD,
+		}
+	}
+}
+impl FromStr for Expression
+{
+	type Err = ParsingError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Self::parse(s)
+	}
+}
+
+/// A parsed [`Expression`] along with the number of times it should be evaluated, such as
+/// `6x 4d6kh3` or `3#1d20+5`
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// use saikoro::RepeatedExpression;
+///
+/// let attacks = RepeatedExpression::parse("3x 1d20+5")?;
+/// assert_eq!(attacks.count(), 3);
+/// assert_eq!(attacks.evaluate()?.len(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepeatedExpression
+{
+	count: u32,
+	expression: Expression,
+	span: Span,
+}
+impl RepeatedExpression
+{
+	/// Parses an expression which may be prefixed by a repetition count followed by `x` or `#`.
+	/// Expressions without a repetition count are evaluated once
+	/// # Errors
+	/// An error variant will be returned if the expression is unable to be parsed
+	pub fn parse(input: &str) -> Result<Self, ParsingError>
+	{
+		Self::parse_with_dialect(input, Dialect::SAIKORO)
+	}
+
+	/// Parses a possibly repeated expression following the syntax rules of `dialect` rather than
+	/// the default ones
+	/// # Errors
+	/// An error variant will be returned if the expression is unable to be parsed
+	pub fn parse_with_dialect(input: &str, dialect: Dialect) -> Result<Self, ParsingError>
+	{
+		Ok(Self::from(parsing::parse_repeated_from(
+			&mut TokenStream::with_dialect(input, dialect),
+		)?))
+	}
+
+	/// Returns the number of times the expression will be evaluated
+	pub fn count(&self) -> u32
+	{
+		self.count
+	}
+
+	/// Returns the [`Expression`] which is repeated
+	pub fn expression(&self) -> &Expression
+	{
+		&self.expression
+	}
+
+	/// Evaluates the expression [`count`][RepeatedExpression::count] times with
+	/// [`rand::thread_rng`]
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	#[cfg(feature = "std")]
+	pub fn evaluate(&self) -> Result<RepeatedEvaluation, ParsingError>
+	{
+		self.eval_with_rand(&mut rand::thread_rng())
+	}
+
+	/// Evaluates the expression [`count`][RepeatedExpression::count] times with the given
+	/// [`RangeRng`]. The same [`RangeRng`] is used for every repetition
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error
+	pub fn eval_with_rand<R>(&self, rand: &mut R) -> Result<RepeatedEvaluation, ParsingError>
+	where
+		R: RangeRng,
+	{
+		self.eval_in_scope(rand, &Scope::EMPTY)
+	}
+
+	pub(crate) fn eval_in_scope<R>(
+		&self,
+		rand: &mut R,
+		scope: &Scope,
+	) -> Result<RepeatedEvaluation, ParsingError>
+	where
+		R: RangeRng,
+	{
+		let evaluations = (0..self.count)
+			.map(|_| self.expression.eval_in_scope(rand, scope))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(RepeatedEvaluation::new(evaluations, self.span.into()))
+	}
+
+	/// Returns a copy with the repeated expression transformed for rolling a critical hit (see
+	/// [`Expression::critical`])
+	#[must_use]
+	pub fn critical(&self, mode: CriticalMode) -> Self
+	{
+		Self {
+			expression: self.expression.critical(mode),
+			..self.clone()
+		}
+	}
+
+	/// Returns a copy with the given large pool threshold for the repeated expression (see
+	/// [`Expression::with_large_pool_threshold`])
+	#[must_use]
+	pub fn with_large_pool_threshold(&self, threshold: u32) -> Self
+	{
+		Self {
+			expression: self.expression.with_large_pool_threshold(threshold),
+			..self.clone()
+		}
+	}
+}
+impl From<RepeatedTree> for RepeatedExpression
+{
+	fn from(value: RepeatedTree) -> Self
+	{
+		Self {
+			count: value.count,
+			expression: Expression::from(value.tree),
+			span: value.span,
+		}
+	}
+}
+impl FromStr for RepeatedExpression
+{
+	type Err = ParsingError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Self::parse(s)
+	}
+}
+
+/// The ways an [`Expression`] can be transformed to roll a critical hit with
+/// [`Expression::critical`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CriticalMode
+{
+	/// Doubles the number of dice rolled by every dice term (eg. `2d6 + 1d8 + 3` becomes
+	/// `4d6 + 2d8 + 3`). The number of rolls kept or dropped by [`DiceModifier`]s is doubled as
+	/// well, so `4d6kh3` becomes `8d6kh6`
+	///
+	/// [`DiceModifier`]: crate::operator::DiceModifier
+	DoubleDice,
+	/// Adds the maximum possible value of every dice term to its rolls (eg. `2d6` becomes
+	/// `12 + 2d6`). The maximum values are added as extra [`Roll`]s of the dice term's
+	/// [`RollGroup`] after its modifiers are applied, with one for each roll they keep (eg. `4d6kh3`
+	/// keeps three rolls and then adds 18)
+	///
+	/// [`Roll`]: crate::evaluation::Roll
+	/// [`RollGroup`]: crate::evaluation::RollGroup
+	MaxPlusRoll,
+	/// Doubles the final value of the whole expression
+	DoubleTotal,
+}
+impl FromStr for CriticalMode
+{
+	type Err = ParseCriticalModeError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		match s
+		{
+			"double" | "dice" => Ok(Self::DoubleDice),
+			"max" | "brutal" => Ok(Self::MaxPlusRoll),
+			"total" => Ok(Self::DoubleTotal),
+			_ => Err(ParseCriticalModeError(s.into())),
+		}
+	}
+}
+
+/// An error to be returned when failing to parse a [`CriticalMode`]
+#[derive(Debug, thiserror::Error)]
+#[error("Unrecognized critical mode: {}", .0)]
+pub struct ParseCriticalModeError(Box<str>);
+
+// only dice terms which contribute to the total are transformed. the count, faces, and modifier
+// arguments of a dice term, and the right-hand side of a comparison, decide how the dice are
+// rolled rather than being rolled for damage themselves
+fn transform_dice(node: &mut Node, mode: CriticalMode)
+{
+	// the parser does not limit how long a chain of arithmetic can be (eg. `1 + 2 + ... + 5000`),
+	// so the operations down its left-hand side are transformed in a loop
+	let mut node = node;
+	loop
+	{
+		if is_dice(node) || is_term_link(node)
+		{
+			transform_term(node, mode);
+			return;
+		}
+		node = match node
+		{
+			Node::Binary { left, right, .. } =>
+			{
+				transform_dice(right, mode);
+				left
+			}
+			Node::Unary {
+				operator:
+					UnaryOperator {
+						token: UnaryOpToken::Not,
+						..
+					},
+				..
+			} => return,
+			Node::Unary { argument, .. } => argument,
+			Node::Conditional {
+				if_true, if_false, ..
+			} =>
+			{
+				transform_dice(if_true, mode);
+				if_false
+			}
+			// only the list a function is applied to is transformed, not any extra arguments such as
+			// the number of values for `highest`. counting rolls is not rolling for damage, so
+			// `count` is left alone
+			Node::Function {
+				function,
+				arguments,
+				..
+			} if *function != ListFunction::Count => match arguments.first_mut()
+			{
+				Some(list) => list,
+				None => return,
+			},
+			Node::Tagged { node, .. } => node,
+			// comparisons and logical operators only produce 0 or 1, and counts and indices pick
+			// out a single number, so their dice decide the result rather than being rolled for
+			// damage
+			Node::Comparison { .. } | Node::Logic { .. } | Node::Function { .. } | Node::Index { .. } =>
+			{
+				return
+			}
+			Node::ComparisonTernary { .. } | Node::Modifier { .. } | Node::Reroll { .. } =>
+			{
+				unreachable!("dice terms should have been transformed as a whole")
+			}
+			// already a critical dice term, so transforming it again would apply the critical twice
+			Node::PlusMaximum { .. } | Node::Variable { .. } | Node::Table { .. } | Node::Leaf(_) =>
+			{
+				return
+			}
+		};
+	}
+}
+
+/// Transforms a dice term along with the modifiers, filters, and element-wise operators applied to
+/// it. The maximum of each die is added after all of them, so that they only act on the dice which
+/// were actually rolled (eg. `4d6kh3` keeps three of its four rolls, and then adds 18)
+fn transform_term(term: &mut Node, mode: CriticalMode)
+{
+	let mut node = &mut *term;
+	while !is_dice(node)
+	{
+		if !is_term_link(node)
+		{
+			// modifiers applied to something other than a dice term (eg. a list), which has no
+			// maximum of its own
+			transform_dice(node, mode);
+			return;
+		}
+		node = match node
+		{
+			Node::Modifier {
+				modifier,
+				dice,
+				argument,
+				..
+			} =>
+			{
+				if modifier.counts_rolls()
+				{
+					double(
+						argument.get_or_insert_with(|| Box::new(Node::Leaf(1.0))),
+						mode,
+					);
+				}
+				dice
+			}
+			Node::Reroll { dice, .. } | Node::ComparisonTernary { dice, .. } => dice,
+			Node::Binary { left, right, .. } =>
+			{
+				transform_dice(right, mode);
+				left
+			}
+			_ => unreachable!("only the links of a dice term should be walked through"),
+		};
+	}
+
+	match node
+	{
+		Node::Binary { left, .. } => double(left, mode),
+		Node::Unary { argument, span, .. } if mode == CriticalMode::DoubleDice =>
+		{
+			*node = Node::Binary {
+				operator: OpToken::Dice.into(),
+				left: Box::new(Node::Leaf(2.0)),
+				right: Box::new(argument.take()),
+				span: *span,
+			};
+		}
+		_ => (),
+	}
+
+	if mode == CriticalMode::MaxPlusRoll
+	{
+		let span = term.span().unwrap_or_default();
+		*term = Node::PlusMaximum {
+			dice: Box::new(term.take()),
+			span,
+		};
+	}
+}
+
+/// Returns whether `node` is a dice operator, without any modifiers
+fn is_dice(node: &Node) -> bool
+{
+	match node
+	{
+		Node::Binary { operator, .. } => operator.token == OpToken::Dice,
+		Node::Unary { operator, .. } => operator.token == UnaryOpToken::Dice,
+		_ => false,
+	}
+}
+
+/// Returns whether `node` is applied to the rolls of a dice term below it
+fn is_term_link(node: &Node) -> bool
+{
+	match node
+	{
+		Node::Binary { operator, .. } => operator.token.is_element_wise(),
+		Node::Modifier { .. } | Node::Reroll { .. } | Node::ComparisonTernary { .. } => true,
+		_ => false,
+	}
+}
+
+/// Doubles `node` if `mode` is [`CriticalMode::DoubleDice`]. Constants are doubled directly so the
+/// transformed tree stays the same as if it had been written doubled
+fn double(node: &mut Node, mode: CriticalMode)
+{
+	if mode != CriticalMode::DoubleDice
+	{
+		return;
+	}
+	*node = match node.take()
+	{
+		Node::Leaf(n) => Node::Leaf(n * 2.0),
+		node => Node::Binary {
+			operator: OpToken::Multiply.into(),
+			span: node.span().unwrap_or_default(),
+			left: Box::new(node),
+			right: Box::new(Node::Leaf(2.0)),
+		},
+	};
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::test_helpers::{assert_approx_eq, RiggedRandom};
+
+	fn critical(input: &str, mode: CriticalMode) -> Expression
+	{
+		Expression::parse(input).unwrap().critical(mode)
+	}
+
+	#[test]
+	fn double_dice()
+	{
+		assert_eq!(
+			critical("2d6 + 1d8 + 3", CriticalMode::DoubleDice),
+			Expression::parse("4d6 + 2d8 + 3").unwrap()
+		);
+		assert_eq!(
+			critical("d20 + 1", CriticalMode::DoubleDice),
+			Expression::parse("2d20 + 1").unwrap()
+		);
+		assert_eq!(
+			critical("4d6kh3", CriticalMode::DoubleDice),
+			Expression::parse("8d6kh6").unwrap()
+		);
+		assert_eq!(
+			critical("(1d4)d6", CriticalMode::DoubleDice),
+			Expression::parse("((1d4) * 2)d6").unwrap()
+		);
+	}
+
+	#[test]
+	fn conditions_unchanged()
+	{
+		assert_eq!(
+			critical("if((1d20) > 10 and not 0, 1d8, 1d4)", CriticalMode::DoubleDice),
+			Expression::parse("if((1d20) > 10 and not 0, 2d8, 2d4)").unwrap()
+		);
+	}
+
+	#[test]
+	fn max_plus_roll()
+	{
+		let evaluation = critical("2d6 + 3", CriticalMode::MaxPlusRoll)
+			.eval_with_rand(&mut RiggedRandom::new([2, 5]))
+			.unwrap();
+		assert_approx_eq!(22.0, evaluation.value);
+		assert_eq!(
+			vec![2, 5, 6, 6],
+			evaluation
+				.ungrouped_rolls()
+				.map(|it| it.original_value)
+				.collect::<Vec<_>>()
+		);
+	}
+
+	#[test]
+	fn long_chains()
+	{
+		// chains of operators are transformed in a loop rather than recursively
+		let chain = format!("1d6{}", " + 1d6".repeat(100_000));
+		for mode in [
+			CriticalMode::DoubleDice,
+			CriticalMode::MaxPlusRoll,
+			CriticalMode::DoubleTotal,
+		]
+		{
+			let expression = critical(&chain, mode);
+			assert!(expression.clone() == expression);
+		}
+	}
+
+	#[test]
+	fn keep_modifiers()
+	{
+		// modifiers only act on the dice which were rolled, and not on any added maximums
+		for (input, mode, rolls, total) in [
+			("4d6kh3", CriticalMode::DoubleDice, &[1, 2, 3, 4, 5, 6, 1, 2][..], 22.0),
+			("4d6kl1", CriticalMode::DoubleDice, &[1, 2, 3, 4, 5, 6, 1, 2], 2.0),
+			("1d20adv", CriticalMode::DoubleDice, &[5, 12, 3, 18], 30.0),
+			("1d20dis", CriticalMode::DoubleDice, &[5, 12, 3, 18], 8.0),
+			("4d6kh3", CriticalMode::MaxPlusRoll, &[1, 2, 3, 4], 27.0),
+			("4d6kl1", CriticalMode::MaxPlusRoll, &[1, 2, 3, 4], 7.0),
+			("1d20adv", CriticalMode::MaxPlusRoll, &[5, 12], 32.0),
+			("1d20dis", CriticalMode::MaxPlusRoll, &[5, 12], 25.0),
+			("4d6kh3", CriticalMode::DoubleTotal, &[1, 2, 3, 4], 18.0),
+			("4d6kl1", CriticalMode::DoubleTotal, &[1, 2, 3, 4], 2.0),
+			("1d20adv", CriticalMode::DoubleTotal, &[5, 12], 24.0),
+			("1d20dis", CriticalMode::DoubleTotal, &[5, 12], 10.0),
+		]
+		{
+			let evaluation = critical(input, mode)
+				.eval_with_rand(&mut RiggedRandom::new(rolls.iter().copied()))
+				.unwrap();
+			assert!(
+				(evaluation.value - total).abs() < f64::EPSILON,
+				"{input} with {mode:?} totalled {}",
+				evaluation.value
+			);
+		}
+	}
+
+	#[test]
+	fn double_total()
+	{
+		let evaluation = critical("1d8 + 3", CriticalMode::DoubleTotal)
+			.eval_with_rand(&mut RiggedRandom::new([4]))
+			.unwrap();
+		assert_approx_eq!(14.0, evaluation.value);
+	}
+}
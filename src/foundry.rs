@@ -0,0 +1,441 @@
+//! A front end which reads [Foundry VTT](https://foundryvtt.com)'s roll formulas (eg.
+//! `2d6[fire] + 1d4[cold] + @mod`) into an [`Expression`], so that formulas written for Foundry
+//! modules can be evaluated without being rewritten
+//!
+//! Along with arithmetic (`+`, `-`, `*`, `/`, `%`, and `**`), the following are supported:
+//! - Data references (eg. `@mod` or `@abilities.str.mod`), which are replaced with values from a
+//!   [`RollData`] before the formula is read, as in Foundry
+//! - Flavor text (eg. the `[fire]` in `2d6[fire]`), which is kept as the
+//!   [`tag`][crate::evaluation::RollGroup::tag] of each [`RollGroup`] rolled by the term before it
+//! - Percentile dice (`d%`)
+//! - Keeping and dropping (`k`, `kh`, `kl`, `d`, `dh`, `dl`)
+//! - Exploding (`x`, `xo`), rerolls (`r`, `rr`), and counting successes (`cs`)
+//! - Dice pools (eg. `{1d20, 1d20}kh` or `{4d6, 3d8}cs>=12`), which keep, drop, or count the
+//!   successes of the totals of their terms
+//!
+//! As in Foundry, modifiers are applied in the order they are written, `r` rerolls each die once
+//! while `rr` rerolls until the comparison is no longer satisfied, and a number on its own
+//! compares for equality (eg. `1d20r1`). Foundry syntax which cannot be evaluated the same way,
+//! such as fate dice (`4dF`), coins (`dc`), counting failures (`cf`), `min`/`max` modifiers, and
+//! math functions (eg. `floor(@level / 2)`), produces an
+//! [`UnsupportedSyntaxError`][crate::error::UnsupportedSyntaxError] rather than a different result
+//! # Examples
+//! ```rust
+//! # fn main() -> Result<(), saikoro::error::ParsingError> {
+//! use saikoro::foundry::{self, RollData};
+//!
+//! let data = RollData::from_iter([("mod", RollData::from(3.0))]);
+//! let evaluation = foundry::parse("2d6[fire] + 1d4[cold] + @mod", &data)?.evaluate()?;
+//! assert!((6.0..=19.0).contains(&evaluation.value));
+//! assert_eq!(evaluation.roll_groups[0].tag(), Some("fire"));
+//! assert_eq!(evaluation.roll_groups[1].tag(), Some("cold"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`RollGroup`]: crate::evaluation::RollGroup
+use crate::{
+	error::{ParsingError, UnresolvedReferenceError},
+	inline_roll::{self, ModifierSyntax, Syntax},
+	operator::{CompOperator, DiceModifier},
+	Expression,
+};
+use alloc::{
+	collections::BTreeMap,
+	format,
+	string::{String, ToString},
+};
+
+/// Parses a Foundry roll formula (eg. `2d6[fire] + 1d4[cold] + @mod`) into an [`Expression`].
+/// Each data reference is replaced with the value at its path in `data` (see
+/// [`replace_formula_data`])
+///
+/// The spans of the expression's [`RollGroup`]s refer to the formula after its references are
+/// replaced, which is the same as `formula` if it has none
+/// # Errors
+/// An error variant will be returned if a data reference cannot be resolved, if the formula is
+/// not valid Foundry syntax, or if it uses Foundry syntax which is not supported (see the
+/// [module documentation][self])
+///
+/// [`RollGroup`]: crate::evaluation::RollGroup
+pub fn parse(formula: &str, data: &RollData) -> Result<Expression, ParsingError>
+{
+	let replaced = replace_formula_data(formula, data)?;
+	inline_roll::parse(&replaced, &SYNTAX)
+}
+
+/// Replaces every data reference in `formula` (eg. `@mod` or `@abilities.str.mod`) with the value
+/// at its path in `data`, where each `.` separated key names a value in the object before it. A
+/// [number][RollData::Number] replaces a reference as written, and a [formula][RollData::Formula]
+/// replaces it in parentheses, so that it is read as one term. As in Foundry, references in the
+/// replacement are not replaced
+/// # Errors
+/// Returns an [`UnresolvedReferenceError`] if there is no number or formula at the path of a
+/// reference
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// use saikoro::foundry::{self, RollData};
+///
+/// let abilities = RollData::from_iter([("str", RollData::from_iter([("mod", 3.0)]))]);
+/// let data = RollData::from_iter([
+///     ("abilities", abilities),
+///     ("sneak", RollData::from("3d6")),
+/// ]);
+/// let replaced = foundry::replace_formula_data("1d20 + @abilities.str.mod + @sneak", &data)?;
+/// assert_eq!(replaced, "1d20 + 3 + (3d6)");
+/// # Ok(())
+/// # }
+/// ```
+pub fn replace_formula_data(formula: &str, data: &RollData) -> Result<String, ParsingError>
+{
+	let mut replaced = String::with_capacity(formula.len());
+	let mut rest = formula;
+	while let Some(start) = rest.find('@')
+	{
+		replaced.push_str(&rest[..start]);
+		let path_length = rest[start + 1..]
+			.bytes()
+			.take_while(|it| it.is_ascii_alphanumeric() || matches!(it, b'_' | b'.' | b'-'))
+			.count();
+		let path = &rest[start + 1..start + 1 + path_length];
+		let value = match data.get(path)
+		{
+			Some(RollData::Number(number)) if *number < 0.0 => format!("({number})"),
+			Some(RollData::Number(number)) => number.to_string(),
+			Some(RollData::Formula(formula)) => format!("({formula})"),
+			Some(RollData::Object(_)) | None =>
+			{
+				return Err(UnresolvedReferenceError(path.into()).into());
+			}
+		};
+
+		replaced.push_str(&value);
+		rest = &rest[start + 1 + path_length..];
+	}
+	replaced.push_str(rest);
+
+	Ok(replaced)
+}
+
+/// A value in the data which references in a Foundry formula are resolved from, such as an
+/// actor's roll data. An [`Object`][RollData::Object] can be built from its keys and values with
+/// [`FromIterator`], and the [default][Default] is an empty object
+/// # Examples
+/// ```rust
+/// use saikoro::foundry::RollData;
+///
+/// let data = RollData::from_iter([
+///     ("attributes", RollData::from_iter([("prof", 2.0)])),
+///     ("level", RollData::from(5.0)),
+/// ]);
+/// assert_eq!(data.get("attributes.prof"), Some(&RollData::Number(2.0)));
+/// assert_eq!(data.get("level.prof"), None);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub enum RollData
+{
+	/// A number, which replaces a reference to it as written
+	Number(f64),
+	/// A formula (eg. `1d6 + 2`), which replaces a reference to it in parentheses
+	Formula(String),
+	/// Named values, each of which is referred to by its key following the object's own path
+	Object(BTreeMap<String, RollData>),
+}
+impl RollData
+{
+	/// Returns the value at `path`, where each `.` separated key names a value in the object
+	/// before it, or [`None`] if there is no such value
+	pub fn get(&self, path: &str) -> Option<&Self>
+	{
+		path.split('.').try_fold(self, |data, key| match data
+		{
+			Self::Object(values) => values.get(key),
+			_ => None,
+		})
+	}
+}
+impl Default for RollData
+{
+	fn default() -> Self
+	{
+		Self::Object(BTreeMap::new())
+	}
+}
+impl From<f64> for RollData
+{
+	fn from(value: f64) -> Self
+	{
+		Self::Number(value)
+	}
+}
+impl From<&str> for RollData
+{
+	fn from(value: &str) -> Self
+	{
+		Self::Formula(value.into())
+	}
+}
+impl From<String> for RollData
+{
+	fn from(value: String) -> Self
+	{
+		Self::Formula(value)
+	}
+}
+impl<K, V> FromIterator<(K, V)> for RollData
+where
+	K: Into<String>,
+	V: Into<Self>,
+{
+	fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self
+	{
+		Self::Object(
+			iter.into_iter()
+				.map(|(key, value)| (key.into(), value.into()))
+				.collect(),
+		)
+	}
+}
+
+const SYNTAX: Syntax = Syntax {
+	comparisons: &[
+		(">=", CompOperator::GreaterOrEqual),
+		("<=", CompOperator::LessOrEqual),
+		(">", CompOperator::GreaterThan),
+		("<", CompOperator::LessThan),
+		("=", CompOperator::Equals),
+	],
+	dice_modifiers: &[
+		("xo", ModifierSyntax::Point(DiceModifier::ExplodeOnce)),
+		("x", ModifierSyntax::Point(DiceModifier::Explode)),
+		("rr", ModifierSyntax::Point(DiceModifier::Reroll)),
+		("r", ModifierSyntax::Point(DiceModifier::RerollOnce)),
+		("kh", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("kl", ModifierSyntax::Count(DiceModifier::KeepLowest)),
+		("k", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("dh", ModifierSyntax::Count(DiceModifier::DropHighest)),
+		("dl", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("df", ModifierSyntax::Unsupported),
+		("d", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("cs", ModifierSyntax::Successes),
+		("cf", ModifierSyntax::Unsupported),
+		("sf", ModifierSyntax::Unsupported),
+		("ms", ModifierSyntax::Unsupported),
+		("min", ModifierSyntax::Unsupported),
+		("max", ModifierSyntax::Unsupported),
+		("even", ModifierSyntax::Unsupported),
+		("odd", ModifierSyntax::Unsupported),
+	],
+	group_modifiers: &[
+		("kh", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("kl", ModifierSyntax::Count(DiceModifier::KeepLowest)),
+		("k", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("dh", ModifierSyntax::Count(DiceModifier::DropHighest)),
+		("dl", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("d", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("cs", ModifierSyntax::Successes),
+		("cf", ModifierSyntax::Unsupported),
+	],
+	bare_successes: false,
+	roll20_order: false,
+	merged_rerolls: false,
+	inline_rolls: false,
+	percentile_dice: true,
+	single_term_groups: false,
+	tags: true,
+	unsupported_operands: &[],
+};
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::test_helpers::RiggedRandom;
+	use alloc::vec::Vec;
+
+	fn data() -> RollData
+	{
+		RollData::from_iter([
+			("mod", RollData::from(3.0)),
+			("penalty", RollData::from(-2.0)),
+			("sneak", RollData::from("2d6")),
+			(
+				"abilities",
+				RollData::from_iter([("dex", RollData::from_iter([("mod", 4.0)]))]),
+			),
+		])
+	}
+
+	fn roll(formula: &str, rolls: &[u32]) -> f64
+	{
+		parse(formula, &data())
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new(rolls.iter().copied()))
+			.unwrap()
+			.value
+	}
+
+	#[test]
+	fn arithmetic()
+	{
+		assert_eq!(roll("1 + 2 * 3", &[]), 7.0);
+		assert_eq!(roll("(1 + 2) * 3", &[]), 9.0);
+		assert_eq!(roll("2 ** 3", &[]), 8.0);
+		assert_eq!(roll("10 % 4 - -1", &[]), 3.0);
+	}
+
+	#[test]
+	fn dice()
+	{
+		assert_eq!(roll("3d6", &[1, 5, 6]), 12.0);
+		assert_eq!(roll("d20 + 5", &[14]), 19.0);
+		assert_eq!(roll("(1 + 1)d6", &[3, 4]), 7.0);
+		assert_eq!(roll("1d%", &[73]), 73.0);
+	}
+
+	#[test]
+	fn data_references()
+	{
+		assert_eq!(roll("1d20 + @mod", &[10]), 13.0);
+		assert_eq!(roll("1d20 + @abilities.dex.mod", &[10]), 14.0);
+		assert_eq!(roll("1d20 - @penalty", &[10]), 12.0);
+		assert_eq!(roll("@sneak * 2", &[3, 4]), 14.0);
+		assert_eq!(roll("(@mod)d6", &[1, 2, 3]), 6.0);
+
+		for path in ["missing", "abilities.dex", "mod.value"]
+		{
+			match parse(&format!("1d20 + @{path}"), &data())
+			{
+				Err(ParsingError::UnresolvedReference(error)) => assert_eq!(&*error.0, path),
+				other => panic!("expected {path} to be unresolved, found {other:?}"),
+			}
+		}
+	}
+
+	#[test]
+	fn flavor_text()
+	{
+		let evaluation = parse("2d6[fire] + 1d4[cold] + 1d8 + 5[bonus]", &data())
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new([3, 4, 2, 8]))
+			.unwrap();
+		assert_eq!(evaluation.value, 22.0);
+		let tags = evaluation
+			.roll_groups
+			.iter()
+			.map(|it| it.tag())
+			.collect::<Vec<_>>();
+		assert_eq!(tags, [Some("fire"), Some("cold"), None]);
+
+		// a formula from the data is tagged as a whole, and modifiers come before flavor text
+		let evaluation = parse("@sneak[piercing] + 4d6kh3[radiant damage]", &data())
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new([1, 2, 3, 1, 6, 2]))
+			.unwrap();
+		assert_eq!(evaluation.value, 14.0);
+		let tags = evaluation
+			.roll_groups
+			.iter()
+			.map(|it| it.tag())
+			.collect::<Vec<_>>();
+		assert_eq!(tags, [Some("piercing"), Some("radiant damage")]);
+	}
+
+	#[test]
+	fn keep_and_drop()
+	{
+		assert_eq!(roll("4d6kh3", &[3, 1, 6, 2]), 11.0);
+		assert_eq!(roll("2d20kl", &[4, 17]), 4.0);
+		assert_eq!(roll("2d20k", &[4, 17]), 17.0);
+		assert_eq!(roll("4d6dl", &[3, 1, 6, 2]), 11.0);
+		assert_eq!(roll("4d6d2", &[3, 1, 6, 2]), 9.0);
+		assert_eq!(roll("4d6dh1", &[3, 1, 6, 2]), 6.0);
+	}
+
+	#[test]
+	fn exploding()
+	{
+		// the 20 explodes into another 20, which explodes into a 3
+		assert_eq!(roll("1d20x", &[20, 20, 3]), 43.0);
+		assert_eq!(roll("1d20xo", &[20, 20]), 40.0);
+		// `>` does not include the value itself, unlike in Roll20
+		assert_eq!(roll("2d6x>5", &[5, 6, 1]), 12.0);
+		assert_eq!(roll("2d6x>=5", &[5, 2, 1]), 8.0);
+	}
+
+	#[test]
+	fn rerolls()
+	{
+		// `r` rerolls the 1 once, even though the new roll is another 1
+		assert_eq!(roll("2d6r", &[1, 5, 1]), 6.0);
+		assert_eq!(roll("2d6rr", &[1, 5, 1, 4]), 9.0);
+		assert_eq!(roll("2d6r<3", &[2, 5, 4]), 9.0);
+		// modifiers apply in the order written, so the 1 is kept and then rerolled
+		assert_eq!(roll("2d20kh1r1", &[1, 1, 12]), 12.0);
+	}
+
+	#[test]
+	fn successes()
+	{
+		assert_eq!(roll("5d10cs>=7", &[10, 3, 7, 9, 1]), 3.0);
+		assert_eq!(roll("3d6cs6", &[6, 3, 6]), 2.0);
+		assert_eq!(roll("3d6xcs>5", &[6, 5, 1, 3]), 1.0);
+	}
+
+	#[test]
+	fn pools()
+	{
+		assert_eq!(roll("{1d20, 1d20}kh", &[5, 12]), 12.0);
+		assert_eq!(roll("{1d20, 1d20}kl", &[5, 12]), 5.0);
+		assert_eq!(roll("{4d6, 3d8}kh", &[1, 2, 3, 4, 8, 8, 8]), 24.0);
+		assert_eq!(roll("{1d20 + 5, 1d20 + 5, 1d20 + 5}cs>=15", &[5, 12, 9]), 1.0);
+		assert_eq!(roll("{2d6, 5}", &[3, 4]), 12.0);
+		// a pool of one term keeps or drops its total, not its rolls
+		assert_eq!(roll("{4d6}kh3", &[3, 1, 6, 2]), 12.0);
+	}
+
+	#[test]
+	fn unsupported()
+	{
+		for (formula, syntax) in [
+			("4dF", "dF"),
+			("1dc", "dc"),
+			("6d6cf<2", "cf"),
+			("1d20min10", "min"),
+			("3d6df", "df"),
+			("1d20 + floor(@mod / 2)", "floor"),
+			("3d6cs>4kh2", "kh2"),
+			("{1d20, 1d20}kh1cs>10", "{1d20, 1d20}kh1cs>10"),
+		]
+		{
+			match parse(formula, &data())
+			{
+				Err(ParsingError::UnsupportedSyntax(error)) => assert_eq!(&*error.syntax, syntax),
+				other => panic!("expected {syntax} in {formula} to be unsupported, got {other:?}"),
+			}
+		}
+	}
+
+	#[test]
+	fn syntax_errors()
+	{
+		for (formula, index) in [
+			("1d20 +", 6),
+			("2d", 2),
+			("1d20 3", 5),
+			("2d6[fire", 8),
+			("[[1d6]]", 0),
+		]
+		{
+			match parse(formula, &data())
+			{
+				Err(ParsingError::ExpectedSyntax(error)) =>
+				{
+					assert_eq!(error.index, index, "{formula}");
+				}
+				other => panic!("expected a syntax error in {formula}, found {other:?}"),
+			}
+		}
+	}
+}
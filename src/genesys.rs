@@ -0,0 +1,694 @@
+//! Narrative dice pools following Genesys and Fantasy Flight Games' Star Wars roleplaying games,
+//! whose dice have faces with symbols rather than numbers
+//!
+//! Positive dice show successes, advantages, and triumphs, and negative dice show failures,
+//! threats, and despairs. Rolling a pool cancels each failure against a success and each threat
+//! against an advantage (see [`NetSymbols`]), and the roll succeeds if any successes are left.
+//! A triumph also counts as a success and a despair as a failure, but they are never cancelled
+//! themselves
+//!
+//! A pool is written as a count followed by the letter of each kind of die (eg. `2A1P2D`), where
+//! the letters are `A` for ability, `P` for proficiency, and `B` for boost dice, and `D` for
+//! difficulty, `C` for challenge, and `S` for setback dice. A letter without a count is a single
+//! die. Dice with other faces can be added with [`DicePool::with_dice`]
+//! # Examples
+//! ```rust
+//! # fn main() -> Result<(), saikoro::error::ParsingError> {
+//! use saikoro::genesys::DicePool;
+//!
+//! let pool = DicePool::parse("2A1P2D")?;
+//! let roll = pool.roll();
+//! println!("{roll}"); // eg. "Ability: SA, Ability: -, Proficiency: T, ... = 1 success, 1 triumph"
+//! assert_eq!(roll.faces.len(), 5);
+//!
+//! let chance = pool.probability_of_success();
+//! assert!(chance > 0.0 && chance < 1.0);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{error::ParsingError, inline_roll::expected_at, math, RangeRng};
+use alloc::{boxed::Box, collections::BTreeMap, format, string::String, vec::Vec};
+use core::{fmt::Display, ops::Add, str::FromStr};
+
+/// The number of each symbol on a face, or on all the faces of a roll before cancellation
+///
+/// Written as a letter for each symbol (eg. `SSA` for two successes and an advantage): `S` for a
+/// success, `A` for an advantage, `T` for a triumph, `F` for a failure, `H` for a threat, and `D`
+/// for a despair. A blank face is written as `-`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Symbols
+{
+	pub successes: u32,
+	pub advantages: u32,
+	pub triumphs: u32,
+	pub failures: u32,
+	pub threats: u32,
+	pub despairs: u32,
+}
+impl Symbols
+{
+	/// A face without any symbols
+	pub const BLANK: Self = Self {
+		successes: 0,
+		advantages: 0,
+		triumphs: 0,
+		failures: 0,
+		threats: 0,
+		despairs: 0,
+	};
+
+	/// Parses symbols written as a letter for each symbol (eg. `SSA`), or `-` for none
+	/// # Errors
+	/// An error variant will be returned if `input` contains anything other than symbol letters,
+	/// `-`, or whitespace
+	pub fn parse(input: &str) -> Result<Self, ParsingError>
+	{
+		let mut symbols = Self::BLANK;
+		for (index, char) in input.char_indices()
+		{
+			let count = match char.to_ascii_uppercase()
+			{
+				'S' => &mut symbols.successes,
+				'A' => &mut symbols.advantages,
+				'T' => &mut symbols.triumphs,
+				'F' => &mut symbols.failures,
+				'H' => &mut symbols.threats,
+				'D' => &mut symbols.despairs,
+				'-' => continue,
+				char if char.is_whitespace() => continue,
+				_ => return Err(expected_at(input, index, SYMBOL_LETTERS)),
+			};
+			*count += 1;
+		}
+		Ok(symbols)
+	}
+
+	/// Returns the symbols left after cancelling failures against successes and threats against
+	/// advantages
+	pub fn net(&self) -> NetSymbols
+	{
+		let successes = signed(self.successes + self.triumphs);
+		let failures = signed(self.failures + self.despairs);
+		NetSymbols {
+			successes: successes - failures,
+			advantages: signed(self.advantages) - signed(self.threats),
+			triumphs: self.triumphs,
+			despairs: self.despairs,
+		}
+	}
+}
+impl Add for Symbols
+{
+	type Output = Self;
+
+	fn add(self, rhs: Self) -> Self::Output
+	{
+		Self {
+			successes: self.successes + rhs.successes,
+			advantages: self.advantages + rhs.advantages,
+			triumphs: self.triumphs + rhs.triumphs,
+			failures: self.failures + rhs.failures,
+			threats: self.threats + rhs.threats,
+			despairs: self.despairs + rhs.despairs,
+		}
+	}
+}
+impl FromStr for Symbols
+{
+	type Err = ParsingError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Self::parse(s)
+	}
+}
+impl Display for Symbols
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		if *self == Self::BLANK
+		{
+			return write!(f, "-");
+		}
+		for (count, letter) in [
+			(self.successes, "S"),
+			(self.advantages, "A"),
+			(self.triumphs, "T"),
+			(self.failures, "F"),
+			(self.threats, "H"),
+			(self.despairs, "D"),
+		]
+		{
+			for _ in 0..count
+			{
+				write!(f, "{letter}")?;
+			}
+		}
+		Ok(())
+	}
+}
+
+const SYMBOL_LETTERS: &str = "a symbol (`S`, `A`, `T`, `F`, `H`, `D`, or `-`)";
+
+fn signed(count: u32) -> i64
+{
+	i64::from(count)
+}
+
+/// The symbols of a roll after cancellation. Triumphs and despairs are counted in
+/// [`successes`][NetSymbols::successes], but are also kept, as they are never cancelled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetSymbols
+{
+	/// Successes less failures, counting triumphs as successes and despairs as failures. Negative
+	/// for net failures
+	pub successes: i64,
+	/// Advantages less threats. Negative for net threats
+	pub advantages: i64,
+	pub triumphs: u32,
+	pub despairs: u32,
+}
+impl NetSymbols
+{
+	/// Returns whether any successes are left after cancellation
+	pub fn is_success(&self) -> bool
+	{
+		self.successes > 0
+	}
+}
+impl Display for NetSymbols
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		let mut parts = Vec::new();
+		parts.push(if self.successes < 0
+		{
+			counted(self.successes.unsigned_abs(), "failure", "failures")
+		}
+		else
+		{
+			counted(self.successes.unsigned_abs(), "success", "successes")
+		});
+		if self.advantages != 0
+		{
+			parts.push(if self.advantages < 0
+			{
+				counted(self.advantages.unsigned_abs(), "threat", "threats")
+			}
+			else
+			{
+				counted(self.advantages.unsigned_abs(), "advantage", "advantages")
+			});
+		}
+		if self.triumphs > 0
+		{
+			parts.push(counted(self.triumphs.into(), "triumph", "triumphs"));
+		}
+		if self.despairs > 0
+		{
+			parts.push(counted(self.despairs.into(), "despair", "despairs"));
+		}
+		write!(f, "{}", parts.join(", "))
+	}
+}
+
+fn counted(count: u64, singular: &str, plural: &str) -> String
+{
+	format!("{count} {}", if count == 1 { singular } else { plural })
+}
+
+/// A die whose faces show [`Symbols`], such as the predefined [`ability`][SymbolDie::ability]
+/// die, or a die with user-defined faces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolDie
+{
+	name: Box<str>,
+	faces: Box<[Symbols]>,
+}
+impl SymbolDie
+{
+	/// Creates a die with the given name and faces, which are equally likely to be rolled
+	/// # Panics
+	/// Panics if `faces` is empty
+	pub fn new<I>(name: &str, faces: I) -> Self
+	where
+		I: IntoIterator<Item = Symbols>,
+	{
+		let faces = faces.into_iter().collect::<Box<[_]>>();
+		assert!(!faces.is_empty(), "a symbol die should have at least one face");
+		Self {
+			name: name.into(),
+			faces,
+		}
+	}
+
+	/// The green eight-sided ability die (`A`)
+	pub fn ability() -> Self
+	{
+		Self::new("Ability", ABILITY)
+	}
+
+	/// The yellow twelve-sided proficiency die (`P`), the only die with a triumph
+	pub fn proficiency() -> Self
+	{
+		Self::new("Proficiency", PROFICIENCY)
+	}
+
+	/// The blue six-sided boost die (`B`)
+	pub fn boost() -> Self
+	{
+		Self::new("Boost", BOOST)
+	}
+
+	/// The purple eight-sided difficulty die (`D`)
+	pub fn difficulty() -> Self
+	{
+		Self::new("Difficulty", DIFFICULTY)
+	}
+
+	/// The red twelve-sided challenge die (`C`), the only die with a despair
+	pub fn challenge() -> Self
+	{
+		Self::new("Challenge", CHALLENGE)
+	}
+
+	/// The black six-sided setback die (`S`)
+	pub fn setback() -> Self
+	{
+		Self::new("Setback", SETBACK)
+	}
+
+	pub fn name(&self) -> &str
+	{
+		&self.name
+	}
+
+	pub fn faces(&self) -> &[Symbols]
+	{
+		&self.faces
+	}
+
+	/// Returns the predefined die written with the given letter in pool notation
+	fn from_letter(letter: char) -> Option<Self>
+	{
+		match letter.to_ascii_uppercase()
+		{
+			'A' => Some(Self::ability()),
+			'P' => Some(Self::proficiency()),
+			'B' => Some(Self::boost()),
+			'D' => Some(Self::difficulty()),
+			'C' => Some(Self::challenge()),
+			'S' => Some(Self::setback()),
+			_ => None,
+		}
+	}
+}
+
+const fn face(successes: u32, advantages: u32, failures: u32, threats: u32) -> Symbols
+{
+	Symbols {
+		successes,
+		advantages,
+		failures,
+		threats,
+		..Symbols::BLANK
+	}
+}
+const BLANK: Symbols = Symbols::BLANK;
+const TRIUMPH: Symbols = Symbols {
+	triumphs: 1,
+	..Symbols::BLANK
+};
+const DESPAIR: Symbols = Symbols {
+	despairs: 1,
+	..Symbols::BLANK
+};
+const S: Symbols = face(1, 0, 0, 0);
+const A: Symbols = face(0, 1, 0, 0);
+const F: Symbols = face(0, 0, 1, 0);
+const H: Symbols = face(0, 0, 0, 1);
+
+const ABILITY: [Symbols; 8] = [
+	BLANK,
+	S,
+	S,
+	face(2, 0, 0, 0),
+	A,
+	A,
+	face(1, 1, 0, 0),
+	face(0, 2, 0, 0),
+];
+const PROFICIENCY: [Symbols; 12] = [
+	BLANK,
+	S,
+	S,
+	face(2, 0, 0, 0),
+	face(2, 0, 0, 0),
+	A,
+	face(1, 1, 0, 0),
+	face(1, 1, 0, 0),
+	face(1, 1, 0, 0),
+	face(0, 2, 0, 0),
+	face(0, 2, 0, 0),
+	TRIUMPH,
+];
+const BOOST: [Symbols; 6] = [BLANK, BLANK, S, face(1, 1, 0, 0), face(0, 2, 0, 0), A];
+const DIFFICULTY: [Symbols; 8] = [
+	BLANK,
+	F,
+	face(0, 0, 2, 0),
+	H,
+	H,
+	H,
+	face(0, 0, 0, 2),
+	face(0, 0, 1, 1),
+];
+const CHALLENGE: [Symbols; 12] = [
+	BLANK,
+	F,
+	F,
+	face(0, 0, 2, 0),
+	face(0, 0, 2, 0),
+	H,
+	H,
+	face(0, 0, 1, 1),
+	face(0, 0, 1, 1),
+	face(0, 0, 0, 2),
+	face(0, 0, 0, 2),
+	DESPAIR,
+];
+const SETBACK: [Symbols; 6] = [BLANK, BLANK, F, F, H, H];
+
+/// A pool of [`SymbolDie`]s which are rolled together, such as `2A1P2D`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DicePool
+{
+	dice: Vec<(SymbolDie, u32)>,
+}
+impl DicePool
+{
+	/// The most dice a pool read by [`parse`][DicePool::parse] may have in total
+	pub const MAX_DICE: u32 = 1_000;
+
+	/// Creates a pool without any dice
+	pub fn new() -> Self
+	{
+		Self::default()
+	}
+
+	/// Parses a pool written as a count followed by the letter of each kind of die (eg. `2A1P2D`).
+	/// Letters may be either case, and a letter without a count is a single die
+	/// # Errors
+	/// An error variant will be returned if `input` is empty, contains anything other than
+	/// counts, die letters, and whitespace, or has more than [`MAX_DICE`][DicePool::MAX_DICE] dice
+	pub fn parse(input: &str) -> Result<Self, ParsingError>
+	{
+		const DIE_LETTERS: &str = "a die letter (`A`, `P`, `B`, `D`, `C`, or `S`)";
+		const SMALLER_POOL: &str = "a pool of at most 1000 dice";
+
+		let mut pool = Self::new();
+		let mut total = 0_u32;
+		let mut chars = input.char_indices();
+		while let Some((index, char)) = chars.next()
+		{
+			if char.is_whitespace()
+			{
+				continue;
+			}
+
+			let mut count = None;
+			let mut next = Some((index, char));
+			while let Some((index, digit)) = next.filter(|(_, it)| it.is_ascii_digit())
+			{
+				let digit = digit.to_digit(10).expect("an ASCII digit should be a digit");
+				let digits = count
+					.unwrap_or(0_u32)
+					.checked_mul(10)
+					.and_then(|it| it.checked_add(digit))
+					.filter(|it| *it <= Self::MAX_DICE - total)
+					.ok_or_else(|| expected_at(input, index, SMALLER_POOL))?;
+				count = Some(digits);
+				next = chars.next();
+			}
+
+			let die = next
+				.and_then(|(_, letter)| SymbolDie::from_letter(letter))
+				.ok_or_else(|| {
+					let index = next.map_or(input.len(), |(index, _)| index);
+					expected_at(input, index, DIE_LETTERS)
+				})?;
+			let count = count.unwrap_or(1);
+			total += count;
+			if total > Self::MAX_DICE
+			{
+				return Err(expected_at(input, index, SMALLER_POOL));
+			}
+			pool = pool.with_dice(die, count);
+		}
+
+		if pool.dice.is_empty()
+		{
+			return Err(expected_at(input, input.len(), DIE_LETTERS));
+		}
+		Ok(pool)
+	}
+
+	/// Returns a copy of the pool with `count` more of the given die, which may have user-defined
+	/// faces
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use saikoro::genesys::{DicePool, SymbolDie, Symbols};
+	///
+	/// let faces = ["-", "S", "SS", "T"].map(Symbols::parse).into_iter();
+	/// let lucky = SymbolDie::new("Lucky", faces.collect::<Result<Vec<_>, _>>()?);
+	/// let pool = DicePool::parse("1D")?.with_dice(lucky, 2);
+	/// assert_eq!(pool.roll().faces.len(), 3);
+	/// # Ok(())
+	/// # }
+	/// ```
+	#[must_use]
+	pub fn with_dice(mut self, die: SymbolDie, count: u32) -> Self
+	{
+		self.dice.push((die, count));
+		self
+	}
+
+	/// Returns each die in the pool, in the order they are rolled
+	pub fn dice(&self) -> impl Iterator<Item = &SymbolDie>
+	{
+		self.dice
+			.iter()
+			.flat_map(|(die, count)| core::iter::repeat_n(die, *count as usize))
+	}
+
+	/// Rolls every die in the pool with [`rand::thread_rng`]
+	#[cfg(feature = "std")]
+	pub fn roll(&self) -> PoolRoll
+	{
+		self.roll_with_rand(&mut rand::thread_rng())
+	}
+
+	/// Rolls every die in the pool with the given [`RangeRng`]
+	pub fn roll_with_rand<R>(&self, rand: &mut R) -> PoolRoll
+	where
+		R: RangeRng,
+	{
+		let faces = self
+			.dice()
+			.map(|die| {
+				let faces = u32::try_from(die.faces.len()).unwrap_or(u32::MAX);
+				let face = die.faces[rand.rng_range(0..faces) as usize];
+				(die.name.clone(), face)
+			})
+			.collect();
+		PoolRoll { faces }
+	}
+
+	/// Returns the probability of each number of net successes (negative for net failures), in
+	/// ascending order of net successes. Calculated exactly from the faces of the dice, rather than
+	/// by rolling them
+	pub fn success_distribution(&self) -> Vec<(i64, f64)>
+	{
+		let mut distribution = BTreeMap::from([(0_i64, 1.0)]);
+		for die in self.dice()
+		{
+			let chance = 1.0 / die.faces.len() as f64;
+			let mut next = BTreeMap::new();
+			for (successes, probability) in &distribution
+			{
+				for face in die.faces.iter()
+				{
+					*next.entry(successes + face.net().successes).or_insert(0.0) +=
+						probability * chance;
+				}
+			}
+			distribution = next;
+		}
+		distribution.into_iter().collect()
+	}
+
+	/// Returns the probability that a roll of the pool is a success, with at least one success
+	/// left after cancellation (see [`success_distribution`][DicePool::success_distribution])
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// use saikoro::genesys::DicePool;
+	///
+	/// // half of the faces of an ability die have a success
+	/// assert_eq!(DicePool::parse("1A")?.probability_of_success(), 0.5);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn probability_of_success(&self) -> f64
+	{
+		math::sum(
+			self.success_distribution()
+				.into_iter()
+				.filter(|(successes, _)| *successes > 0)
+				.map(|(_, probability)| probability),
+		)
+	}
+}
+impl FromStr for DicePool
+{
+	type Err = ParsingError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Self::parse(s)
+	}
+}
+
+/// The result of rolling a [`DicePool`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolRoll
+{
+	/// The name of each die rolled, along with the face it landed on
+	pub faces: Vec<(Box<str>, Symbols)>,
+}
+impl PoolRoll
+{
+	/// Returns the symbols of every face rolled, before cancellation
+	pub fn total(&self) -> Symbols
+	{
+		self.faces
+			.iter()
+			.fold(Symbols::BLANK, |total, (_, face)| total + *face)
+	}
+
+	/// Returns the symbols left after cancellation
+	pub fn net(&self) -> NetSymbols
+	{
+		self.total().net()
+	}
+
+	/// Returns whether any successes are left after cancellation
+	pub fn is_success(&self) -> bool
+	{
+		self.net().is_success()
+	}
+}
+impl Display for PoolRoll
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		let faces = self
+			.faces
+			.iter()
+			.map(|(name, face)| format!("{name}: {face}"))
+			.collect::<Vec<_>>();
+		write!(f, "{} = {}", faces.join(", "), self.net())
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::test_helpers::{assert_approx_eq, RiggedRandom};
+
+	#[test]
+	fn pool_notation()
+	{
+		let pool = DicePool::parse("2A1P 2d").unwrap();
+		assert_eq!(
+			pool.dice().map(SymbolDie::name).collect::<Vec<_>>(),
+			vec!["Ability", "Ability", "Proficiency", "Difficulty", "Difficulty"]
+		);
+		assert_eq!(DicePool::parse("APC").unwrap().dice().count(), 3);
+
+		let max = DicePool::MAX_DICE;
+		assert_eq!(DicePool::parse(&format!("{max}A")).unwrap().dice().count(), max as usize);
+		for input in ["", "2", "2X", "A2", "99999999999A", "4294967295A", "1001A", "600A400DC", "1000AA"]
+		{
+			assert!(
+				matches!(DicePool::parse(input), Err(ParsingError::ExpectedSyntax(_))),
+				"{input}"
+			);
+		}
+	}
+
+	#[test]
+	fn symbols()
+	{
+		let symbols = Symbols::parse("SSA T").unwrap();
+		assert_eq!(symbols, face(2, 1, 0, 0) + TRIUMPH);
+		assert_eq!(symbols.to_string(), "SSAT");
+		assert_eq!(Symbols::parse("-").unwrap(), Symbols::BLANK);
+		assert!(Symbols::parse("SX").is_err());
+	}
+
+	#[test]
+	fn cancellation()
+	{
+		// ability: SA, proficiency: triumph, difficulty: FH, challenge: despair
+		let roll = DicePool::parse("1A1P1D1C")
+			.unwrap()
+			.roll_with_rand(&mut RiggedRandom::new([7, 12, 8, 12]));
+		assert_eq!(
+			roll.net(),
+			NetSymbols {
+				successes: 0,
+				advantages: 0,
+				triumphs: 1,
+				despairs: 1,
+			}
+		);
+		assert!(!roll.is_success());
+		assert_eq!(
+			roll.to_string(),
+			"Ability: SA, Proficiency: T, Difficulty: FH, Challenge: D = 0 successes, 1 triumph, \
+			 1 despair"
+		);
+
+		let roll = DicePool::parse("2B1S")
+			.unwrap()
+			.roll_with_rand(&mut RiggedRandom::new([3, 4, 5]));
+		assert_eq!(roll.net().to_string(), "2 successes");
+		assert!(roll.is_success());
+	}
+
+	#[test]
+	fn probability()
+	{
+		assert_approx_eq!(0.5, DicePool::parse("A").unwrap().probability_of_success());
+		assert_approx_eq!(8.0 / 12.0, DicePool::parse("P").unwrap().probability_of_success());
+		assert_approx_eq!(22.0 / 64.0, DicePool::parse("AD").unwrap().probability_of_success());
+		assert_approx_eq!(0.0, DicePool::new().probability_of_success());
+
+		// pools which can never succeed have a probability of 0 rather than -0
+		for pool in ["1S", "0A", "1C"]
+		{
+			let probability = DicePool::parse(pool).unwrap().probability_of_success();
+			assert_eq!(format!("{probability:.6}"), "0.000000", "`{pool}`");
+		}
+
+		let distribution = DicePool::parse("2A1P2D").unwrap().success_distribution();
+		assert_approx_eq!(1.0, distribution.iter().map(|(_, it)| it).sum::<f64>(), 1e-12);
+
+		let certain = SymbolDie::new("Certain", [S]);
+		let pool = DicePool::new().with_dice(certain, 3);
+		assert_eq!(pool.success_distribution(), vec![(3, 1.0)]);
+	}
+}
@@ -0,0 +1,783 @@
+//! The parser shared by the front ends for the roll syntax of virtual tabletops (eg.
+//! [`roll20`][crate::roll20] and [`foundry`][crate::foundry]), which only differ in the details
+//! described by a [`Syntax`]
+use crate::{
+	error::{ExpectedSyntaxError, NestingDepthError, ParsingError, UnsupportedSyntaxError},
+	operator::{BinaryOperator, CompOperator, DiceModifier, ListFunction, OpToken, UnaryOperator},
+	parsing::{nest, Node},
+	tokenization::Span,
+	Expression,
+};
+use alloc::{boxed::Box, vec, vec::Vec};
+
+/// The rules which differ between the roll syntaxes of virtual tabletops
+pub(crate) struct Syntax
+{
+	/// The comparisons which can start a compare point and what each means, with comparisons
+	/// which start with another comparison first (eg. `>=` before `>`)
+	pub comparisons: &'static [(&'static str, CompOperator)],
+	/// The modifiers which can follow a dice term, in the order they are tried
+	pub dice_modifiers: &'static [(&'static str, ModifierSyntax)],
+	/// The modifiers which can follow a group, in the order they are tried
+	pub group_modifiers: &'static [(&'static str, ModifierSyntax)],
+	/// Whether a compare point on its own after a dice term or group counts its successes (eg. the
+	/// `>4` in `3d6>4`)
+	pub bare_successes: bool,
+	/// Whether modifiers are applied in the order Roll20 applies them (rerolls, exploding, keeping,
+	/// critical ranges, sorting, then successes), rather than in the order they are written
+	pub roll20_order: bool,
+	/// Whether consecutive rerolls of the same kind reroll while any of their conditions is
+	/// satisfied (eg. `r1r2` rerolls until neither a 1 nor a 2 is rolled), rather than one after
+	/// another
+	pub merged_rerolls: bool,
+	/// Whether `[[` and `]]` enclose an expression in the same way as parentheses
+	pub inline_rolls: bool,
+	/// Whether `d%` rolls a die with 100 faces
+	pub percentile_dice: bool,
+	/// Whether keeping or dropping on a group of a single dice term keeps or drops its rolls
+	/// (eg. `{4d6}kh3`), rather than the group's one total
+	pub single_term_groups: bool,
+	/// Whether a label (eg. the `[fire]` in `2d6[fire]`) tags the [`RollGroup`]s of the term
+	/// before it, rather than being ignored
+	///
+	/// [`RollGroup`]: crate::evaluation::RollGroup
+	pub tags: bool,
+	/// Text which starts an operand which is not supported (eg. Roll20's roll queries, `?{`)
+	pub unsupported_operands: &'static [&'static str],
+}
+
+/// What follows the text of a modifier, and what it does
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum ModifierSyntax
+{
+	/// A modifier followed by an optional number of rolls (eg. `kh3`), 1 if omitted
+	Count(DiceModifier),
+	/// A modifier followed by an optional compare point (eg. `!>5`), where a number on its own is
+	/// compared for equality
+	Point(fn(CompOperator) -> DiceModifier),
+	/// A modifier followed by nothing
+	Plain(DiceModifier),
+	/// Counting the rolls, or the totals of a group, which satisfy the compare point which follows
+	Successes,
+	/// A modifier which cannot be evaluated the same way, and so produces an
+	/// [`UnsupportedSyntaxError`]
+	Unsupported,
+}
+
+/// Parses the whole of `input` following `syntax`
+pub(crate) fn parse(input: &str, syntax: &'static Syntax) -> Result<Expression, ParsingError>
+{
+	let mut parser = Parser {
+		input,
+		syntax,
+		index: 0,
+		depth: 0,
+	};
+	let (tree, _) = parser.expression(0)?;
+	parser.skip_whitespace();
+	if parser.index < input.len()
+	{
+		return Err(parser.expected("an operator or the end of the roll"));
+	}
+
+	Ok(Expression::from(tree))
+}
+
+pub(crate) fn expected_at(input: &str, index: usize, expected: &'static str) -> ParsingError
+{
+	ExpectedSyntaxError {
+		expected,
+		index,
+		char_index: input[..index].chars().count(),
+	}
+	.into()
+}
+
+/// A modifier read after a dice term
+#[derive(Debug, Clone, Copy)]
+enum Modifier
+{
+	Dice(DiceModifier, Option<f64>),
+	/// Counting the rolls which satisfy a comparison (eg. the `>4` in `3d6>4`)
+	Success(CompOperator, f64),
+}
+impl Modifier
+{
+	/// The position of the modifier in the order Roll20 applies modifiers
+	fn roll20_order(self) -> u8
+	{
+		match self
+		{
+			Self::Dice(DiceModifier::Reroll(_) | DiceModifier::RerollOnce(_), _) => 0,
+			Self::Dice(
+				DiceModifier::Explode(_) | DiceModifier::ExplodeOnce(_) | DiceModifier::Compound(_),
+				_,
+			) => 1,
+			Self::Dice(DiceModifier::CriticalSuccess(_) | DiceModifier::CriticalFailure(_), _) => 3,
+			Self::Dice(DiceModifier::SortAscending | DiceModifier::SortDescending, _) => 4,
+			Self::Dice(..) => 2,
+			Self::Success(..) => 5,
+		}
+	}
+}
+
+struct Parser<'a>
+{
+	input: &'a str,
+	syntax: &'static Syntax,
+	index: usize,
+	/// How many calls to `expression` the current one is nested within
+	depth: usize,
+}
+impl<'a> Parser<'a>
+{
+	fn rest(&self) -> &'a str
+	{
+		&self.input[self.index..]
+	}
+
+	fn skip_whitespace(&mut self)
+	{
+		let rest = self.rest();
+		self.index += rest.len() - rest.trim_start().len();
+	}
+
+	/// Moves past `prefix` and returns `true` if the rest of the input starts with it
+	fn eat(&mut self, prefix: &str) -> bool
+	{
+		let matches = self.rest().starts_with(prefix);
+		if matches
+		{
+			self.index += prefix.len();
+		}
+		matches
+	}
+
+	fn expect(&mut self, prefix: &'static str, expected: &'static str) -> Result<(), ParsingError>
+	{
+		self.skip_whitespace();
+		if self.eat(prefix)
+		{
+			Ok(())
+		}
+		else
+		{
+			Err(self.expected(expected))
+		}
+	}
+
+	fn expected(&self, expected: &'static str) -> ParsingError
+	{
+		expected_at(self.input, self.index, expected)
+	}
+
+	/// Returns an [`UnsupportedSyntaxError`] for the input from `start` up to the current index
+	fn unsupported(&self, start: usize) -> ParsingError
+	{
+		self.unsupported_range(start, self.index)
+	}
+
+	fn unsupported_range(&self, start: usize, end: usize) -> ParsingError
+	{
+		UnsupportedSyntaxError {
+			syntax: self.input[start..end].into(),
+			index: start,
+			char_index: self.input[..start].chars().count(),
+		}
+		.into()
+	}
+
+	fn span(&self, start: usize) -> Span
+	{
+		Span::new(start, self.index)
+	}
+
+	/// Parses an expression whose operators bind at least as tightly as `min_power`, returning its
+	/// tree along with the depth of that tree
+	fn expression(&mut self, min_power: u8) -> Result<(Node, usize), ParsingError>
+	{
+		if self.depth >= NestingDepthError::MAX_DEPTH
+		{
+			return Err(NestingDepthError.into());
+		}
+		self.depth += 1;
+
+		self.skip_whitespace();
+		let start = self.index;
+		let (mut lhs, mut depth) = match self.rest().as_bytes().first()
+		{
+			Some(sign @ (b'+' | b'-')) =>
+			{
+				let token = if *sign == b'+' { OpToken::Plus } else { OpToken::Minus };
+				self.index += 1;
+				let operator = UnaryOperator::try_from(token)?;
+				let (argument, argument_depth) = self.expression(operator.binding_power)?;
+				let unary = Node::Unary {
+					operator,
+					argument: Box::new(argument),
+					span: self.span(start),
+				};
+				(unary, nest(argument_depth)?)
+			}
+			_ => self.operand()?,
+		};
+
+		loop
+		{
+			let before = self.index;
+			self.skip_whitespace();
+			let Some((token, len)) = self.binary_operator()
+			else
+			{
+				self.index = before;
+				break;
+			};
+			let operator = BinaryOperator::from(token);
+			if operator.binding_power.left < min_power
+			{
+				self.index = before;
+				break;
+			}
+
+			self.index += len;
+			let (rhs, rhs_depth) = self.expression(operator.binding_power.right)?;
+			lhs = Node::Binary {
+				operator,
+				left: Box::new(lhs),
+				right: Box::new(rhs),
+				span: self.span(start),
+			};
+			// chains of operators are evaluated in a loop, so only nesting counts towards the depth
+			depth = depth.max(rhs_depth);
+		}
+
+		self.depth -= 1;
+		Ok((lhs, depth))
+	}
+
+	/// Returns the binary operator at the start of the rest of the input and its length
+	fn binary_operator(&self) -> Option<(OpToken, usize)>
+	{
+		let rest = self.rest();
+		if rest.starts_with("**")
+		{
+			return Some((OpToken::Power, 2));
+		}
+		let token = match rest.as_bytes().first()?
+		{
+			b'+' => OpToken::Plus,
+			b'-' => OpToken::Minus,
+			b'*' => OpToken::Multiply,
+			b'/' => OpToken::Divide,
+			b'%' => OpToken::Modulus,
+			_ => return None,
+		};
+		Some((token, 1))
+	}
+
+	/// Parses a number, dice term, parenthesized expression, inline roll, or group, followed by an
+	/// optional label
+	fn operand(&mut self) -> Result<(Node, usize), ParsingError>
+	{
+		let start = self.index;
+		let operand = match self.rest().as_bytes().first()
+		{
+			Some(b'0'..=b'9') =>
+			{
+				let count = Node::Leaf(self.number()?);
+				self.dice_if_present(start, (count, 1))?
+			}
+			Some(b'd' | b'D') => self.dice(start, (Node::Leaf(1.0), 1))?,
+			Some(b'(') =>
+			{
+				let inner = self.enclosed()?;
+				self.dice_if_present(start, inner)?
+			}
+			Some(b'[') if self.syntax.inline_rolls =>
+			{
+				let inner = self.enclosed()?;
+				self.dice_if_present(start, inner)?
+			}
+			Some(b'{') => self.group()?,
+			_ =>
+			{
+				if let Some(syntax) = self
+					.syntax
+					.unsupported_operands
+					.iter()
+					.find(|it| self.rest().starts_with(**it))
+				{
+					self.index += syntax.len();
+					return Err(self.unsupported(start));
+				}
+				// there are no functions, so math functions (eg. `floor(1.5)`) are not supported
+				let name = self.rest().bytes().take_while(u8::is_ascii_alphabetic).count();
+				if name > 0 && self.rest()[name..].trim_start().starts_with('(')
+				{
+					self.index += name;
+					return Err(self.unsupported(start));
+				}
+				return Err(self.expected(if self.syntax.inline_rolls
+				{
+					"a number, a dice term, `(`, `[[`, or `{`"
+				}
+				else
+				{
+					"a number, a dice term, `(`, or `{`"
+				}));
+			}
+		};
+
+		self.label(start, operand)
+	}
+
+	/// Parses an expression enclosed in parentheses or inline roll delimiters
+	fn enclosed(&mut self) -> Result<(Node, usize), ParsingError>
+	{
+		if self.eat("(")
+		{
+			let inner = self.expression(0)?;
+			self.expect(")", "`)`")?;
+			Ok(inner)
+		}
+		else if self.syntax.inline_rolls && self.eat("[[")
+		{
+			let inner = self.expression(0)?;
+			self.expect("]]", "`]]`")?;
+			Ok(inner)
+		}
+		else if self.syntax.inline_rolls
+		{
+			Err(self.expected("`(` or `[[`"))
+		}
+		else
+		{
+			Err(self.expected("`(`"))
+		}
+	}
+
+	fn number(&mut self) -> Result<f64, ParsingError>
+	{
+		let rest = self.rest();
+		let integer = rest.bytes().take_while(u8::is_ascii_digit).count();
+		let fraction = match rest.as_bytes().get(integer..integer + 2)
+		{
+			Some([b'.', second]) if second.is_ascii_digit() =>
+			{
+				1 + rest[integer + 1..].bytes().take_while(u8::is_ascii_digit).count()
+			}
+			_ => 0,
+		};
+		if integer == 0
+		{
+			return Err(self.expected("a number"));
+		}
+
+		let len = integer + fraction;
+		self.index += len;
+		Ok(rest[..len].parse().unwrap_or_default())
+	}
+
+	fn dice_if_present(&mut self, start: usize, count: (Node, usize))
+		-> Result<(Node, usize), ParsingError>
+	{
+		if self.rest().starts_with(['d', 'D'])
+		{
+			self.dice(start, count)
+		}
+		else
+		{
+			Ok(count)
+		}
+	}
+
+	/// Parses a dice term whose count has already been read, starting from its `d`
+	fn dice(&mut self, start: usize, count: (Node, usize)) -> Result<(Node, usize), ParsingError>
+	{
+		let (count, count_depth) = count;
+		self.index += 1;
+		let faces_start = self.index;
+		let (faces, faces_depth) = match self.rest().as_bytes().first()
+		{
+			Some(b'0'..=b'9') => (Node::Leaf(self.number()?), 1),
+			Some(b'(') => self.enclosed()?,
+			Some(b'[') if self.syntax.inline_rolls => self.enclosed()?,
+			Some(b'%') if self.syntax.percentile_dice =>
+			{
+				self.index += 1;
+				(Node::Leaf(100.0), 1)
+			}
+			// dice with special faces (eg. fate dice, `dF`)
+			Some(letter) if letter.is_ascii_alphabetic() =>
+			{
+				self.index += 1;
+				return Err(self.unsupported(faces_start - 1));
+			}
+			_ => return Err(self.expected("the number of faces")),
+		};
+
+		let dice = Node::Binary {
+			operator: OpToken::Dice.into(),
+			left: Box::new(count),
+			right: Box::new(faces),
+			span: self.span(start),
+		};
+		let depth = nest(count_depth.max(faces_depth))?;
+		self.dice_modifiers(start, (dice, depth))
+	}
+
+	/// Returns the syntax of the modifier at the start of the rest of the input out of
+	/// `modifiers`, moving past its text
+	fn modifier(&mut self, modifiers: &[(&str, ModifierSyntax)]) -> Option<ModifierSyntax>
+	{
+		modifiers
+			.iter()
+			.find(|(prefix, _)| self.eat(prefix))
+			.map(|(_, syntax)| *syntax)
+	}
+
+	/// Parses the modifiers directly following a dice term, and wraps `dice` in the nodes they
+	/// produce
+	fn dice_modifiers(&mut self, start: usize, dice: (Node, usize))
+		-> Result<(Node, usize), ParsingError>
+	{
+		let mut modifiers = Vec::new();
+		loop
+		{
+			let modifier_start = self.index;
+			let modifier = match self.modifier(self.syntax.dice_modifiers)
+			{
+				Some(ModifierSyntax::Count(modifier)) =>
+				{
+					Modifier::Dice(modifier, self.optional_count()?)
+				}
+				Some(ModifierSyntax::Point(modifier)) => self.compare_point(modifier)?,
+				Some(ModifierSyntax::Plain(modifier)) => Modifier::Dice(modifier, None),
+				Some(ModifierSyntax::Successes) =>
+				{
+					let (comp_op, target) = self.success_point()?;
+					Modifier::Success(comp_op, target)
+				}
+				Some(ModifierSyntax::Unsupported) => return Err(self.unsupported(modifier_start)),
+				None => match self.bare_success()?
+				{
+					Some((comp_op, target)) => Modifier::Success(comp_op, target),
+					None => break,
+				},
+			};
+			modifiers.push((modifier_start..self.index, modifier));
+		}
+		if self.syntax.roll20_order
+		{
+			// sorting is stable, so modifiers of the same kind are applied in the order written
+			modifiers.sort_by_key(|(_, it)| it.roll20_order());
+		}
+
+		let (mut dice, mut depth) = dice;
+		let mut counted = false;
+		for (range, modifier) in modifiers
+		{
+			// successes are a number rather than rolls, so nothing can modify them
+			if counted
+			{
+				return Err(self.unsupported_range(range.start, range.end));
+			}
+
+			let span = self.span(start);
+			dice = match modifier
+			{
+				Modifier::Dice(
+					modifier @ (DiceModifier::Reroll(comp_op) | DiceModifier::RerollOnce(comp_op)),
+					target,
+				) if self.syntax.merged_rerolls =>
+				{
+					let once = matches!(modifier, DiceModifier::RerollOnce(_));
+					let condition = (comp_op, target.unwrap_or(1.0));
+					match &mut dice
+					{
+						Node::Reroll {
+							conditions,
+							once: merged_once,
+							span: merged_span,
+							..
+						} if *merged_once == once =>
+						{
+							let mut merged = core::mem::take(conditions).into_vec();
+							merged.push(condition);
+							*conditions = merged.into();
+							*merged_span = span;
+							dice
+						}
+						_ => Node::Reroll {
+							dice: Box::new(dice),
+							conditions: Box::new([condition]),
+							once,
+							span,
+						},
+					}
+				}
+				Modifier::Dice(modifier, argument) => Node::Modifier {
+					modifier,
+					dice: Box::new(dice),
+					argument: argument.map(|it| Box::new(Node::Leaf(it))),
+					span,
+				},
+				Modifier::Success(comp_op, target) =>
+				{
+					counted = true;
+					let filter = Node::ComparisonTernary {
+						comp_op,
+						dice: Box::new(dice),
+						compare_to: Box::new(Node::Leaf(target)),
+						span,
+					};
+					depth = nest(depth)?;
+					function(ListFunction::Count, vec![filter], span)
+				}
+			};
+			depth = nest(depth)?;
+		}
+
+		Ok((dice, depth))
+	}
+
+	/// Reads the compare point of a modifier (eg. the `>5` in `3d6!>5`), which is a comparison
+	/// followed by a number, a number alone to compare for equality, or nothing at all
+	fn compare_point(&mut self, modifier: fn(CompOperator) -> DiceModifier)
+		-> Result<Modifier, ParsingError>
+	{
+		let comp_op = self.comparison();
+		let value = if comp_op.is_some() || self.rest().starts_with(|it: char| it.is_ascii_digit())
+		{
+			Some(self.number()?)
+		}
+		else
+		{
+			None
+		};
+		Ok(Modifier::Dice(modifier(comp_op.unwrap_or(CompOperator::Equals)), value))
+	}
+
+	/// Reads the compare point of counting successes (eg. the `>=5` in `cs>=5`), which must have
+	/// a number
+	fn success_point(&mut self) -> Result<(CompOperator, f64), ParsingError>
+	{
+		let comp_op = self.comparison().unwrap_or(CompOperator::Equals);
+		Ok((comp_op, self.number()?))
+	}
+
+	/// Reads a compare point on its own, which counts successes if the syntax allows it
+	fn bare_success(&mut self) -> Result<Option<(CompOperator, f64)>, ParsingError>
+	{
+		if !self.syntax.bare_successes
+		{
+			return Ok(None);
+		}
+		match self.comparison()
+		{
+			Some(comp_op) => Ok(Some((comp_op, self.number()?))),
+			None => Ok(None),
+		}
+	}
+
+	fn comparison(&mut self) -> Option<CompOperator>
+	{
+		self.syntax
+			.comparisons
+			.iter()
+			.find(|(prefix, _)| self.eat(prefix))
+			.map(|(_, comp_op)| *comp_op)
+	}
+
+	fn optional_count(&mut self) -> Result<Option<f64>, ParsingError>
+	{
+		if self.rest().starts_with(|it: char| it.is_ascii_digit())
+		{
+			self.number().map(Some)
+		}
+		else
+		{
+			Ok(None)
+		}
+	}
+
+	/// Parses a group of comma-separated expressions in braces (eg. `{4d6, 3d8}kh1`) along with
+	/// its modifiers. A group keeps or drops, or counts the successes of, its expressions'
+	/// totals, unless the syntax keeps or drops the rolls of a group of a single expression
+	fn group(&mut self) -> Result<(Node, usize), ParsingError>
+	{
+		let start = self.index;
+		self.index += 1;
+		let mut elements = vec![self.expression(0)?];
+		loop
+		{
+			self.skip_whitespace();
+			if !self.eat(",")
+			{
+				break;
+			}
+			elements.push(self.expression(0)?);
+		}
+		self.expect("}", "`,` or `}`")?;
+
+		let mut keep = None;
+		let mut success = None;
+		loop
+		{
+			let modifier_start = self.index;
+			match self.modifier(self.syntax.group_modifiers)
+			{
+				Some(ModifierSyntax::Count(modifier)) if keep.is_none() =>
+				{
+					keep = Some((modifier, self.optional_count()?.unwrap_or(1.0)));
+				}
+				Some(ModifierSyntax::Successes) => success = Some(self.success_point()?),
+				Some(_) => return Err(self.unsupported(modifier_start)),
+				None => match self.bare_success()?
+				{
+					Some(point) => success = Some(point),
+					None => break,
+				},
+			}
+		}
+
+		let span = self.span(start);
+		if elements.len() == 1 && (keep.is_none() || self.syntax.single_term_groups)
+		{
+			let (mut node, mut depth) = elements.swap_remove(0);
+			if let Some((modifier, count)) = keep
+			{
+				// keeping the rolls of several dice terms at once cannot be represented
+				if !is_dice(&node)
+				{
+					return Err(self.unsupported(start));
+				}
+				node = Node::Modifier {
+					modifier,
+					dice: Box::new(node),
+					argument: Some(Box::new(Node::Leaf(count))),
+					span,
+				};
+				depth = nest(depth)?;
+			}
+			if let Some((comp_op, target)) = success
+			{
+				node = comparison(comp_op, node, target, span);
+				depth = nest(depth)?;
+			}
+			return Ok((node, depth));
+		}
+
+		let depth = elements.iter().map(|(_, depth)| *depth).max().unwrap_or_default();
+		let elements = elements.into_iter().map(|(node, _)| node);
+		match (keep, success)
+		{
+			(Some(_), Some(_)) => Err(self.unsupported(start)),
+			(Some((modifier, count)), None) =>
+			{
+				#[allow(clippy::cast_precision_loss)] // groups are never that large
+				let len = elements.len() as f64;
+				let (function_kept, count) = match modifier
+				{
+					DiceModifier::KeepLowest => (ListFunction::Lowest, count),
+					DiceModifier::DropHighest => (ListFunction::Lowest, (len - count).max(0.0)),
+					DiceModifier::DropLowest => (ListFunction::Highest, (len - count).max(0.0)),
+					_ => (ListFunction::Highest, count),
+				};
+				let totals = elements
+					.map(|it| function(ListFunction::Sum, vec![it], span))
+					.collect();
+				let list = function(ListFunction::List, totals, span);
+				let kept = function(function_kept, vec![list, Node::Leaf(count)], span);
+				// each total, the list of them, the kept totals, and their sum are a level deeper
+				Ok((function(ListFunction::Sum, vec![kept], span), nest(depth + 3)?))
+			}
+			(None, Some((comp_op, target))) =>
+			{
+				let successes = elements.map(|it| comparison(comp_op, it, target, span));
+				Ok((sum(successes, span), nest(depth)?))
+			}
+			(None, None) => Ok((sum(elements, span), depth)),
+		}
+	}
+
+	/// Reads a label (eg. the `[fire]` in `2d6[fire]`) if one follows `operand`, which starts at
+	/// `start`, and tags `operand` with it if the syntax uses labels as tags
+	fn label(&mut self, start: usize, operand: (Node, usize)) -> Result<(Node, usize), ParsingError>
+	{
+		let before = self.index;
+		self.skip_whitespace();
+		let inline_roll = self.syntax.inline_rolls && self.rest().starts_with("[[");
+		if !self.rest().starts_with('[') || inline_roll
+		{
+			self.index = before;
+			return Ok(operand);
+		}
+
+		let Some(length) = self.rest().find(']')
+		else
+		{
+			self.index = self.input.len();
+			return Err(self.expected("`]`"));
+		};
+		let tag = self.rest()[1..length].trim();
+		self.index += length + 1;
+		if !self.syntax.tags
+		{
+			return Ok(operand);
+		}
+
+		let (node, depth) = operand;
+		let tagged = Node::Tagged {
+			node: Box::new(node),
+			tag: tag.into(),
+			span: self.span(start),
+		};
+		Ok((tagged, nest(depth)?))
+	}
+}
+
+/// Returns whether `node` is a dice term, which may have modifiers
+fn is_dice(node: &Node) -> bool
+{
+	match node
+	{
+		Node::Binary { operator, .. } => operator.token == OpToken::Dice,
+		Node::Modifier { .. } | Node::Reroll { .. } => true,
+		_ => false,
+	}
+}
+
+fn function(function: ListFunction, arguments: Vec<Node>, span: Span) -> Node
+{
+	Node::Function {
+		function,
+		arguments: arguments.into(),
+		span,
+	}
+}
+
+fn comparison(comp_op: CompOperator, node: Node, target: f64, span: Span) -> Node
+{
+	Node::Comparison {
+		comp_op,
+		left: Box::new(node),
+		right: Box::new(Node::Leaf(target)),
+		span,
+	}
+}
+
+/// Adds every node of `nodes` together
+fn sum<I>(nodes: I, span: Span) -> Node
+where
+	I: IntoIterator<Item = Node>,
+{
+	let mut nodes = nodes.into_iter();
+	let first = nodes.next().unwrap_or(Node::Leaf(0.0));
+	nodes.fold(first, |total, node| Node::Binary {
+		operator: OpToken::Plus.into(),
+		left: Box::new(total),
+		right: Box::new(node),
+		span,
+	})
+}
@@ -7,19 +7,59 @@
 //! println!("Fireball deals {} fire damage", damage.value);
 //! # Ok(())}
 //! ```
+//! # Features
+//! The `std` feature is enabled by default. It provides the functions which evaluate with
+//! `rand::thread_rng` or a seeded `StdRng` (eg. [`evaluate`] and [`eval_with_seed`]). Without it,
+//! the crate is `#![no_std]` and only needs `alloc`, so expressions can still be parsed and
+//! evaluated with any [`RangeRng`]
 
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod bcdice;
+mod compiled;
+mod dialect;
 pub mod error;
 pub mod evaluation;
+mod expression;
+pub mod foundry;
+pub mod genesys;
+mod inline_roll;
+mod macro_support;
+mod math;
 pub mod operator;
 mod parsing;
+pub mod roll20;
+mod script;
 mod statistics;
+pub mod table;
 mod tokenization;
 
+pub use compiled::*;
+pub use dialect::*;
+pub use expression::*;
+pub use script::*;
+
+/// Used by the `dice!` macro of the `saikoro-macros` crate and by the benchmarks. Not part of the
+/// public API: anything in this module may change or be removed in any release, without following
+/// semver
+#[doc(hidden)]
+pub mod __private
+{
+	pub use crate::{
+		macro_support::expression_source,
+		parsing::Node,
+		tokenization::{Span, TokenStream},
+	};
+	pub use alloc::boxed::Box;
+}
+
 use error::ParsingError;
-use evaluation::DiceEvaluation;
-use rand::{Rng, RngCore, SeedableRng};
-use std::ops::Range;
-use tokenization::TokenStream;
+use evaluation::{DiceEvaluation, LabelledEvaluation, RepeatedEvaluation};
+use rand::{Rng, RngCore};
+use alloc::vec::Vec;
+use core::ops::Range;
 
 /// Evaluates a string in format similar to [Standard Dice Notation](https://en.wikipedia.org/wiki/Dice_notation)
 /// evaluated with [`rand::thread_rng`]. Equivalent to [`eval_with_rand`] called with `&mut
@@ -37,6 +77,7 @@ use tokenization::TokenStream;
 /// # Errors
 /// An error variant will be returned if the expression is unable to be parsed, or the evaluation
 /// function produces an error
+#[cfg(feature = "std")]
 pub fn evaluate(input: &str) -> Result<DiceEvaluation, ParsingError>
 {
 	eval_with_rand(input, &mut rand::thread_rng())
@@ -44,10 +85,10 @@ pub fn evaluate(input: &str) -> Result<DiceEvaluation, ParsingError>
 
 /// A utility wrapper function for seeding a dice roll with the given u64 as the seed
 /// (see [`saikoro::eval_with_rand`][`eval_with_rand`] for more information)
+#[cfg(feature = "std")]
 pub fn eval_with_seed(input: &str, seed: u64) -> Result<DiceEvaluation, ParsingError>
 {
-	let mut seeded_random = rand::rngs::StdRng::seed_from_u64(seed);
-	eval_with_rand(input, &mut seeded_random)
+	Expression::parse(input)?.eval_with_seed(seed)
 }
 
 /// Evaluates a string in format similar to [Standard Dice Notation](https://en.wikipedia.org/wiki/Dice_notation)
@@ -81,10 +122,82 @@ pub fn eval_with_rand<R>(input: &str, rand: &mut R) -> Result<DiceEvaluation, Pa
 where
 	R: RangeRng,
 {
-	evaluation::evaluate_tree(
-		parsing::parse_tree_from(&mut TokenStream::new(input))?,
-		rand,
-	)
+	Expression::parse(input)?.eval_with_rand(rand)
+}
+
+/// Evaluates an expression which may be prefixed by a repetition count followed by `x` or `#` (eg.
+/// `6x 4d6kh3` or `3#1d20+5`) the given number of times, independently of one another, evaluated
+/// with [`rand::thread_rng`]. Expressions without a repetition count are evaluated once
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// let ability_scores = saikoro::evaluate_repeated("6x 4d6kh3")?;
+/// assert_eq!(ability_scores.len(), 6);
+/// for score in &ability_scores
+/// {
+///     assert!(score.value >= 3.0 && score.value <= 18.0);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+/// # Errors
+/// An error variant will be returned if the expression is unable to be parsed, or the evaluation
+/// function produces an error
+#[cfg(feature = "std")]
+pub fn evaluate_repeated(input: &str) -> Result<RepeatedEvaluation, ParsingError>
+{
+	eval_repeated_with_rand(input, &mut rand::thread_rng())
+}
+
+/// Same as [`evaluate_repeated`], but evaluated with the given [`RangeRng`] (see
+/// [`saikoro::eval_with_rand`][`eval_with_rand`] for more information). The same [`RangeRng`] is
+/// used for every repetition
+/// # Errors
+/// An error variant will be returned if the expression is unable to be parsed, or the evaluation
+/// function produces an error
+pub fn eval_repeated_with_rand<R>(input: &str, rand: &mut R)
+	-> Result<RepeatedEvaluation, ParsingError>
+where
+	R: RangeRng,
+{
+	RepeatedExpression::parse(input)?.eval_with_rand(rand)
+}
+
+/// Evaluates a sequence of statements separated by `;`, each of which may be given a label (eg.
+/// `attack: 1d20+7; damage: 2d6+4`), in order with [`rand::thread_rng`]. Labels can be used as
+/// variables by later statements, and anything from a `#` to the end of a statement is a comment
+/// (see [`Script`] for more information)
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// let results = saikoro::evaluate_script("attack: 1d20+7; damage: 2d6 + attack # silly")?;
+/// assert_eq!(results.len(), 2);
+/// assert_eq!(results[0].label.as_deref(), Some("attack"));
+/// assert!(results[1].value() >= results[0].value() + 2.0);
+/// # Ok(())
+/// # }
+/// ```
+/// # Errors
+/// An error variant will be returned if any statement is unable to be parsed, or the evaluation
+/// function produces an error
+#[cfg(feature = "std")]
+pub fn evaluate_script(input: &str) -> Result<Vec<LabelledEvaluation>, ParsingError>
+{
+	eval_script_with_rand(input, &mut rand::thread_rng())
+}
+
+/// Same as [`evaluate_script`], but evaluated with the given [`RangeRng`] (see
+/// [`saikoro::eval_with_rand`][`eval_with_rand`] for more information). The same [`RangeRng`] is
+/// used for every statement
+/// # Errors
+/// An error variant will be returned if any statement is unable to be parsed, or the evaluation
+/// function produces an error
+pub fn eval_script_with_rand<R>(input: &str, rand: &mut R)
+	-> Result<Vec<LabelledEvaluation>, ParsingError>
+where
+	R: RangeRng,
+{
+	Script::parse(input)?.eval_with_rand(rand)
 }
 
 /// A utility trait for allowing flexibility for testing or rigging saikoro's random number
@@ -192,7 +305,10 @@ pub(crate) mod test_helpers
 			{
 				(left_val, right_val) =>
 				{
-					if !(f64::abs(*left_val - *right_val) < f64::EPSILON)
+					// written this way so NaNs are never approx equal to anything
+					#[allow(clippy::neg_cmp_op_on_partial_ord)]
+					let not_approx_eq = !(f64::abs(*left_val - *right_val) < f64::EPSILON);
+					if not_approx_eq
 					{
 						std::panic!("assertion that `left` approx equals `right` failed\nleft: {}\nright: {}", &*left_val, &*right_val);
 					}
@@ -204,7 +320,9 @@ pub(crate) mod test_helpers
 			{
 				(left_val, right_val, error_val) =>
 				{
-					if !(f64::abs(*left_val - *right_val) < *error_val)
+					#[allow(clippy::neg_cmp_op_on_partial_ord)]
+					let not_approx_eq = !(f64::abs(*left_val - *right_val) < *error_val);
+					if not_approx_eq
 					{
 						std::panic!("assertion that `left` approx equals `right` failed\nleft: {}\nright: {}\nmax error: {}", &*left_val, &*right_val, &*error_val);
 					}
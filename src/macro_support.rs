@@ -0,0 +1,358 @@
+//! Support for the `dice!` macro of the `saikoro-macros` crate, which parses expressions at compile
+//! time and expands to code which builds the same parse tree. Nothing here is part of the public
+//! API, and it may change at any time
+
+use crate::{
+	parsing::{self, Node},
+	tokenization::{Span, TokenStream},
+};
+use alloc::{format, string::String, string::ToString, vec::Vec};
+
+/// Parses `input` and returns the source of a Rust expression which evaluates to an equivalent
+/// [`Expression`][crate::Expression]. Any `{ident}` in `input` is replaced by the Rust variable
+/// `ident` converted to a number with [`f64::from`]. Spans in the produced tree refer to `input`
+/// as it was written, braces included
+/// # Errors
+/// An error message will be returned if `input` is unable to be parsed, if its braces do not
+/// contain an identifier, or if it refers to a variable without interpolating it, as an
+/// [`Expression`][crate::Expression] cannot refer to labelled statements
+pub fn expression_source(input: &str) -> Result<String, String>
+{
+	let interpolated = Interpolated::new(input)?;
+	let tree = parsing::parse_tree_from(&mut TokenStream::new(&interpolated.expression))
+		.map_err(|err| err.to_string())?;
+	Ok(format!(
+		"::saikoro::Expression::from({})",
+		node_source(&tree, &interpolated)?
+	))
+}
+
+/// An input string with each `{ident}` replaced by a placeholder variable, wrapped in parentheses
+/// so it cannot run into the text around it (eg. `{count}d6`)
+struct Interpolated
+{
+	expression: String,
+	replacements: Vec<Replacement>,
+}
+struct Replacement
+{
+	placeholder: String,
+	ident: String,
+	/// Where the `{ident}` was in the original input
+	original: Span,
+	/// Where the placeholder is in the interpolated expression
+	replaced: Span,
+}
+impl Interpolated
+{
+	fn new(input: &str) -> Result<Self, String>
+	{
+		let mut expression = String::with_capacity(input.len());
+		let mut replacements = Vec::<Replacement>::new();
+
+		let mut consumed = 0;
+		while let Some(open) = input[consumed..].find(['{', '}']).map(|it| it + consumed)
+		{
+			if input[open..].starts_with('}')
+			{
+				return Err(String::from("Found `}` without a matching `{`!"));
+			}
+			let close = input[open..]
+				.find('}')
+				.ok_or_else(|| String::from("Found `{` without a matching `}`!"))?
+				+ open;
+
+			let ident = input[open + 1..close].trim();
+			if !is_rust_ident(ident)
+			{
+				return Err(format!("`{{{ident}}}` does not name a variable!"));
+			}
+			let placeholder = replacements
+				.iter()
+				.find(|it| it.ident == ident)
+				.map_or_else(|| placeholder_name(replacements.len()), |it| it.placeholder.clone());
+
+			expression.push_str(&input[consumed..open]);
+			let start = expression.len();
+			expression.push('(');
+			expression.push_str(&placeholder);
+			expression.push(')');
+
+			replacements.push(Replacement {
+				placeholder,
+				ident: String::from(ident),
+				original: Span::new(open, close + 1),
+				replaced: Span::new(start, expression.len()),
+			});
+			consumed = close + 1;
+		}
+		expression.push_str(&input[consumed..]);
+
+		Ok(Self {
+			expression,
+			replacements,
+		})
+	}
+
+	fn ident(&self, placeholder: &str) -> Option<&str>
+	{
+		self.replacements
+			.iter()
+			.find(|it| it.placeholder == placeholder)
+			.map(|it| it.ident.as_str())
+	}
+
+	/// Maps a byte index in the interpolated expression back to the original input
+	fn original_index(&self, index: usize) -> usize
+	{
+		let mut original = index;
+		for replacement in &self.replacements
+		{
+			if index >= replacement.replaced.end
+			{
+				original = original + (replacement.original.end - replacement.original.start)
+					- (replacement.replaced.end - replacement.replaced.start);
+			}
+			else if index > replacement.replaced.start
+			{
+				return replacement.original.end;
+			}
+		}
+		original
+	}
+}
+
+fn is_rust_ident(str: &str) -> bool
+{
+	str.chars()
+		.next()
+		.is_some_and(|it| it.is_alphabetic() || it == '_')
+		&& str.chars().all(|it| it.is_alphanumeric() || it == '_')
+}
+
+/// Returns a variable name for the interpolation at `index`. Variable names can only contain
+/// letters and underscores, so the index is written in letters
+fn placeholder_name(index: usize) -> String
+{
+	let mut name = String::from("__interpolated_");
+	let mut index = index;
+	loop
+	{
+		name.push(char::from(b'a' + u8::try_from(index % 26).unwrap_or_default()));
+		index /= 26;
+		if index == 0
+		{
+			return name;
+		}
+	}
+}
+
+fn node_source(node: &Node, interpolated: &Interpolated) -> Result<String, String>
+{
+	const NODE: &str = "::saikoro::__private::Node";
+	const OPERATOR: &str = "::saikoro::operator";
+
+	let boxed = |node: &Node| -> Result<String, String> {
+		Ok(format!(
+			"::saikoro::__private::Box::new({})",
+			node_source(node, interpolated)?
+		))
+	};
+	let span_source = |span: &Span| {
+		format!(
+			"::saikoro::__private::Span::new({}, {})",
+			interpolated.original_index(span.start),
+			interpolated.original_index(span.end)
+		)
+	};
+
+	Ok(match node
+	{
+		Node::Leaf(value) => format!("{NODE}::Leaf({value:?}_f64)"),
+		Node::Variable { name, .. } =>
+		{
+			let ident = interpolated.ident(name).ok_or_else(|| {
+				format!(
+					"Found variable `{name}`! Rust variables must be interpolated as `{{{name}}}`"
+				)
+			})?;
+			format!("{NODE}::Leaf(::core::primitive::f64::from({ident}))")
+		}
+		Node::Binary {
+			operator,
+			left,
+			right,
+			span,
+		} => format!(
+			"{NODE}::Binary {{ \
+			 operator: {OPERATOR}::BinaryOperator::from({OPERATOR}::OpToken::{:?}), \
+			 left: {}, right: {}, span: {} }}",
+			operator.token,
+			boxed(left)?,
+			boxed(right)?,
+			span_source(span)
+		),
+		Node::Unary {
+			operator,
+			argument,
+			span,
+		} => format!(
+			"{NODE}::Unary {{ \
+			 operator: {OPERATOR}::UnaryOperator::from({OPERATOR}::UnaryOpToken::{:?}), \
+			 argument: {}, span: {} }}",
+			operator.token,
+			boxed(argument)?,
+			span_source(span)
+		),
+		Node::ComparisonTernary {
+			comp_op,
+			dice,
+			compare_to,
+			span,
+		} => format!(
+			"{NODE}::ComparisonTernary {{ comp_op: {OPERATOR}::CompOperator::{comp_op:?}, \
+			 dice: {}, compare_to: {}, span: {} }}",
+			boxed(dice)?,
+			boxed(compare_to)?,
+			span_source(span)
+		),
+		Node::Modifier {
+			modifier,
+			dice,
+			argument,
+			span,
+		} => format!(
+			"{NODE}::Modifier {{ modifier: {OPERATOR}::DiceModifier::{modifier:?}, dice: {}, \
+			 argument: {}, span: {} }}",
+			boxed(dice)?,
+			match argument
+			{
+				Some(argument) => format!("::core::option::Option::Some({})", boxed(argument)?),
+				None => String::from("::core::option::Option::None"),
+			},
+			span_source(span)
+		),
+		Node::Reroll {
+			dice,
+			conditions,
+			once,
+			span,
+		} => format!(
+			"{NODE}::Reroll {{ dice: {}, conditions: ::saikoro::__private::Box::new([{}]), \
+			 once: {once}, span: {} }}",
+			boxed(dice)?,
+			conditions
+				.iter()
+				.map(|(comp_op, target)| {
+					format!("({OPERATOR}::CompOperator::{comp_op:?}, {target:?}_f64)")
+				})
+				.collect::<Vec<_>>()
+				.join(", "),
+			span_source(span)
+		),
+		Node::PlusMaximum { dice, span } => format!(
+			"{NODE}::PlusMaximum {{ dice: {}, span: {} }}",
+			boxed(dice)?,
+			span_source(span)
+		),
+		Node::Comparison {
+			comp_op,
+			left,
+			right,
+			span,
+		} => format!(
+			"{NODE}::Comparison {{ comp_op: {OPERATOR}::CompOperator::{comp_op:?}, left: {}, \
+			 right: {}, span: {} }}",
+			boxed(left)?,
+			boxed(right)?,
+			span_source(span)
+		),
+		Node::Logic {
+			operator,
+			left,
+			right,
+			span,
+		} => format!(
+			"{NODE}::Logic {{ operator: {OPERATOR}::LogicOperator::{operator:?}, left: {}, \
+			 right: {}, span: {} }}",
+			boxed(left)?,
+			boxed(right)?,
+			span_source(span)
+		),
+		Node::Conditional {
+			condition,
+			if_true,
+			if_false,
+			span,
+		} => format!(
+			"{NODE}::Conditional {{ condition: {}, if_true: {}, if_false: {}, span: {} }}",
+			boxed(condition)?,
+			boxed(if_true)?,
+			boxed(if_false)?,
+			span_source(span)
+		),
+		Node::Function {
+			function,
+			arguments,
+			span,
+		} => format!(
+			"{NODE}::Function {{ function: {OPERATOR}::ListFunction::{function:?}, \
+			 arguments: ::saikoro::__private::Box::new([{}]), span: {} }}",
+			arguments
+				.iter()
+				.map(|it| node_source(it, interpolated))
+				.collect::<Result<Vec<_>, _>>()?
+				.join(", "),
+			span_source(span)
+		),
+		Node::Index { list, index, span } => format!(
+			"{NODE}::Index {{ list: {}, index: {}, span: {} }}",
+			boxed(list)?,
+			boxed(index)?,
+			span_source(span)
+		),
+		Node::Tagged { node, tag, span } => format!(
+			"{NODE}::Tagged {{ node: {}, tag: ::saikoro::__private::Box::from({tag:?}), \
+			 span: {} }}",
+			boxed(node)?,
+			span_source(span)
+		),
+		Node::Table { name, span } => format!(
+			"{NODE}::Table {{ name: ::saikoro::__private::Box::from({name:?}), span: {} }}",
+			span_source(span)
+		),
+	})
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn interpolation()
+	{
+		let source = expression_source("{count}d6").unwrap();
+		assert!(source.contains("Leaf(::core::primitive::f64::from(count))"));
+		assert!(source.contains("OpToken::Dice"));
+		// spans refer to the input as written, not the placeholder
+		assert!(source.contains("Span::new(0, 9)"));
+
+		assert!(expression_source("2d6 + bonus").is_err());
+		assert!(expression_source("2d6 + {bonus").is_err());
+		assert!(expression_source("2d6 + {1}").is_err());
+		assert!(expression_source("2d6 +").is_err());
+	}
+
+	#[test]
+	fn original_index()
+	{
+		let interpolated = Interpolated::new("{a} + {bonus} + 1").unwrap();
+		assert_eq!(interpolated.expression, "(__interpolated_a) + (__interpolated_b) + 1");
+		assert_eq!(interpolated.original_index(0), 0);
+		assert_eq!(interpolated.original_index(18), 3);
+		assert_eq!(interpolated.original_index(21), 6);
+		assert_eq!(interpolated.original_index(40), 14);
+		assert_eq!(interpolated.original_index(44), 18);
+	}
+}
@@ -1,9 +1,109 @@
+use saikoro::{bcdice, table::Tables, CriticalMode, Dialect, Script};
+
 fn main()
 {
-	let input = std::env::args().skip(1).collect::<Vec<String>>().join(" ");
-	match saikoro::evaluate(&input)
+	let mut critical = None;
+	let mut dialect = Dialect::SAIKORO;
+	let mut tables = None;
+	let mut expression_args = Vec::new();
+	for arg in std::env::args().skip(1)
 	{
-		Ok(result) => println!("{result}"),
+		if arg == "--crit"
+		{
+			critical = Some(CriticalMode::DoubleDice);
+		}
+		else if let Some(mode) = arg.strip_prefix("--crit=")
+		{
+			match mode.parse()
+			{
+				Ok(mode) => critical = Some(mode),
+				Err(err) =>
+				{
+					eprintln!("{err} (expected one of `double`, `max`, or `total`)");
+					return;
+				}
+			}
+		}
+		else if let Some(name) = arg.strip_prefix("--dialect=")
+		{
+			match name.parse()
+			{
+				Ok(parsed) => dialect = parsed,
+				Err(err) =>
+				{
+					eprintln!("{err} (expected one of `saikoro`, `mathematical`, or `bcdice`)");
+					return;
+				}
+			}
+		}
+		else if let Some(path) = arg.strip_prefix("--tables=")
+		{
+			match std::fs::read_to_string(path).map(|text| text.parse::<Tables>())
+			{
+				Ok(Ok(parsed)) => tables = Some(parsed),
+				Ok(Err(err)) =>
+				{
+					eprintln!("Could not parse tables in \"{path}\"!\n{err}");
+					return;
+				}
+				Err(err) =>
+				{
+					eprintln!("Could not read tables from \"{path}\"!\n{err}");
+					return;
+				}
+			}
+		}
+		else
+		{
+			expression_args.push(arg);
+		}
+	}
+
+	let input = expression_args.join(" ");
+	// BCDice commands are printed in BCDice's own result format
+	if dialect == Dialect::BCDICE
+	{
+		if critical.is_some()
+		{
+			eprintln!("`--crit` cannot be used with the `bcdice` dialect");
+			return;
+		}
+		match bcdice::Command::parse(&input).and_then(|command| command.evaluate())
+		{
+			Ok(result) => println!("{result}"),
+			Err(err) => eprintln!("Could not parse input expression \"{input}\"!\n{err}"),
+		}
+		return;
+	}
+
+	let result = Script::parse_with_dialect(&input, dialect).and_then(|script| {
+		let script = match critical
+		{
+			Some(mode) => script.critical(mode),
+			None => script,
+		};
+		match &tables
+		{
+			Some(tables) => script.eval_with_tables(&mut rand::thread_rng(), tables),
+			None => script.evaluate(),
+		}
+	});
+	match result
+	{
+		Ok(statements) =>
+		{
+			for statement in statements
+			{
+				let prefix = statement
+					.label
+					.as_ref()
+					.map_or_else(String::new, |label| format!("{label}: "));
+				for result in statement.annotate(&input)
+				{
+					println!("{prefix}{result}");
+				}
+			}
+		}
 		Err(err) => eprintln!("Could not parse input expression \"{input}\"!\n{err}"),
 	}
 }
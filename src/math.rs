@@ -0,0 +1,88 @@
+//! Floating point functions which are not available in `core`. With the `std` feature, these use
+//! the standard library's implementations, and otherwise they use [`libm`]
+
+#[cfg(feature = "std")]
+pub fn trunc(x: f64) -> f64
+{
+	x.trunc()
+}
+#[cfg(not(feature = "std"))]
+pub fn trunc(x: f64) -> f64
+{
+	libm::trunc(x)
+}
+
+#[cfg(feature = "std")]
+pub fn floor(x: f64) -> f64
+{
+	x.floor()
+}
+#[cfg(not(feature = "std"))]
+pub fn floor(x: f64) -> f64
+{
+	libm::floor(x)
+}
+
+#[cfg(feature = "std")]
+pub fn powf(x: f64, y: f64) -> f64
+{
+	x.powf(y)
+}
+#[cfg(not(feature = "std"))]
+pub fn powf(x: f64, y: f64) -> f64
+{
+	libm::pow(x, y)
+}
+
+#[cfg(feature = "std")]
+pub fn sqrt(x: f64) -> f64
+{
+	x.sqrt()
+}
+#[cfg(not(feature = "std"))]
+pub fn sqrt(x: f64) -> f64
+{
+	libm::sqrt(x)
+}
+
+#[cfg(feature = "std")]
+pub fn round(x: f64) -> f64
+{
+	x.round()
+}
+#[cfg(not(feature = "std"))]
+pub fn round(x: f64) -> f64
+{
+	libm::round(x)
+}
+
+#[cfg(feature = "std")]
+pub fn ln(x: f64) -> f64
+{
+	x.ln()
+}
+#[cfg(not(feature = "std"))]
+pub fn ln(x: f64) -> f64
+{
+	libm::log(x)
+}
+
+#[cfg(feature = "std")]
+pub fn cos(x: f64) -> f64
+{
+	x.cos()
+}
+#[cfg(not(feature = "std"))]
+pub fn cos(x: f64) -> f64
+{
+	libm::cos(x)
+}
+
+/// Sums `values` starting from 0. Summing with [`Iterator::sum`] starts from -0, so an empty sum
+/// would be displayed as `-0`
+pub fn sum<I>(values: I) -> f64
+where
+	I: IntoIterator<Item = f64>,
+{
+	values.into_iter().fold(0.0, |total, value| total + value)
+}
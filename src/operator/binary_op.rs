@@ -22,6 +22,11 @@ impl BinaryOperator
 			OpToken::Modulus => function::modulo,
 			OpToken::Power => function::power,
 			OpToken::Dice => function::dice,
+			OpToken::ElementPlus => function::element_add,
+			OpToken::ElementMinus => function::element_subtract,
+			OpToken::ElementMultiply => function::element_multiply,
+			OpToken::ElementDivide => function::element_divide,
+			OpToken::FloorDivide => function::floor_divide,
 		}
 	}
 
@@ -55,19 +60,23 @@ impl From<OpToken> for BinaryOperator
 			token,
 			binding_power: match token
 			{
-				Op::Plus | Op::Minus => BindingPower::new(1, 2),
-				Op::Multiply | Op::Divide | Op::Modulus => BindingPower::new(3, 4),
-				Op::Power => BindingPower::new(6, 5),
-				Op::Dice => BindingPower::new(11, 12),
+				Op::Plus | Op::Minus => BindingPower::new(9, 10),
+				Op::Multiply | Op::Divide | Op::Modulus | Op::FloorDivide => BindingPower::new(11, 12),
+				Op::Power => BindingPower::new(14, 13),
+				// element-wise operators bind tighter than any other arithmetic, as they act on the
+				// rolls of the dice term to their left rather than on its total
+				Op::ElementPlus | Op::ElementMinus => BindingPower::new(16, 17),
+				Op::ElementMultiply | Op::ElementDivide => BindingPower::new(17, 18),
+				Op::Dice => BindingPower::new(19, 20),
 			},
 		}
 	}
 }
 
-// Not redundant because it gets re-exported in saikoro::operator -morgan 2024-01-12
-#[allow(clippy::redundant_pub_crate)]
+/// How tightly an operator binds to the expressions on its left and right. A higher binding power
+/// binds more tightly (eg. `*` binds more tightly than `+`)
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub(crate) struct BindingPower
+pub struct BindingPower
 {
 	pub left: u8,
 	pub right: u8,
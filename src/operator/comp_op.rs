@@ -1,7 +1,7 @@
 use crate::{evaluation::Operand, RangeRng};
 
-use super::{function, ParseOperatorError};
-use std::{fmt::Display, str::FromStr};
+use super::{function, function::ApproxEq, BindingPower, ParseOperatorError};
+use core::{fmt::Display, str::FromStr};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum CompOperator
@@ -38,6 +38,48 @@ impl CompOperator
 	{
 		self.eval_fn()(dice_lhs, dice_rhs, compare_to, rand)
 	}
+
+	/// Removes every [`Roll`][crate::evaluation::Roll] in `dice` whose value does not satisfy the
+	/// comparison with `compare_to`. If `dice` is not a [`Roll`][Operand::Roll] variant, it is
+	/// returned unchanged
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::{evaluation::{Operand, Roll, RollGroup}, operator::CompOperator};
+	/// let dice = Operand::from(RollGroup::new(6, [3, 6, 1, 4].map(Roll::new)));
+	/// let filtered = CompOperator::GreaterThan.filter(dice, Operand::Number(3.0));
+	/// assert_eq!(filtered.value(), 10.0);
+	/// ```
+	pub fn filter(self, dice: Operand, compare_to: Operand) -> Operand
+	{
+		function::filter(dice, compare_to, |roll, rhs| {
+			self.compare(roll.modified_value(), rhs.value())
+		})
+	}
+
+	/// The binding power of comparisons used between two values (eg. `(2d6 + 5) > 9`), rather than
+	/// as a filter directly following a dice term
+	pub(crate) const BINDING_POWER: BindingPower = BindingPower::new(7, 8);
+
+	/// Returns whether or not `lhs` and `rhs` satisfy the comparison. Equality is checked to within
+	/// [`f64::EPSILON`]
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::operator::CompOperator;
+	/// assert!(CompOperator::LessOrEqual.compare(2.0, 3.0));
+	/// assert!(!CompOperator::Equals.compare(2.0, 3.0));
+	/// ```
+	pub fn compare(self, lhs: f64, rhs: f64) -> bool
+	{
+		match self
+		{
+			Self::Equals => lhs.approx_eq(rhs),
+			Self::NotEquals => !lhs.approx_eq(rhs),
+			Self::GreaterThan => lhs > rhs,
+			Self::LessThan => lhs < rhs,
+			Self::GreaterOrEqual => lhs >= rhs,
+			Self::LessOrEqual => lhs <= rhs,
+		}
+	}
 }
 impl FromStr for CompOperator
 {
@@ -59,7 +101,7 @@ impl FromStr for CompOperator
 }
 impl Display for CompOperator
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
 	{
 		write!(
 			f,
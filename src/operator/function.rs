@@ -1,9 +1,12 @@
 // i promise i want to consume all of these thanks -morgan 2024-01-14
 #![allow(clippy::needless_pass_by_value)]
+use super::CompOperator;
 use crate::{
+	error::{InvalidDigitError, InvalidFacesError},
 	evaluation::{Operand, Roll, RollGroup},
-	RangeRng,
+	math, RangeRng,
 };
+use alloc::{vec, vec::Vec};
 
 pub fn unary_plus<R: RangeRng>(operand: Operand, _random: &mut R) -> Operand
 {
@@ -20,6 +23,43 @@ pub fn unary_dice<R: RangeRng>(operand: Operand, random: &mut R) -> Operand
 
 	Operand::from(RollGroup::new(faces, [roll]))
 }
+/// Returns an [`InvalidFacesError`] if `faces` is less than one face, which would leave a die with
+/// no values to roll
+pub fn check_faces(faces: &Operand) -> Result<(), InvalidFacesError>
+{
+	match clamp_f64_to_u32(faces.value())
+	{
+		0 => Err(InvalidFacesError(faces.value())),
+		_ => Ok(()),
+	}
+}
+/// Returns an [`InvalidDigitError`] if the rolls of `dice` cannot be read as digits, because it has
+/// more than 10 faces or a kept roll is modified to something other than a whole number from 0 to
+/// 9. As with [`concatenate`], a ten-sided die's 10 is read as 0
+pub fn check_digits(dice: &Operand) -> Result<(), InvalidDigitError>
+{
+	let Operand::Roll { data, .. } = dice
+	else
+	{
+		return Ok(());
+	};
+	if data.faces > 10
+	{
+		return Err(InvalidDigitError(f64::from(data.faces)));
+	}
+	let digit = |value: f64| if data.faces == 10 { value % 10.0 } else { value };
+	match data
+		.kept_values()
+		.find(|value| !(0.0..=9.0).contains(&digit(*value)) || math::trunc(*value) != *value)
+	{
+		Some(value) => Err(InvalidDigitError(value)),
+		None => Ok(()),
+	}
+}
+pub fn logical_not<R: RangeRng>(operand: Operand, _random: &mut R) -> Operand
+{
+	Operand::from(!operand.is_truthy())
+}
 
 pub fn add<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
 {
@@ -37,13 +77,17 @@ pub fn divide<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Opera
 {
 	lhs / rhs
 }
+pub fn floor_divide<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
+{
+	Operand::Number(math::floor(lhs.into_value() / rhs.into_value()))
+}
 pub fn modulo<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
 {
 	lhs % rhs
 }
 pub fn power<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
 {
-	Operand::Number(lhs.into_value().powf(rhs.into_value()))
+	Operand::Number(math::powf(lhs.into_value(), rhs.into_value()))
 }
 pub fn dice<R: RangeRng>(lhs: Operand, rhs: Operand, random: &mut R) -> Operand
 {
@@ -53,6 +97,26 @@ pub fn dice<R: RangeRng>(lhs: Operand, rhs: Operand, random: &mut R) -> Operand
 		random,
 	))
 }
+/// The same as [`dice`], for a dice term whose rolls will only be totalled. Pools of more than
+/// `large_pool_threshold` dice have their total sampled directly (see [`sampled_dice_roll`])
+pub fn totalled_dice<R: RangeRng>(
+	lhs: Operand,
+	rhs: Operand,
+	large_pool_threshold: u32,
+	random: &mut R,
+) -> Operand
+{
+	let count = clamp_f64_to_u32(lhs.value());
+	let faces = clamp_f64_to_u32(rhs.value());
+	Operand::from(if count > large_pool_threshold && faces > 1
+	{
+		sampled_dice_roll(count, faces, random)
+	}
+	else
+	{
+		dice_roll(count, faces, random)
+	})
+}
 
 pub fn eq_roll_comp<R: RangeRng>(
 	dice_lhs: Operand,
@@ -65,7 +129,7 @@ pub fn eq_roll_comp<R: RangeRng>(
 		clamp_f64_to_u32(dice_lhs.value()),
 		clamp_f64_to_u32(dice_rhs.value()),
 		compare_to,
-		|l, r| f64::from(l.original_value).approx_eq(r.value()),
+		|l, r| l.modified_value().approx_eq(r.value()),
 		random,
 	))
 }
@@ -80,7 +144,7 @@ pub fn ne_roll_comp<R: RangeRng>(
 		clamp_f64_to_u32(dice_lhs.value()),
 		clamp_f64_to_u32(dice_rhs.value()),
 		compare_to,
-		|l, r| !f64::from(l.original_value).approx_eq(r.value()),
+		|l, r| !l.modified_value().approx_eq(r.value()),
 		random,
 	))
 }
@@ -95,7 +159,7 @@ pub fn greater_roll_comp<R: RangeRng>(
 		clamp_f64_to_u32(dice_lhs.value()),
 		clamp_f64_to_u32(dice_rhs.value()),
 		compare_to,
-		|l, r| f64::from(l.original_value) > r.value(),
+		|l, r| l.modified_value() > r.value(),
 		random,
 	))
 }
@@ -110,7 +174,7 @@ pub fn less_roll_comp<R: RangeRng>(
 		clamp_f64_to_u32(dice_lhs.value()),
 		clamp_f64_to_u32(dice_rhs.value()),
 		compare_to,
-		|l, r| f64::from(l.original_value) < r.value(),
+		|l, r| l.modified_value() < r.value(),
 		random,
 	))
 }
@@ -125,7 +189,7 @@ pub fn greater_eq_roll_comp<R: RangeRng>(
 		clamp_f64_to_u32(dice_lhs.value()),
 		clamp_f64_to_u32(dice_rhs.value()),
 		compare_to,
-		|l, r| f64::from(l.original_value) >= r.value(),
+		|l, r| l.modified_value() >= r.value(),
 		random,
 	))
 }
@@ -140,11 +204,376 @@ pub fn less_eq_roll_comp<R: RangeRng>(
 		clamp_f64_to_u32(dice_lhs.value()),
 		clamp_f64_to_u32(dice_rhs.value()),
 		compare_to,
-		|l, r| f64::from(l.original_value) <= r.value(),
+		|l, r| l.modified_value() <= r.value(),
 		random,
 	))
 }
 
+pub fn keep_highest<R: RangeRng>(dice: Operand, count: Option<Operand>, _random: &mut R)
+	-> Operand
+{
+	map_roll(dice, |group| {
+		let count = modifier_count(count);
+		keep_by_key(group, count, |roll| -roll.modified_value())
+	})
+}
+pub fn keep_lowest<R: RangeRng>(dice: Operand, count: Option<Operand>, _random: &mut R)
+	-> Operand
+{
+	map_roll(dice, |group| {
+		let count = modifier_count(count);
+		keep_by_key(group, count, Roll::modified_value)
+	})
+}
+pub fn drop_highest<R: RangeRng>(dice: Operand, count: Option<Operand>, _random: &mut R)
+	-> Operand
+{
+	map_roll(dice, |group| {
+		let count = kept_count(&group).saturating_sub(modifier_count(count));
+		keep_by_key(group, count, Roll::modified_value)
+	})
+}
+pub fn drop_lowest<R: RangeRng>(dice: Operand, count: Option<Operand>, _random: &mut R)
+	-> Operand
+{
+	map_roll(dice, |group| {
+		let count = kept_count(&group).saturating_sub(modifier_count(count));
+		keep_by_key(group, count, |roll| -roll.modified_value())
+	})
+}
+pub fn advantage<R: RangeRng>(dice: Operand, _argument: Option<Operand>, random: &mut R) -> Operand
+{
+	map_roll(dice, |group| {
+		let count = kept_count(&group);
+		let group = reroll_into(group, random);
+		keep_by_key(group, count, |roll| -roll.modified_value())
+	})
+}
+pub fn disadvantage<R: RangeRng>(dice: Operand, _argument: Option<Operand>, random: &mut R)
+	-> Operand
+{
+	map_roll(dice, |group| {
+		let count = kept_count(&group);
+		let group = reroll_into(group, random);
+		keep_by_key(group, count, Roll::modified_value)
+	})
+}
+
+/// Rolls an extra die for each kept roll whose natural value satisfies `comp_op` with `target`
+/// (the number of faces if [`None`]). Unless `once`, extra dice which satisfy it explode as well
+pub fn explode<R: RangeRng>(
+	dice: Operand,
+	comp_op: CompOperator,
+	target: Option<Operand>,
+	once: bool,
+	random: &mut R,
+) -> Operand
+{
+	map_roll(dice, |group| {
+		let target = target.map_or(f64::from(group.faces), |it| it.value());
+		let limit = if once { 1 } else { MAX_REROLLS };
+		let mut extra = Vec::new();
+		for roll in group.iter().filter(|it| !it.is_removed())
+		{
+			let mut last = roll.original_value;
+			for _ in 0..limit
+			{
+				if !comp_op.compare(f64::from(last), target)
+				{
+					break;
+				}
+				last = single_roll(group.faces, random);
+				extra.push(Roll::new(last));
+			}
+		}
+
+		let mut group = group;
+		group.extend(extra);
+		group
+	})
+}
+/// The same as [`explode`], but each extra die is added to the roll which exploded rather than
+/// kept as a roll of its own
+pub fn compound<R: RangeRng>(
+	dice: Operand,
+	comp_op: CompOperator,
+	target: Option<Operand>,
+	random: &mut R,
+) -> Operand
+{
+	map_roll(dice, |group| {
+		let target = target.map_or(f64::from(group.faces), |it| it.value());
+		let faces = group.faces;
+		let mut group = group;
+		for roll in group.iter_mut().filter(|it| !it.is_removed())
+		{
+			let mut last = roll.original_value;
+			let mut total = last;
+			for _ in 0..MAX_REROLLS
+			{
+				if !comp_op.compare(f64::from(last), target)
+				{
+					break;
+				}
+				last = single_roll(faces, random);
+				total = total.saturating_add(last);
+			}
+			*roll = Roll::new(total);
+		}
+		group
+	})
+}
+/// Removes each kept roll whose natural value satisfies `comp_op` with `target` (1 if [`None`]),
+/// and rolls a new die in its place. Unless `once`, new dice which satisfy it are rerolled as well
+pub fn reroll<R: RangeRng>(
+	dice: Operand,
+	comp_op: CompOperator,
+	target: Option<Operand>,
+	once: bool,
+	random: &mut R,
+) -> Operand
+{
+	let target = target.map_or(1.0, |it| it.value());
+	reroll_any(dice, &[(comp_op, target)], once, random)
+}
+/// The same as [`reroll`], but rerolls each roll whose natural value satisfies any of the
+/// `conditions`, so that a new die is only kept once it satisfies none of them
+pub fn reroll_any<R: RangeRng>(
+	dice: Operand,
+	conditions: &[(CompOperator, f64)],
+	once: bool,
+	random: &mut R,
+) -> Operand
+{
+	let satisfies_any = |roll: &Roll| {
+		conditions
+			.iter()
+			.any(|(comp_op, target)| comp_op.compare(f64::from(roll.original_value), *target))
+	};
+	map_roll(dice, |group| {
+		let faces = group.faces;
+		let limit = if once { 1 } else { MAX_REROLLS };
+		let mut group = group;
+		let mut replacements = Vec::new();
+		for roll in group.iter_mut().filter(|it| !it.is_removed())
+		{
+			if !satisfies_any(roll)
+			{
+				continue;
+			}
+			roll.remove();
+
+			let mut replacement = Roll::new(single_roll(faces, random));
+			for _ in 1..limit
+			{
+				if !satisfies_any(&replacement)
+				{
+					break;
+				}
+				replacements.push(replacement.into_removed());
+				replacement = Roll::new(single_roll(faces, random));
+			}
+			replacements.push(replacement);
+		}
+		group.extend(replacements);
+		group
+	})
+}
+/// Sets the range of natural values counted as critical successes (or failures, unless
+/// `success`) of the group to those which satisfy `comp_op` with `target`. If `target` is
+/// [`None`], it is the number of faces for successes and 1 for failures
+pub fn critical_range(
+	dice: Operand,
+	comp_op: CompOperator,
+	target: Option<Operand>,
+	success: bool,
+) -> Operand
+{
+	map_roll(dice, |group| {
+		let mut group = group;
+		if success
+		{
+			let target = target.map_or(f64::from(group.faces), |it| it.value());
+			group.set_critical_success(comp_op, target);
+		}
+		else
+		{
+			group.set_critical_failure(comp_op, target.map_or(1.0, |it| it.value()));
+		}
+		group
+	})
+}
+/// Marks the group so that its kept rolls are read as the digits of one number rather than summed
+pub fn concatenate(dice: Operand) -> Operand
+{
+	map_roll(dice, |group| {
+		let mut group = group;
+		group.set_concatenated();
+		group
+	})
+}
+/// Sorts the rolls of the group by their modified values, from lowest to highest unless
+/// `descending`
+pub fn sort_rolls(dice: Operand, descending: bool) -> Operand
+{
+	map_roll(dice, |group| {
+		let mut group = group;
+		group.sort_rolls_by(|l, r| {
+			let ordering = l.modified_value().total_cmp(&r.modified_value());
+			if descending { ordering.reverse() } else { ordering }
+		});
+		group
+	})
+}
+
+pub fn element_add<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
+{
+	element_wise(lhs, rhs, |l, r| l + r)
+}
+pub fn element_subtract<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
+{
+	element_wise(lhs, rhs, |l, r| l - r)
+}
+pub fn element_multiply<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
+{
+	element_wise(lhs, rhs, |l, r| l * r)
+}
+pub fn element_divide<R: RangeRng>(lhs: Operand, rhs: Operand, _random: &mut R) -> Operand
+{
+	element_wise(lhs, rhs, |l, r| l / r)
+}
+
+/// Concatenates the values of every argument into one list, where dice terms contribute each of
+/// their kept rolls
+pub fn list(arguments: Vec<Operand>) -> Operand
+{
+	Operand::List(arguments.iter().flat_map(Operand::values).collect())
+}
+pub fn count(list: Operand) -> Operand
+{
+	Operand::Number(list.values().len() as f64)
+}
+pub fn sum(list: Operand) -> Operand
+{
+	Operand::Number(list.value())
+}
+pub fn sort(list: Operand) -> Operand
+{
+	let mut values = list.values();
+	values.sort_by(f64::total_cmp);
+	Operand::List(values.into())
+}
+pub fn highest(list: Operand, count: Option<Operand>) -> Operand
+{
+	let mut values = list.values();
+	values.sort_by(|l, r| r.total_cmp(l));
+	values.truncate(modifier_count(count));
+	Operand::List(values.into())
+}
+pub fn lowest(list: Operand, count: Option<Operand>) -> Operand
+{
+	let mut values = list.values();
+	values.sort_by(f64::total_cmp);
+	values.truncate(modifier_count(count));
+	Operand::List(values.into())
+}
+
+pub fn plus_maximum(dice: Operand) -> Operand
+{
+	map_roll(dice, |group| {
+		let mut group = group;
+		let maximums = vec![Roll::new(group.faces); kept_count(&group)];
+		group.extend(maximums);
+		group
+	})
+}
+
+pub fn filter<F>(dice: Operand, compare_to: Operand, predicate: F) -> Operand
+where
+	F: Fn(&Roll, &Operand) -> bool,
+{
+	map_roll(dice, |group| comparison(group, compare_to, predicate))
+}
+
+/// Applies `op` to the modified value of every roll in `lhs` with the value of `rhs`. If `lhs` is a
+/// [`List`][Operand::List] variant, `op` is applied to each of its elements, and if it is a
+/// [`Number`][Operand::Number] variant, `op` is applied to its value
+fn element_wise<F>(lhs: Operand, rhs: Operand, op: F) -> Operand
+where
+	F: Fn(f64, f64) -> f64,
+{
+	let rhs = rhs.value();
+	match lhs
+	{
+		Operand::Number(n) => Operand::Number(op(n, rhs)),
+		Operand::List(values) => Operand::List(values.iter().map(|it| op(*it, rhs)).collect()),
+		roll => map_roll(roll, |group| group.map_modified(|value| op(value, rhs))),
+	}
+}
+
+fn map_roll<F>(operand: Operand, map: F) -> Operand
+where
+	F: FnOnce(RollGroup) -> RollGroup,
+{
+	match operand
+	{
+		Operand::Roll { id, data } => Operand::Roll {
+			id,
+			data: map(data),
+		},
+		other => other,
+	}
+}
+
+fn modifier_count(count: Option<Operand>) -> usize
+{
+	count.map_or(1, |it| clamp_f64_to_u32(it.value()) as usize)
+}
+
+fn kept_count(group: &RollGroup) -> usize
+{
+	group.iter().filter(|it| !it.is_removed()).count()
+}
+
+/// Rolls each die in the group again, adding the new rolls to the end of the group
+fn reroll_into<R: RangeRng>(group: RollGroup, random: &mut R) -> RollGroup
+{
+	let mut group = group;
+	let extra = dice_roll(kept_count(&group) as u32, group.faces, random);
+	group.extend(extra.iter().copied());
+	group
+}
+
+/// Removes all but `count` of the kept rolls in `group`, keeping the ones which come first when
+/// sorted by `key`. Rolls with equal keys are kept in the order they were rolled
+fn keep_by_key<F>(group: RollGroup, count: usize, key: F) -> RollGroup
+where
+	F: Fn(&Roll) -> f64,
+{
+	let mut group = group;
+	let mut kept_indices = group
+		.iter()
+		.enumerate()
+		.filter(|(_, roll)| !roll.is_removed())
+		.map(|(i, roll)| (i, key(roll)))
+		.collect::<Vec<_>>();
+	kept_indices.sort_by(|(_, l), (_, r)| l.total_cmp(r));
+
+	let to_remove = kept_indices
+		.into_iter()
+		.skip(count)
+		.map(|(i, _)| i)
+		.collect::<Vec<_>>();
+	for (i, roll) in group.iter_mut().enumerate()
+	{
+		if to_remove.contains(&i)
+		{
+			roll.remove();
+		}
+	}
+	group
+}
+
 fn roll_compare<F, R>(
 	count: u32,
 	faces: u32,
@@ -165,6 +594,17 @@ where
 	F: Fn(&Roll, &Operand) -> bool,
 {
 	let mut lhs = lhs;
+	// the digits of a concatenated group are compared as the one number they make, and are all
+	// removed if it does not satisfy the comparison
+	if lhs.is_concatenated()
+	{
+		let number = Roll::new(0).into_modified(|_| lhs.total());
+		if !predicate(&number, &rhs)
+		{
+			lhs.iter_mut().for_each(Roll::remove);
+		}
+		return lhs;
+	}
 	for roll in &mut lhs
 	{
 		roll.remove_unless(|it| predicate(it, &rhs));
@@ -174,17 +614,56 @@ where
 
 fn dice_roll<R: RangeRng>(count: u32, faces: u32, random: &mut R) -> RollGroup
 {
-	let values = (0..count).map(|_| Roll::new(random.rng_range(0..faces) + 1));
+	let values = (0..count).map(|_| Roll::new(single_roll(faces, random)));
 	RollGroup::new(faces, values)
 }
 
+fn single_roll<R: RangeRng>(faces: u32, random: &mut R) -> u32
+{
+	random.rng_range(0..faces) + 1
+}
+
+/// The most times a single die can explode or be rerolled, so that a comparison which every roll
+/// satisfies (eg. exploding on anything at least 1) cannot roll forever
+const MAX_REROLLS: usize = 100;
+
+/// Samples the total of `count` dice with `faces` faces from the normal distribution with the same
+/// mean and variance as their sum, in constant time and space, rather than rolling each die
+///
+/// The sum of independent dice tends to a normal distribution as the number of dice grows, and
+/// since the distribution of a single die is symmetric, the approximation error shrinks in
+/// proportion to `1 / count` rather than `1 / sqrt(count)`. For pools of many thousands of dice
+/// it is far smaller than any simulation could detect, but it is not exact: totals more than
+/// about 6.8 standard deviations from the mean are never produced, and the individual rolls are
+/// not known
+fn sampled_dice_roll<R: RangeRng>(count: u32, faces: u32, random: &mut R) -> RollGroup
+{
+	let (count_f64, faces_f64) = (f64::from(count), f64::from(faces));
+	let mean = count_f64 * (faces_f64 + 1.0) / 2.0;
+	let stdev = math::sqrt(count_f64 * (faces_f64 * faces_f64 - 1.0) / 12.0);
+
+	// rounding to the nearest whole number is the continuity correction for the discrete sum
+	let total = math::round(mean + stdev * standard_normal(random))
+		.clamp(count_f64, count_f64 * faces_f64);
+	RollGroup::sampled(faces, count, total)
+}
+
+/// Generates a standard normal value with the Box-Muller transform
+fn standard_normal<R: RangeRng>(random: &mut R) -> f64
+{
+	// strictly between 0 and 1, so that the logarithm is finite
+	let mut uniform = || (f64::from(random.rng_range(0..u32::MAX)) + 0.5) / f64::from(u32::MAX);
+	let radius = math::sqrt(-2.0 * math::ln(uniform()));
+	radius * math::cos(core::f64::consts::TAU * uniform())
+}
+
 #[allow(clippy::cast_sign_loss)]
 fn clamp_f64_to_u32(value: f64) -> u32
 {
 	value.clamp(f64::from(u32::MIN), f64::from(u32::MAX)) as u32
 }
 
-trait ApproxEq
+pub(super) trait ApproxEq
 {
 	fn approx_eq(self, rhs: Self) -> bool;
 }
@@ -199,7 +678,10 @@ impl ApproxEq for f64
 #[cfg(test)]
 mod tests
 {
-	use crate::{evaluation::Operand, test_helpers::RiggedRandom};
+	use crate::{
+		evaluation::{Operand, Roll, RollGroup},
+		test_helpers::RiggedRandom,
+	};
 
 	#[test]
 	fn add()
@@ -261,6 +743,132 @@ mod tests
 		)
 		.approx_eq(&Operand::Number(1728.0)));
 	}
+	#[test]
+	fn keep_highest()
+	{
+		let kept = super::keep_highest(
+			roll_of(6, &[3, 6, 1, 6]),
+			Some(Operand::Number(2.0)),
+			&mut rand::thread_rng(),
+		);
+		assert_eq!(removed_flags(&kept), vec![true, false, true, false]);
+	}
+	#[test]
+	fn keep_lowest()
+	{
+		let kept = super::keep_lowest(roll_of(6, &[3, 6, 1, 4]), None, &mut rand::thread_rng());
+		assert_eq!(removed_flags(&kept), vec![true, true, false, true]);
+	}
+	#[test]
+	fn drop_highest()
+	{
+		let kept = super::drop_highest(roll_of(6, &[3, 6, 1, 4]), None, &mut rand::thread_rng());
+		assert_eq!(removed_flags(&kept), vec![false, true, false, false]);
+	}
+	#[test]
+	fn drop_lowest()
+	{
+		let kept = super::drop_lowest(
+			roll_of(6, &[3, 6, 1, 4]),
+			Some(Operand::Number(2.0)),
+			&mut rand::thread_rng(),
+		);
+		assert_eq!(removed_flags(&kept), vec![true, false, true, false]);
+	}
+	#[test]
+	fn advantage()
+	{
+		let rolled = super::advantage(
+			roll_of(20, &[8]),
+			None,
+			&mut RiggedRandom::new([15]),
+		);
+		assert!(rolled.approx_eq(&Operand::Number(15.0)));
+		assert_eq!(removed_flags(&rolled), vec![true, false]);
+	}
+	#[test]
+	fn disadvantage()
+	{
+		let rolled = super::disadvantage(
+			roll_of(20, &[8]),
+			None,
+			&mut RiggedRandom::new([15]),
+		);
+		assert!(rolled.approx_eq(&Operand::Number(8.0)));
+		assert_eq!(removed_flags(&rolled), vec![false, true]);
+	}
+
+	#[test]
+	fn element_wise()
+	{
+		let added = super::element_add(
+			roll_of(6, &[3, 6, 1]),
+			Operand::Number(2.0),
+			&mut rand::thread_rng(),
+		);
+		assert!(added.approx_eq(&Operand::Number(16.0)));
+		let Operand::Roll { data, .. } = &added
+		else
+		{
+			panic!("expected roll")
+		};
+		assert_eq!(
+			data.iter().map(|it| it.original_value).collect::<Vec<_>>(),
+			vec![3, 6, 1]
+		);
+		assert!(data.iter().all(Roll::is_modified));
+
+		// keep/drop compare modified values
+		let halved = super::element_divide(
+			roll_of(6, &[4, 6, 1]),
+			Operand::Number(-2.0),
+			&mut rand::thread_rng(),
+		);
+		let kept = super::keep_highest(halved, None, &mut rand::thread_rng());
+		assert_eq!(removed_flags(&kept), vec![true, true, false]);
+		assert!(kept.approx_eq(&Operand::Number(-0.5)));
+
+		assert!(super::element_multiply(
+			Operand::Number(3.0),
+			Operand::Number(2.0),
+			&mut rand::thread_rng()
+		)
+		.approx_eq(&Operand::Number(6.0)));
+	}
+
+	#[test]
+	fn lists()
+	{
+		let list = || roll_of(10, &[7, 2, 9, 2, 5]);
+		assert_eq!(super::count(list()), Operand::Number(5.0));
+		assert_eq!(super::sum(list()), Operand::Number(25.0));
+		assert_eq!(
+			super::sort(list()),
+			Operand::List(Box::new([2.0, 2.0, 5.0, 7.0, 9.0]))
+		);
+		assert_eq!(
+			super::highest(list(), Some(Operand::Number(2.0))),
+			Operand::List(Box::new([9.0, 7.0]))
+		);
+		assert_eq!(
+			super::lowest(list(), None),
+			Operand::List(Box::new([2.0]))
+		);
+	}
+
+	fn roll_of(faces: u32, values: &[u32]) -> Operand
+	{
+		Operand::from(RollGroup::new(faces, values.iter().copied().map(Roll::new)))
+	}
+	fn removed_flags(operand: &Operand) -> Vec<bool>
+	{
+		match operand
+		{
+			Operand::Roll { data, .. } => data.iter().map(Roll::is_removed).collect(),
+			Operand::Number(_) | Operand::List(_) => panic!("expected roll operand"),
+		}
+	}
+
 	#[test]
 	fn dice()
 	{
@@ -271,4 +879,32 @@ mod tests
 		)
 		.approx_eq(&Operand::Number(12.0)));
 	}
+
+	#[test]
+	fn sampled_dice()
+	{
+		use rand::{rngs::StdRng, SeedableRng};
+
+		let mut rng = StdRng::seed_from_u64(41);
+		let totals = (0..2000)
+			.map(|_| super::sampled_dice_roll(10_000, 6, &mut rng).total())
+			.collect::<Vec<_>>();
+		assert!(totals.iter().all(|it| it.fract() == 0.0));
+
+		// 10000d6 has a mean of 35000 and a standard deviation of about 170.8
+		let mean = totals.iter().sum::<f64>() / 2000.0;
+		let stdev = (totals.iter().map(|it| (it - mean).powi(2)).sum::<f64>() / 2000.0).sqrt();
+		assert!((mean - 35_000.0).abs() < 20.0, "mean was {mean}");
+		assert!((stdev - 170.8).abs() < 15.0, "stdev was {stdev}");
+
+		// only pools above the threshold are sampled
+		let rolled = super::totalled_dice(
+			Operand::Number(4.0),
+			Operand::Number(6.0),
+			4,
+			&mut RiggedRandom::new([4, 1, 5, 2]),
+		);
+		assert!(matches!(&rolled, Operand::Roll { data, .. } if !data.is_sampled()));
+		assert!(rolled.approx_eq(&Operand::Number(12.0)));
+	}
 }
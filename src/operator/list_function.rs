@@ -0,0 +1,130 @@
+use super::{function, ParseOperatorError};
+use crate::{
+	error::{InvalidValueCountError, ParsingError},
+	evaluation::Operand,
+	math,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::{fmt::Display, ops::RangeInclusive, str::FromStr};
+
+/// Represents a function which treats its arguments as lists of values (eg. the rolls of a dice
+/// term). [`Roll`][Operand::Roll] arguments are treated as the list of their kept rolls'
+/// modified values, or of the one number their digits make if they are
+/// [concatenated][crate::evaluation::RollGroup::is_concatenated]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ListFunction
+{
+	/// The number of values in the list (`count`). With a filter, this counts how many rolls
+	/// satisfied it (eg. `count(6d6 == 6)`)
+	Count,
+	/// The sum of the list (`sum`)
+	Sum,
+	/// The list sorted from lowest to highest (`sort`)
+	Sort,
+	/// The highest `n` values of the list, from highest to lowest (`highest`). If `n` is omitted,
+	/// it is treated as 1
+	Highest,
+	/// The lowest `n` values of the list, from lowest to highest (`lowest`). If `n` is omitted, it
+	/// is treated as 1
+	Lowest,
+	/// Every value of every argument, in order (`list`). For example, `list(2d6, 3)` is the two
+	/// rolls of `2d6` followed by 3, and `list(sum(2d6), sum(1d8))` is the total of each term
+	List,
+}
+impl ListFunction
+{
+	/// Returns the range of the number of arguments the function can be called with
+	pub fn arity(self) -> RangeInclusive<usize>
+	{
+		match self
+		{
+			Self::Count | Self::Sum | Self::Sort => 1..=1,
+			Self::Highest | Self::Lowest => 1..=2,
+			Self::List => 1..=usize::MAX,
+		}
+	}
+
+	/// Applies the function to `arguments`, which are expected to be within the function's
+	/// [`arity`][ListFunction::arity]. Missing arguments are treated as their default values
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::{evaluation::{Operand, Roll, RollGroup}, operator::ListFunction};
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// let dice = Operand::from(RollGroup::new(6, [3, 6, 1, 4].map(Roll::new)));
+	/// let highest = ListFunction::Highest.eval(vec![dice, Operand::Number(2.0)])?;
+	/// assert_eq!(highest, Operand::List(Box::new([6.0, 4.0])));
+	/// # Ok(())
+	/// # }
+	/// ```
+	/// # Errors
+	/// Returns an [`InvalidValueCountError`] if the number of values for [`Highest`] or [`Lowest`]
+	/// is negative or not a whole number
+	///
+	/// [`Highest`]: ListFunction::Highest
+	/// [`Lowest`]: ListFunction::Lowest
+	pub fn eval(self, arguments: Vec<Operand>) -> Result<Operand, ParsingError>
+	{
+		let mut arguments = arguments.into_iter();
+		let list = arguments.next().unwrap_or(Operand::List(Box::new([])));
+		Ok(match self
+		{
+			Self::Count => function::count(list),
+			Self::Sum => function::sum(list),
+			Self::Sort => function::sort(list),
+			Self::Highest => function::highest(list, self.value_count(arguments.next())?),
+			Self::Lowest => function::lowest(list, self.value_count(arguments.next())?),
+			Self::List => function::list(core::iter::once(list).chain(arguments).collect()),
+		})
+	}
+
+	/// Checks that `count`, the number of values to take from a list, is a non-negative whole
+	/// number
+	fn value_count(self, count: Option<Operand>) -> Result<Option<Operand>, InvalidValueCountError>
+	{
+		match count.as_ref().map(Operand::value)
+		{
+			Some(value) if value < 0.0 || math::trunc(value) != value => Err(InvalidValueCountError {
+				function: self,
+				count: value,
+			}),
+			_ => Ok(count),
+		}
+	}
+}
+impl FromStr for ListFunction
+{
+	type Err = ParseOperatorError;
+
+	fn from_str(str: &str) -> Result<Self, Self::Err>
+	{
+		match str
+		{
+			"count" => Ok(Self::Count),
+			"sum" => Ok(Self::Sum),
+			"sort" => Ok(Self::Sort),
+			"highest" => Ok(Self::Highest),
+			"lowest" => Ok(Self::Lowest),
+			"list" => Ok(Self::List),
+			unrecognized => Err(ParseOperatorError::from(unrecognized)),
+		}
+	}
+}
+impl Display for ListFunction
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		write!(
+			f,
+			"{}",
+			match self
+			{
+				Self::Count => "count",
+				Self::Sum => "sum",
+				Self::Sort => "sort",
+				Self::Highest => "highest",
+				Self::Lowest => "lowest",
+				Self::List => "list",
+			}
+		)
+	}
+}
@@ -0,0 +1,53 @@
+use super::{BindingPower, ParseOperatorError};
+use core::{fmt::Display, str::FromStr};
+
+/// Represents a logical operator joining two conditions. The right-hand side is only evaluated if
+/// the left-hand side does not already decide the result, so it may not roll any dice
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LogicOperator
+{
+	/// Produces 1 if both sides are non-zero, and 0 otherwise (`and`)
+	And,
+	/// Produces 1 if either side is non-zero, and 0 otherwise (`or`)
+	Or,
+}
+impl LogicOperator
+{
+	pub(crate) const fn binding_power(self) -> BindingPower
+	{
+		match self
+		{
+			Self::Or => BindingPower::new(1, 2),
+			Self::And => BindingPower::new(3, 4),
+		}
+	}
+}
+impl FromStr for LogicOperator
+{
+	type Err = ParseOperatorError;
+
+	fn from_str(str: &str) -> Result<Self, Self::Err>
+	{
+		match str
+		{
+			"and" => Ok(Self::And),
+			"or" => Ok(Self::Or),
+			unrecognized => Err(ParseOperatorError::from(unrecognized)),
+		}
+	}
+}
+impl Display for LogicOperator
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		write!(
+			f,
+			"{}",
+			match self
+			{
+				Self::And => "and",
+				Self::Or => "or",
+			}
+		)
+	}
+}
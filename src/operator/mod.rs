@@ -3,13 +3,20 @@
 mod binary_op;
 mod comp_op;
 pub(crate) mod function;
+mod list_function;
+mod logic_op;
+mod modifier;
 mod unary_op;
 
 pub use binary_op::*;
 pub use comp_op::*;
+pub use list_function::*;
+pub use logic_op::*;
+pub use modifier::*;
 pub use unary_op::*;
 
-use std::{fmt::Display, str::FromStr};
+use alloc::{borrow::ToOwned, boxed::Box};
+use core::{fmt::Display, str::FromStr};
 use thiserror::Error;
 
 /// An enum representing a token which corresponds to an operator
@@ -23,6 +30,29 @@ pub enum OpToken
 	Modulus,
 	Power,
 	Dice,
+	/// Element-wise addition (`.+`), adding to each roll of a dice term individually
+	ElementPlus,
+	/// Element-wise subtraction (`.-`), subtracting from each roll of a dice term individually
+	ElementMinus,
+	/// Element-wise multiplication (`.*`), multiplying each roll of a dice term individually
+	ElementMultiply,
+	/// Element-wise division (`./`), dividing each roll of a dice term individually
+	ElementDivide,
+	/// Division which rounds the quotient down to a whole number. This is what `/` means in
+	/// dialects with [floor division][crate::Dialect::with_floor_division] (eg. BCDice's `7/2` is 3)
+	FloorDivide,
+}
+impl OpToken
+{
+	/// Returns whether or not the operator applies to each roll of a dice term individually,
+	/// rather than to its total
+	pub fn is_element_wise(self) -> bool
+	{
+		matches!(
+			self,
+			Self::ElementPlus | Self::ElementMinus | Self::ElementMultiply | Self::ElementDivide
+		)
+	}
 }
 impl FromStr for OpToken
 {
@@ -39,13 +69,17 @@ impl FromStr for OpToken
 			"%" => Ok(Self::Modulus),
 			"^" => Ok(Self::Power),
 			"d" | "D" => Ok(Self::Dice),
+			".+" => Ok(Self::ElementPlus),
+			".-" => Ok(Self::ElementMinus),
+			".*" => Ok(Self::ElementMultiply),
+			"./" => Ok(Self::ElementDivide),
 			unrecognized => Err(ParseOperatorError::from(unrecognized)),
 		}
 	}
 }
 impl Display for OpToken
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
 	{
 		write!(
 			f,
@@ -59,6 +93,11 @@ impl Display for OpToken
 				Self::Modulus => "%",
 				Self::Power => "^",
 				Self::Dice => "d",
+				Self::ElementPlus => ".+",
+				Self::ElementMinus => ".-",
+				Self::ElementMultiply => ".*",
+				Self::ElementDivide => "./",
+				Self::FloorDivide => "/",
 			}
 		)
 	}
@@ -0,0 +1,174 @@
+use super::{function, CompOperator, ParseOperatorError};
+use crate::{evaluation::Operand, RangeRng};
+use core::{fmt::Display, str::FromStr};
+
+/// Represents a modifier which can be written directly after a dice term to change which of its
+/// [`Roll`][crate::evaluation::Roll]s are kept (eg. the `kh3` in `4d6kh3`)
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DiceModifier
+{
+	/// Keeps only the highest `n` rolls (`kh`/`k`)
+	KeepHighest,
+	/// Keeps only the lowest `n` rolls (`kl`)
+	KeepLowest,
+	/// Removes the highest `n` rolls (`dh`)
+	DropHighest,
+	/// Removes the lowest `n` rolls (`dl`)
+	DropLowest,
+	/// Rolls the dice a second time, and keeps only the highest half of all rolls (`adv`). With a
+	/// single die, this is equivalent to `2d20kh1`
+	Advantage,
+	/// Rolls the dice a second time, and keeps only the lowest half of all rolls (`dis`). With a
+	/// single die, this is equivalent to `2d20kl1`
+	Disadvantage,
+	/// Rolls an extra die for each roll whose natural value satisfies the comparison with the
+	/// argument, which is the number of faces if omitted. Extra rolls can explode as well. Not
+	/// produced by the default syntax, only by front ends such as [`roll20`][crate::roll20]
+	Explode(CompOperator),
+	/// The same as [`Explode`][DiceModifier::Explode], but extra rolls do not explode
+	ExplodeOnce(CompOperator),
+	/// The same as [`Explode`][DiceModifier::Explode], but each extra die is added to the roll
+	/// which exploded rather than kept as a roll of its own
+	Compound(CompOperator),
+	/// Removes each roll whose natural value satisfies the comparison with the argument (1 if
+	/// omitted) and rolls a new die in its place, until the new die does not satisfy it
+	Reroll(CompOperator),
+	/// The same as [`Reroll`][DiceModifier::Reroll], but each roll is only rerolled once
+	RerollOnce(CompOperator),
+	/// Counts rolls whose natural value satisfies the comparison with the argument (the number of
+	/// faces if omitted) as critical successes of their [`RollGroup`], rather than only rolls of
+	/// the maximum value
+	///
+	/// [`RollGroup`]: crate::evaluation::RollGroup
+	CriticalSuccess(CompOperator),
+	/// Counts rolls whose natural value satisfies the comparison with the argument (1 if omitted)
+	/// as critical failures of their [`RollGroup`], rather than only rolls of 1
+	///
+	/// [`RollGroup`]: crate::evaluation::RollGroup
+	CriticalFailure(CompOperator),
+	/// Reads the kept rolls as the decimal digits of one number, from first to last, rather than
+	/// summing them (`cat`, see [`RollGroup::is_concatenated`]). Sorting the rolls first reads the
+	/// digits in order (eg. `2d6 sa cat` reads a 5 and a 3 as 35)
+	///
+	/// [`RollGroup::is_concatenated`]: crate::evaluation::RollGroup::is_concatenated
+	Concatenate,
+	/// Sorts the rolls from lowest to highest, without changing which are kept (`sa`)
+	SortAscending,
+	/// Sorts the rolls from highest to lowest, without changing which are kept (`sd`)
+	SortDescending,
+}
+impl DiceModifier
+{
+	/// Returns whether or not the modifier can be followed by an argument, which is either the
+	/// number of rolls it applies to (eg. the `3` in `kh3`) or the value rolls are compared with
+	pub fn takes_argument(self) -> bool
+	{
+		!matches!(
+			self,
+			Self::Advantage
+				| Self::Disadvantage
+				| Self::Concatenate
+				| Self::SortAscending
+				| Self::SortDescending
+		)
+	}
+
+	/// Returns whether or not the argument of the modifier is a number of rolls to keep or drop
+	pub(crate) fn counts_rolls(self) -> bool
+	{
+		matches!(
+			self,
+			Self::KeepHighest | Self::KeepLowest | Self::DropHighest | Self::DropLowest
+		)
+	}
+
+	/// Applies the modifier to `dice`, using `argument` as the number of rolls to keep or drop, or
+	/// the value rolls are compared with, where applicable. If a number of rolls is [`None`], it is
+	/// treated as 1. If `dice` is not a [`Roll`][Operand::Roll] variant, it is returned unchanged
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::{evaluation::{Operand, Roll, RollGroup}, operator::DiceModifier};
+	/// let dice = Operand::from(RollGroup::new(6, [3, 6, 1, 4].map(Roll::new)));
+	/// let kept = DiceModifier::KeepHighest.eval(
+	///     dice,
+	///     Some(Operand::Number(3.0)),
+	///     &mut rand::thread_rng(),
+	/// );
+	/// assert_eq!(kept.value(), 13.0);
+	/// ```
+	pub fn eval<R: RangeRng>(self, dice: Operand, argument: Option<Operand>, rng: &mut R)
+		-> Operand
+	{
+		match self
+		{
+			Self::KeepHighest => function::keep_highest(dice, argument, rng),
+			Self::KeepLowest => function::keep_lowest(dice, argument, rng),
+			Self::DropHighest => function::drop_highest(dice, argument, rng),
+			Self::DropLowest => function::drop_lowest(dice, argument, rng),
+			Self::Advantage => function::advantage(dice, argument, rng),
+			Self::Disadvantage => function::disadvantage(dice, argument, rng),
+			Self::Explode(comp_op) => function::explode(dice, comp_op, argument, false, rng),
+			Self::ExplodeOnce(comp_op) => function::explode(dice, comp_op, argument, true, rng),
+			Self::Compound(comp_op) => function::compound(dice, comp_op, argument, rng),
+			Self::Reroll(comp_op) => function::reroll(dice, comp_op, argument, false, rng),
+			Self::RerollOnce(comp_op) => function::reroll(dice, comp_op, argument, true, rng),
+			Self::CriticalSuccess(comp_op) =>
+			{
+				function::critical_range(dice, comp_op, argument, true)
+			}
+			Self::CriticalFailure(comp_op) =>
+			{
+				function::critical_range(dice, comp_op, argument, false)
+			}
+			Self::Concatenate => function::concatenate(dice),
+			Self::SortAscending => function::sort_rolls(dice, false),
+			Self::SortDescending => function::sort_rolls(dice, true),
+		}
+	}
+}
+impl FromStr for DiceModifier
+{
+	type Err = ParseOperatorError;
+
+	fn from_str(str: &str) -> Result<Self, Self::Err>
+	{
+		match str
+		{
+			"kh" | "k" => Ok(Self::KeepHighest),
+			"kl" => Ok(Self::KeepLowest),
+			"dh" => Ok(Self::DropHighest),
+			"dl" => Ok(Self::DropLowest),
+			"adv" => Ok(Self::Advantage),
+			"dis" => Ok(Self::Disadvantage),
+			"cat" => Ok(Self::Concatenate),
+			"sa" => Ok(Self::SortAscending),
+			"sd" => Ok(Self::SortDescending),
+			unrecognized => Err(ParseOperatorError::from(unrecognized)),
+		}
+	}
+}
+impl Display for DiceModifier
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		match self
+		{
+			Self::KeepHighest => write!(f, "kh"),
+			Self::KeepLowest => write!(f, "kl"),
+			Self::DropHighest => write!(f, "dh"),
+			Self::DropLowest => write!(f, "dl"),
+			Self::Advantage => write!(f, "adv"),
+			Self::Disadvantage => write!(f, "dis"),
+			Self::Explode(comp_op) => write!(f, "!{comp_op}"),
+			Self::ExplodeOnce(comp_op) => write!(f, "xo{comp_op}"),
+			Self::Compound(comp_op) => write!(f, "!!{comp_op}"),
+			Self::Reroll(comp_op) => write!(f, "r{comp_op}"),
+			Self::RerollOnce(comp_op) => write!(f, "ro{comp_op}"),
+			Self::CriticalSuccess(comp_op) => write!(f, "cs{comp_op}"),
+			Self::CriticalFailure(comp_op) => write!(f, "cf{comp_op}"),
+			Self::Concatenate => write!(f, "cat"),
+			Self::SortAscending => write!(f, "sa"),
+			Self::SortDescending => write!(f, "sd"),
+		}
+	}
+}
@@ -1,6 +1,6 @@
 use super::{function, OpToken};
 use crate::{error::InvalidOperatorError, evaluation::Operand, RangeRng};
-use std::fmt::Display;
+use core::fmt::Display;
 
 /// Represents an operator which takes only one [`Operand`] as an argument
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -19,6 +19,7 @@ impl UnaryOperator
 			UnaryOpToken::Plus => function::unary_plus,
 			UnaryOpToken::Minus => function::unary_minus,
 			UnaryOpToken::Dice => function::unary_dice,
+			UnaryOpToken::Not => function::logical_not,
 		}
 	}
 
@@ -53,8 +54,9 @@ impl From<UnaryOpToken> for UnaryOperator
 		use UnaryOpToken as Op;
 		let (binding_power, direction) = match value
 		{
-			Op::Plus | Op::Minus => (7, UnaryDirection::Prefix),
-			Op::Dice => (13, UnaryDirection::Prefix),
+			Op::Not => (5, UnaryDirection::Prefix),
+			Op::Plus | Op::Minus => (15, UnaryDirection::Prefix),
+			Op::Dice => (21, UnaryDirection::Prefix),
 		};
 		Self {
 			token: value,
@@ -71,6 +73,8 @@ pub enum UnaryOpToken
 	Plus,
 	Minus,
 	Dice,
+	/// Logical negation (`not`), producing 1 if its argument is 0 and 0 otherwise
+	Not,
 }
 impl TryFrom<OpToken> for UnaryOpToken
 {
@@ -96,7 +100,7 @@ pub enum UnaryDirection
 }
 impl Display for UnaryDirection
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
 	{
 		write!(
 			f,
@@ -1,13 +1,20 @@
 use crate::{
 	error::{
-		ParsingError, TokenizationError, UnaryWrongDirectionError, UnexpectedTokenError,
-		UnmatchedCloseDelimiterError, UnmatchedComparisonError,
+		ArgumentCountError, DisabledOperatorError, InvalidRepetitionCountError, ParsingError,
+		ReservedLabelError, TokenizationError, NestingDepthError, UnaryWrongDirectionError, UnexpectedTokenError,
+		UnknownFunctionError, UnmatchedCloseDelimiterError, UnmatchedModifierError,
 	},
-	operator::{BinaryOperator, CompOperator, OpToken, UnaryDirection, UnaryOperator},
-	tokenization::{Token, TokenStream, TokenType},
+	operator::{
+		BinaryOperator, BindingPower, CompOperator, DiceModifier, ListFunction, LogicOperator,
+		OpToken, UnaryDirection, UnaryOperator, UnaryOpToken,
+	},
+	math,
+	tokenization::{Span, Token, TokenStream, TokenType},
+	ComparisonMode, Dialect,
 };
+use alloc::{boxed::Box, vec, vec::Vec};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug)]
 pub enum Node
 {
 	Binary
@@ -15,27 +22,799 @@ pub enum Node
 		operator: BinaryOperator,
 		left: Box<Self>,
 		right: Box<Self>,
+		span: Span,
 	},
 	Unary
 	{
 		operator: UnaryOperator,
 		argument: Box<Self>,
+		span: Span,
 	},
 	ComparisonTernary
 	{
 		comp_op: CompOperator,
-		dice_left: Box<Self>,
-		dice_right: Box<Self>,
+		dice: Box<Self>,
 		compare_to: Box<Self>,
+		span: Span,
+	},
+	Modifier
+	{
+		modifier: DiceModifier,
+		dice: Box<Self>,
+		argument: Option<Box<Self>>,
+		span: Span,
+	},
+	/// Rerolls each roll of a dice term whose natural value satisfies any of the `conditions`,
+	/// rather than rerolling for each condition in turn (eg. Roll20's `r1r2`). Not produced by
+	/// the default syntax, only by front ends such as [`roll20`][crate::roll20]
+	Reroll
+	{
+		dice: Box<Self>,
+		conditions: Box<[(CompOperator, f64)]>,
+		once: bool,
+		span: Span,
+	},
+	/// Adds the maximum value of each die to the rolls of a dice term. Not produced by the parser,
+	/// only by [`CriticalMode::MaxPlusRoll`][crate::CriticalMode::MaxPlusRoll]
+	PlusMaximum
+	{
+		dice: Box<Self>,
+		span: Span,
+	},
+	/// A comparison between two values, producing 1 if it is satisfied and 0 otherwise
+	Comparison
+	{
+		comp_op: CompOperator,
+		left: Box<Self>,
+		right: Box<Self>,
+		span: Span,
+	},
+	/// `and`/`or`. The right-hand side is only evaluated if the left-hand side does not decide the
+	/// result on its own
+	Logic
+	{
+		operator: LogicOperator,
+		left: Box<Self>,
+		right: Box<Self>,
+		span: Span,
+	},
+	/// `if(condition, if_true, if_false)`. Only the branch which is taken is evaluated
+	Conditional
+	{
+		condition: Box<Self>,
+		if_true: Box<Self>,
+		if_false: Box<Self>,
+		span: Span,
+	},
+	/// A call to a [`ListFunction`] (eg. `highest(5d10, 2)`)
+	Function
+	{
+		function: ListFunction,
+		arguments: Box<[Self]>,
+		span: Span,
+	},
+	/// Indexing into a list of values (eg. `(4d6)[1]`). Indices start at 0, and negative indices
+	/// count back from the end of the list
+	Index
+	{
+		list: Box<Self>,
+		index: Box<Self>,
+		span: Span,
+	},
+	/// A node with a tag attached, which is given to each [`RollGroup`] rolled within it that does
+	/// not already have one (eg. the `fire` in Foundry's `2d6[fire]`). Not produced by the
+	/// default syntax, only by front ends such as [`foundry`][crate::foundry]
+	///
+	/// [`RollGroup`]: crate::evaluation::RollGroup
+	Tagged
+	{
+		node: Box<Self>,
+		tag: Box<str>,
+		span: Span,
+	},
+	/// A reference to the value of an earlier labelled statement
+	Variable
+	{
+		name: Box<str>,
+		span: Span,
+	},
+	/// A roll on the table with the given name (eg. `table(loot)`), whose value is the value of
+	/// the roll which selected the row (see [`table`][crate::table])
+	Table
+	{
+		name: Box<str>,
+		span: Span,
 	},
 	Leaf(f64),
 }
+impl Node
+{
+	/// Returns the [`Span`] of the input string this node was parsed from. [`Leaf`][Node::Leaf]
+	/// nodes do not keep track of their spans, and so always return [`None`]
+	pub fn span(&self) -> Option<Span>
+	{
+		match self
+		{
+			Self::Binary { span, .. }
+			| Self::Unary { span, .. }
+			| Self::ComparisonTernary { span, .. }
+			| Self::Modifier { span, .. }
+			| Self::Reroll { span, .. }
+			| Self::PlusMaximum { span, .. }
+			| Self::Comparison { span, .. }
+			| Self::Logic { span, .. }
+			| Self::Conditional { span, .. }
+			| Self::Function { span, .. }
+			| Self::Index { span, .. }
+			| Self::Tagged { span, .. }
+			| Self::Variable { span, .. }
+			| Self::Table { span, .. } => Some(*span),
+			Self::Leaf(_) => None,
+		}
+	}
+
+	/// Returns the lowest and highest values this node can evaluate to, if they can be found
+	/// without evaluating it. Only numbers, dice terms without modifiers, and their sums,
+	/// differences, and products have known bounds
+	pub(crate) fn bounds(&self) -> Option<(f64, f64)>
+	{
+		// chains of binary operators are followed in a loop, as they are when cloned
+		let mut chain = Vec::new();
+		let mut first = self;
+		while let Self::Binary {
+			operator,
+			left,
+			right,
+			..
+		} = first
+		{
+			chain.push((operator.token, right));
+			first = left;
+		}
+
+		let first = match first
+		{
+			Self::Leaf(value) => (*value, *value),
+			Self::Unary {
+				operator, argument, ..
+			} =>
+			{
+				let (low, high) = argument.bounds()?;
+				match operator.token
+				{
+					UnaryOpToken::Plus => (low, high),
+					UnaryOpToken::Minus => (-high, -low),
+					UnaryOpToken::Dice if low >= 1.0 => (1.0, high),
+					_ => return None,
+				}
+			}
+			Self::Tagged { node, .. } => node.bounds()?,
+			_ => return None,
+		};
+		chain
+			.into_iter()
+			.rev()
+			.try_fold(first, |(low, high), (token, right)| {
+				let (right_low, right_high) = right.bounds()?;
+				match token
+				{
+					OpToken::Plus => Some((low + right_low, high + right_high)),
+					OpToken::Minus => Some((low - right_high, high - right_low)),
+					OpToken::Multiply =>
+					{
+						let products = [
+							low * right_low,
+							low * right_high,
+							high * right_low,
+							high * right_high,
+						];
+						Some((
+							products.into_iter().fold(f64::INFINITY, f64::min),
+							products.into_iter().fold(f64::NEG_INFINITY, f64::max),
+						))
+					}
+					// each die rolls at least 1 and at most its number of faces
+					OpToken::Dice if low >= 0.0 && right_low >= 1.0 =>
+					{
+						Some((low, high * right_high))
+					}
+					_ => None,
+				}
+			})
+	}
+}
+// the parser does not limit how long a chain of operations can be (eg. `1 + 1 + ... + 1`), so the
+// operations down the left-hand side of one are dropped, cloned, and compared in a loop rather than
+// recursively
+impl Node
+{
+	/// Returns the left-hand side of an operation with one, which may be the next link of an
+	/// arbitrarily long chain of operations
+	fn chain_left(&self) -> Option<&Self>
+	{
+		match self
+		{
+			Self::Binary { left, .. } | Self::Comparison { left, .. } | Self::Logic { left, .. } =>
+			{
+				Some(left)
+			}
+			_ => None,
+		}
+	}
+
+	fn chain_left_mut(&mut self) -> Option<&mut Self>
+	{
+		match self
+		{
+			Self::Binary { left, .. } | Self::Comparison { left, .. } | Self::Logic { left, .. } =>
+			{
+				Some(left)
+			}
+			_ => None,
+		}
+	}
+
+	/// Takes the node out of its place in a tree, leaving a [`Leaf`][Node::Leaf] behind
+	pub(crate) fn take(&mut self) -> Self
+	{
+		core::mem::replace(self, Self::Leaf(0.0))
+	}
+
+	/// Clones a link of a chain with `left` as its left-hand side
+	fn clone_link(&self, left: Self) -> Self
+	{
+		let left = Box::new(left);
+		match self
+		{
+			Self::Binary {
+				operator,
+				right,
+				span,
+				..
+			} => Self::Binary {
+				operator: *operator,
+				left,
+				right: right.clone(),
+				span: *span,
+			},
+			Self::Comparison {
+				comp_op,
+				right,
+				span,
+				..
+			} => Self::Comparison {
+				comp_op: *comp_op,
+				left,
+				right: right.clone(),
+				span: *span,
+			},
+			Self::Logic {
+				operator,
+				right,
+				span,
+				..
+			} => Self::Logic {
+				operator: *operator,
+				left,
+				right: right.clone(),
+				span: *span,
+			},
+			_ => unreachable!("only operations with a left-hand side should be links of a chain"),
+		}
+	}
+
+	/// Returns whether two links of chains are equal, other than their left-hand sides
+	fn link_eq(&self, other: &Self) -> bool
+	{
+		match (self, other)
+		{
+			(
+				Self::Binary {
+					operator: l_op,
+					right: l_right,
+					..
+				},
+				Self::Binary {
+					operator: r_op,
+					right: r_right,
+					..
+				},
+			) => l_op == r_op && l_right == r_right,
+			(
+				Self::Comparison {
+					comp_op: l_op,
+					right: l_right,
+					..
+				},
+				Self::Comparison {
+					comp_op: r_op,
+					right: r_right,
+					..
+				},
+			) => l_op == r_op && l_right == r_right,
+			(
+				Self::Logic {
+					operator: l_op,
+					right: l_right,
+					..
+				},
+				Self::Logic {
+					operator: r_op,
+					right: r_right,
+					..
+				},
+			) => l_op == r_op && l_right == r_right,
+			_ => false,
+		}
+	}
+}
+impl Drop for Node
+{
+	fn drop(&mut self)
+	{
+		// each link is detached from the rest of the chain before it is dropped
+		let mut next = self.chain_left_mut().map(Self::take);
+		while let Some(mut link) = next
+		{
+			next = link.chain_left_mut().map(Self::take);
+		}
+	}
+}
+impl Clone for Node
+{
+	fn clone(&self) -> Self
+	{
+		let mut chain = Vec::new();
+		let mut first = self;
+		while let Some(left) = first.chain_left()
+		{
+			chain.push(first);
+			first = left;
+		}
+
+		let first = match first
+		{
+			Self::Binary { .. } | Self::Comparison { .. } | Self::Logic { .. } =>
+			{
+				unreachable!("the whole chain should have been walked")
+			}
+			Self::Unary {
+				operator,
+				argument,
+				span,
+			} => Self::Unary {
+				operator: *operator,
+				argument: argument.clone(),
+				span: *span,
+			},
+			Self::ComparisonTernary {
+				comp_op,
+				dice,
+				compare_to,
+				span,
+			} => Self::ComparisonTernary {
+				comp_op: *comp_op,
+				dice: dice.clone(),
+				compare_to: compare_to.clone(),
+				span: *span,
+			},
+			Self::Modifier {
+				modifier,
+				dice,
+				argument,
+				span,
+			} => Self::Modifier {
+				modifier: *modifier,
+				dice: dice.clone(),
+				argument: argument.clone(),
+				span: *span,
+			},
+			Self::Reroll {
+				dice,
+				conditions,
+				once,
+				span,
+			} => Self::Reroll {
+				dice: dice.clone(),
+				conditions: conditions.clone(),
+				once: *once,
+				span: *span,
+			},
+			Self::PlusMaximum { dice, span } => Self::PlusMaximum {
+				dice: dice.clone(),
+				span: *span,
+			},
+			Self::Conditional {
+				condition,
+				if_true,
+				if_false,
+				span,
+			} => Self::Conditional {
+				condition: condition.clone(),
+				if_true: if_true.clone(),
+				if_false: if_false.clone(),
+				span: *span,
+			},
+			Self::Function {
+				function,
+				arguments,
+				span,
+			} => Self::Function {
+				function: *function,
+				arguments: arguments.clone(),
+				span: *span,
+			},
+			Self::Index { list, index, span } => Self::Index {
+				list: list.clone(),
+				index: index.clone(),
+				span: *span,
+			},
+			Self::Tagged { node, tag, span } => Self::Tagged {
+				node: node.clone(),
+				tag: tag.clone(),
+				span: *span,
+			},
+			Self::Variable { name, span } => Self::Variable {
+				name: name.clone(),
+				span: *span,
+			},
+			Self::Table { name, span } => Self::Table {
+				name: name.clone(),
+				span: *span,
+			},
+			Self::Leaf(n) => Self::Leaf(*n),
+		};
+
+		chain
+			.into_iter()
+			.rev()
+			.fold(first, |left, link| link.clone_link(left))
+	}
+}
+// spans are only metadata about where in the input a node came from, so two trees with the same
+// structure are considered equal regardless of where they were parsed from
+impl PartialEq for Node
+{
+	fn eq(&self, other: &Self) -> bool
+	{
+		let (mut l_node, mut r_node) = (self, other);
+		while let (Some(l_left), Some(r_left)) = (l_node.chain_left(), r_node.chain_left())
+		{
+			if !l_node.link_eq(r_node)
+			{
+				return false;
+			}
+			(l_node, r_node) = (l_left, r_left);
+		}
+
+		match (l_node, r_node)
+		{
+			(
+				Self::Unary {
+					operator: l_op,
+					argument: l_arg,
+					..
+				},
+				Self::Unary {
+					operator: r_op,
+					argument: r_arg,
+					..
+				},
+			) => l_op == r_op && l_arg == r_arg,
+			(
+				Self::ComparisonTernary {
+					comp_op: l_op,
+					dice: l_dice,
+					compare_to: l_compare,
+					..
+				},
+				Self::ComparisonTernary {
+					comp_op: r_op,
+					dice: r_dice,
+					compare_to: r_compare,
+					..
+				},
+			) => l_op == r_op && l_dice == r_dice && l_compare == r_compare,
+			(
+				Self::Modifier {
+					modifier: l_mod,
+					dice: l_dice,
+					argument: l_arg,
+					..
+				},
+				Self::Modifier {
+					modifier: r_mod,
+					dice: r_dice,
+					argument: r_arg,
+					..
+				},
+			) => l_mod == r_mod && l_dice == r_dice && l_arg == r_arg,
+			(
+				Self::Reroll {
+					dice: l_dice,
+					conditions: l_conditions,
+					once: l_once,
+					..
+				},
+				Self::Reroll {
+					dice: r_dice,
+					conditions: r_conditions,
+					once: r_once,
+					..
+				},
+			) => l_once == r_once && l_conditions == r_conditions && l_dice == r_dice,
+			(Self::PlusMaximum { dice: l_dice, .. }, Self::PlusMaximum { dice: r_dice, .. }) =>
+			{
+				l_dice == r_dice
+			}
+			(
+				Self::Conditional {
+					condition: l_cond,
+					if_true: l_true,
+					if_false: l_false,
+					..
+				},
+				Self::Conditional {
+					condition: r_cond,
+					if_true: r_true,
+					if_false: r_false,
+					..
+				},
+			) => l_cond == r_cond && l_true == r_true && l_false == r_false,
+			(
+				Self::Function {
+					function: l_fn,
+					arguments: l_args,
+					..
+				},
+				Self::Function {
+					function: r_fn,
+					arguments: r_args,
+					..
+				},
+			) => l_fn == r_fn && l_args == r_args,
+			(
+				Self::Index {
+					list: l_list,
+					index: l_index,
+					..
+				},
+				Self::Index {
+					list: r_list,
+					index: r_index,
+					..
+				},
+			) => l_list == r_list && l_index == r_index,
+			(
+				Self::Tagged {
+					node: l_node,
+					tag: l_tag,
+					..
+				},
+				Self::Tagged {
+					node: r_node,
+					tag: r_tag,
+					..
+				},
+			) => l_node == r_node && l_tag == r_tag,
+			(Self::Variable { name: l_name, .. }, Self::Variable { name: r_name, .. })
+			| (Self::Table { name: l_name, .. }, Self::Table { name: r_name, .. }) =>
+			{
+				l_name == r_name
+			}
+			(Self::Leaf(l), Self::Leaf(r)) => l == r,
+			_ => false,
+		}
+	}
+}
+
+/// Returns the number of dice and their faces if `faces` is a digit repeated (eg. 2 dice with 6
+/// faces for `66`), for dialects with digit dice
+fn digit_dice(faces: f64) -> Option<(f64, f64)>
+{
+	let digit = faces % 10.0;
+	let mut count = 0.0;
+	let mut rest = faces;
+	while rest >= 1.0 && rest % 10.0 == digit
+	{
+		count += 1.0;
+		rest = math::trunc(rest / 10.0);
+	}
+	(rest == 0.0 && count >= 2.0 && digit >= 2.0).then_some((count, digit))
+}
+
+/// Parses the repeated digit of a digit dice term (eg. the `66` of `D66`) as concatenated dice,
+/// along with the order its digits are read in, if written after it: `A` or `S` for lowest
+/// first, `D` for highest first, or `N` for the order they were rolled in
+fn parse_digit_dice(
+	stream: &mut TokenStream,
+	digits: (f64, f64),
+	start: usize,
+) -> Result<(Node, usize), ParsingError>
+{
+	let (count, faces) = digits;
+	stream.consume()?;
+	let mut dice = Node::Binary {
+		operator: OpToken::Dice.into(),
+		left: Box::new(Node::Leaf(count)),
+		right: Box::new(Node::Leaf(faces)),
+		span: Span::new(start, stream.last_span().end),
+	};
+	let mut depth = 2;
+
+	let (has_order, order) = match stream.peek()
+	{
+		Some(Ok(Token::Identifier(word)))
+			if word.eq_ignore_ascii_case("a") || word.eq_ignore_ascii_case("s") =>
+		{
+			(true, Some(DiceModifier::SortAscending))
+		}
+		Some(Ok(Token::Identifier(word))) if word.eq_ignore_ascii_case("n") => (true, None),
+		// a `D` directly after the digits cannot start another dice term
+		Some(Ok(Token::Operator(OpToken::Dice))) => (true, Some(DiceModifier::SortDescending)),
+		_ => (false, None),
+	};
+	if has_order
+	{
+		stream.consume()?;
+	}
+	if let Some(modifier) = order
+	{
+		depth += 1;
+		dice = Node::Modifier {
+			modifier,
+			dice: Box::new(dice),
+			argument: None,
+			span: Span::new(start, stream.last_span().end),
+		};
+	}
+
+	let concatenated = Node::Modifier {
+		modifier: DiceModifier::Concatenate,
+		dice: Box::new(dice),
+		argument: None,
+		span: Span::new(start, stream.last_span().end),
+	};
+	Ok((concatenated, nest(depth)?))
+}
 
 pub fn parse_tree_from(stream: &mut TokenStream) -> Result<Node, ParsingError>
 {
-	parse_min_power(stream, 0, ParseContext::default())
+	parse_min_power(stream, 0, ParseContext::default()).map(|(tree, _)| tree)
+}
+
+/// An expression tree to be evaluated `count` times, independently of one another
+#[derive(Debug, PartialEq)]
+pub struct RepeatedTree
+{
+	pub count: u32,
+	pub tree: Node,
+	pub span: Span,
+}
+
+/// A statement of a sequence of statements separated by `;`, optionally labelled (eg. the
+/// `attack: 1d20+7` in `attack: 1d20+7; damage: 2d6+4`)
+#[derive(Debug, PartialEq)]
+pub struct StatementTree
+{
+	pub label: Option<Box<str>>,
+	pub repeated: RepeatedTree,
+}
+
+/// Parses an expression which may be prefixed by a repetition count and `x` or `#` (eg.
+/// `6x 4d6kh3` or `3#1d20+5`). Expressions without a repetition count are repeated once
+pub fn parse_repeated_from(stream: &mut TokenStream) -> Result<RepeatedTree, ParsingError>
+{
+	parse_repeated(stream, ParseContext::default())
+}
+
+/// Parses any number of statements separated by `;`, each of which may be prefixed by a label
+/// and a colon (eg. `attack: 1d20+7; damage: 2d6+4`). Empty statements are skipped, and labels
+/// which would not be read as a variable (eg. `d:` or `if:`) produce a [`ReservedLabelError`]
+pub fn parse_statements_from(stream: &mut TokenStream)
+	-> Result<Vec<StatementTree>, ParsingError>
+{
+	let context = ParseContext {
+		expecting_statement_end: true,
+		..Default::default()
+	};
+	let mut statements = Vec::new();
+	loop
+	{
+		match stream.peek()
+		{
+			None => break,
+			Some(Ok(Token::StatementEnd)) =>
+			{
+				stream.consume()?;
+				continue;
+			}
+			_ => (),
+		}
+
+		let label = match stream.peek()
+		{
+			Some(Ok(Token::Label(_))) => match stream.next()
+			{
+				Some(Ok(Token::Label(label))) => Some(check_label(label, stream.dialect())?),
+				_ => unreachable!("peeked token should be a label"),
+			},
+			_ => None,
+		};
+		let repeated = parse_repeated(stream, context)?;
+		statements.push(StatementTree { label, repeated });
+
+		if stream.peek().is_some()
+		{
+			stream.consume_expecting(TokenType::StatementEnd)?;
+		}
+	}
+
+	Ok(statements)
+}
+
+/// Returns `label` if a later statement could refer to it as a variable. Words which are read as
+/// any other token (eg. `d` or `kh`), or which start a conditional or a table roll when called
+/// (`if` and `table`), cannot be labels
+fn check_label(label: Box<str>, dialect: Dialect) -> Result<Box<str>, ReservedLabelError>
+{
+	match Token::from_word(&label, &dialect)
+	{
+		Token::Identifier(_) if !matches!(label.as_ref(), "if" | "table") => Ok(label),
+		_ => Err(ReservedLabelError(label)),
+	}
+}
+
+#[allow(clippy::cast_sign_loss)] // count is checked to be a non-negative whole number in range
+fn parse_repeated(
+	stream: &mut TokenStream,
+	context: ParseContext,
+) -> Result<RepeatedTree, ParsingError>
+{
+	let start = stream.next_start();
+	let (tree, _) = parse_min_power(
+		stream,
+		0,
+		ParseContext {
+			expecting_repetition: true,
+			..context
+		},
+	)?;
+
+	if let Some(Ok(Token::Repetition)) = stream.peek()
+	{
+		let count = match tree
+		{
+			Node::Leaf(n)
+				if math::trunc(n) == n
+					&& (0.0..=f64::from(InvalidRepetitionCountError::MAX_COUNT)).contains(&n) =>
+			{
+				n as u32
+			}
+			_ => return Err(InvalidRepetitionCountError.into()),
+		};
+		stream.consume()?;
+
+		let start = stream.next_start();
+		let (tree, _) = parse_min_power(stream, 0, context)?;
+		Ok(RepeatedTree {
+			count,
+			tree,
+			span: Span::new(start, stream.last_span().end),
+		})
+	}
+	else
+	{
+		Ok(RepeatedTree {
+			count: 1,
+			tree,
+			span: Span::new(start, stream.last_span().end),
+		})
+	}
 }
 
+/// Parses an expression whose operators bind at least as tightly as `min_power`, returning its
+/// tree along with the depth of that tree
 // parsing function kinda has to be big. separating this out much further would not be great. at
 // least for now -morgan 2024-01-22
 #[allow(clippy::too_many_lines)]
@@ -43,14 +822,58 @@ fn parse_min_power(
 	stream: &mut TokenStream,
 	min_power: u8,
 	context: ParseContext,
-) -> Result<Node, ParsingError>
+) -> Result<(Node, usize), ParsingError>
 {
-	let mut lhs = match stream
-		.expect(TokenType::Number | TokenType::Operator | TokenType::OpenDelimiter)?
+	let context = context.nested()?;
+	let dialect = stream.dialect();
+	let start = stream.next_start();
+	let expected = TokenType::Number
+		| TokenType::Operator
+		| TokenType::OpenDelimiter
+		| TokenType::Identifier
+		| TokenType::LogicOperator;
+	let (mut lhs, mut depth) = match stream.expect(expected)?
 	{
-		Token::Number(n) => Node::Leaf(n),
+		Token::Number(n) => (Node::Leaf(n), 1),
+		Token::Identifier(name) =>
+		{
+			if name.as_ref() == "table" && matches!(stream.peek(), Some(Ok(Token::OpenDelimiter)))
+			{
+				(parse_table_roll(stream, start)?, 1)
+			}
+			else if let Some(Ok(Token::OpenDelimiter)) = stream.peek()
+			{
+				let (arguments, arguments_depth) = parse_arguments(stream, context)?;
+				let call = parse_call(name, arguments, Span::new(start, stream.last_span().end))?;
+				(call, nest(arguments_depth)?)
+			}
+			else
+			{
+				let variable = Node::Variable {
+					name,
+					span: stream.last_span(),
+				};
+				(variable, 1)
+			}
+		}
+		Token::Not =>
+		{
+			let operator = UnaryOperator::from(UnaryOpToken::Not);
+			let binding_power = dialect.unary_binding_power(operator.token);
+			let (argument, argument_depth) = parse_min_power(stream, binding_power, context)?;
+			let unary = Node::Unary {
+				operator,
+				argument: Box::new(argument),
+				span: Span::new(start, stream.last_span().end),
+			};
+			(unary, nest(argument_depth)?)
+		}
 		Token::Operator(op_token) =>
 		{
+			if !dialect.allows(op_token)
+			{
+				return Err(DisabledOperatorError(op_token).into());
+			}
 			let operator = UnaryOperator::try_from(op_token)?;
 			if operator.direction != UnaryDirection::Prefix
 			{
@@ -60,9 +883,40 @@ fn parse_min_power(
 				}
 				.into());
 			}
-			Node::Unary {
-				operator,
-				argument: Box::new(parse_min_power(stream, operator.binding_power, context)?),
+			let binding_power = dialect.unary_binding_power(operator.token);
+			if operator.token == UnaryOpToken::Dice
+			{
+				let digits = match stream.peek()
+				{
+					Some(Ok(Token::Number(faces))) if dialect.digit_dice() => digit_dice(*faces),
+					_ => None,
+				};
+				let dice = match digits
+				{
+					Some(digits) => parse_digit_dice(stream, digits, start)?,
+					None =>
+					{
+						let (argument, argument_depth) =
+							parse_min_power(stream, binding_power, context.expect_dice_suffix())?;
+						let unary = Node::Unary {
+							operator,
+							argument: Box::new(argument),
+							span: Span::new(start, stream.last_span().end),
+						};
+						(unary, nest(argument_depth)?)
+					}
+				};
+				parse_dice_suffix(stream, dice, start, context)?
+			}
+			else
+			{
+				let (argument, argument_depth) = parse_min_power(stream, binding_power, context)?;
+				let unary = Node::Unary {
+					operator,
+					argument: Box::new(argument),
+					span: Span::new(start, stream.last_span().end),
+				};
+				(unary, nest(argument_depth)?)
 			}
 		}
 		Token::OpenDelimiter =>
@@ -71,23 +925,38 @@ fn parse_min_power(
 			stream.consume_expecting(TokenType::CloseDelimiter)?;
 			value
 		}
-		_ => unreachable!("stream.expect should prevent this branch from ever occuring"),
+		// `not` shares its token type with `and`/`or`, which cannot start an expression
+		token @ Token::LogicOperator(_) =>
+		{
+			return Err(TokenizationError::from(UnexpectedTokenError {
+				found: Some(token.token_type()),
+				expected,
+			})
+			.into());
+		}
+		token => unreachable!(
+			"stream.expect should prevent this branch from ever occuring (found {token:?})"
+		),
 	};
 
 	while let Some(peeked) = stream.peek()
 	{
 		let op = match peeked
 		{
-			Ok(Token::Operator(op)) => Ok(BinaryOperator::from(*op)),
-			Ok(Token::OpenDelimiter) =>
+			Ok(Token::Operator(op)) if !dialect.allows(*op) =>
 			{
-				let rhs = parse_min_power(stream, 0, context.expect_close_paren())?;
-				lhs = Node::Binary {
-					operator: OpToken::Multiply.into(),
-					left: Box::new(lhs),
-					right: Box::new(rhs),
-				};
-				break;
+				Err(ParsingError::from(DisabledOperatorError(*op)))
+			}
+			Ok(Token::Operator(OpToken::Divide)) if dialect.floor_division() =>
+			{
+				Ok(InfixOperator::Binary(BinaryOperator::from(OpToken::FloorDivide)))
+			}
+			Ok(Token::Operator(op)) => Ok(InfixOperator::Binary(BinaryOperator::from(*op))),
+			// an opening parenthesis directly after an expression multiplies them (eg. `2(1d6)`)
+			// in dialects with implicit multiplication, and is unexpected otherwise
+			Ok(Token::OpenDelimiter) if dialect.implicit_multiplication() =>
+			{
+				Ok(InfixOperator::ImplicitMultiply)
 			}
 			Ok(Token::CloseDelimiter) =>
 			{
@@ -98,15 +967,34 @@ fn parse_min_power(
 
 				Err(ParsingError::from(UnmatchedCloseDelimiterError))
 			}
+			// directly after a dice term, a comparison is a filter on its rolls, which is parsed
+			// by `parse_dice_suffix`, unless the dialect only has boolean comparisons. anywhere
+			// else, it compares two values
 			Ok(Token::ComparisonOperator(op)) =>
 			{
-				if context.expecting_comparison
+				if context.expecting_comparison && dialect.comparisons() == ComparisonMode::Filter
+				{
+					break;
+				}
+
+				Ok(InfixOperator::Comparison(*op))
+			}
+			Ok(Token::LogicOperator(op)) => Ok(InfixOperator::Logic(*op)),
+			Ok(Token::OpenBracket) => Ok(InfixOperator::Index),
+			Ok(Token::Tag(tag)) => Ok(InfixOperator::Tag(tag.clone())),
+			Ok(Token::Modifier(modifier)) =>
+			{
+				if context.expecting_modifier
 				{
 					break;
 				}
 
-				Err(ParsingError::from(UnmatchedComparisonError(*op)))
+				Err(ParsingError::from(UnmatchedModifierError(*modifier)))
 			}
+			Ok(Token::Repetition) if context.expecting_repetition => break,
+			Ok(Token::StatementEnd) if context.expecting_statement_end => break,
+			Ok(Token::Separator) if context.expecting_separator => break,
+			Ok(Token::CloseBracket) if context.expecting_close_bracket => break,
 			result =>
 			{
 				let token = result.clone()?;
@@ -118,73 +1006,375 @@ fn parse_min_power(
 			}
 		}?;
 
-		let binding_power = op.binding_power;
+		let binding_power = op.binding_power(&dialect);
 		if binding_power.left < min_power
 		{
 			break;
 		}
 
-		stream.consume()?; // consume current operator
-		lhs = if op.token == OpToken::Dice
+		// the parenthesis of an implicit multiplication starts its right-hand side, so it is left
+		// to be parsed along with it
+		if !matches!(op, InfixOperator::ImplicitMultiply)
+		{
+			stream.consume()?; // consume current operator
+		}
+		// a chain of operators (eg. `1 + 1 + 1`) is parsed by this loop rather than recursively,
+		// and evaluated in a loop as well, so each operator in it is not nested any deeper than
+		// its operands
+		(lhs, depth) = match op
 		{
-			let rhs = parse_min_power(stream, binding_power.right, context.expect_comparison())?;
-			if let Some(Ok(Token::ComparisonOperator(comp_op))) = stream.peek()
+			// element-wise operators produce a modified dice term, which can itself be followed by
+			// modifiers and filters (eg. `4d6 .+ 1 kh3`)
+			InfixOperator::Binary(op) if op.token == OpToken::Dice || op.token.is_element_wise() =>
 			{
-				// deref now so mut borrow is possible next line -morgan 2024-01-14
-				let comp_op = *comp_op;
-				stream.consume_expecting(TokenType::ComparisonOperator)?;
-				let compare_to = parse_min_power(stream, 0, context)?;
-				Node::ComparisonTernary {
-					comp_op,
-					dice_left: Box::new(lhs),
-					dice_right: Box::new(rhs),
-					compare_to: Box::new(compare_to),
-				}
+				let (rhs, rhs_depth) =
+					parse_min_power(stream, binding_power.right, context.expect_dice_suffix())?;
+				let dice = Node::Binary {
+					operator: op,
+					left: Box::new(lhs),
+					right: Box::new(rhs),
+					span: Span::new(start, stream.last_span().end),
+				};
+				parse_dice_suffix(stream, (dice, nest(depth.max(rhs_depth))?), start, context)?
 			}
-			else
+			InfixOperator::Binary(op) =>
 			{
-				// please stabalize if let chaining i beg you ;-; -morgan 2024-01-14
-				Node::Binary {
+				let (rhs, rhs_depth) = parse_min_power(stream, binding_power.right, context)?;
+				let binary = Node::Binary {
 					operator: op,
 					left: Box::new(lhs),
 					right: Box::new(rhs),
-				}
+					span: Span::new(start, stream.last_span().end),
+				};
+				(binary, depth.max(rhs_depth))
+			}
+			InfixOperator::ImplicitMultiply =>
+			{
+				let (rhs, rhs_depth) = parse_min_power(stream, binding_power.right, context)?;
+				let product = Node::Binary {
+					operator: OpToken::Multiply.into(),
+					left: Box::new(lhs),
+					right: Box::new(rhs),
+					span: Span::new(start, stream.last_span().end),
+				};
+				(product, depth.max(rhs_depth))
+			}
+			InfixOperator::Comparison(comp_op) =>
+			{
+				let (rhs, rhs_depth) = parse_min_power(stream, binding_power.right, context)?;
+				let comparison = Node::Comparison {
+					comp_op,
+					left: Box::new(lhs),
+					right: Box::new(rhs),
+					span: Span::new(start, stream.last_span().end),
+				};
+				(comparison, depth.max(rhs_depth))
+			}
+			InfixOperator::Logic(operator) =>
+			{
+				let (rhs, rhs_depth) = parse_min_power(stream, binding_power.right, context)?;
+				let logic = Node::Logic {
+					operator,
+					left: Box::new(lhs),
+					right: Box::new(rhs),
+					span: Span::new(start, stream.last_span().end),
+				};
+				(logic, depth.max(rhs_depth))
+			}
+			InfixOperator::Index =>
+			{
+				let (index, index_depth) =
+					parse_min_power(stream, 0, context.expect_close_bracket())?;
+				stream.consume_expecting(TokenType::CloseBracket)?;
+				let index = Node::Index {
+					list: Box::new(lhs),
+					index: Box::new(index),
+					span: Span::new(start, stream.last_span().end),
+				};
+				(index, nest(depth.max(index_depth))?)
+			}
+			InfixOperator::Tag(tag) =>
+			{
+				let tagged = Node::Tagged {
+					node: Box::new(lhs),
+					tag,
+					span: Span::new(start, stream.last_span().end),
+				};
+				(tagged, nest(depth)?)
 			}
 		}
-		else
+	}
+
+	Ok((lhs, depth))
+}
+
+/// Returns the depth of a node whose deepest child has a depth of `child_depth`
+/// # Errors
+/// Returns a [`NestingDepthError`] if the node would be deeper than
+/// [`NestingDepthError::MAX_DEPTH`]
+pub(crate) fn nest(child_depth: usize) -> Result<usize, ParsingError>
+{
+	let depth = child_depth + 1;
+	if depth > NestingDepthError::MAX_DEPTH
+	{
+		Err(NestingDepthError.into())
+	}
+	else
+	{
+		Ok(depth)
+	}
+}
+
+/// Parses the parenthesized, comma-separated arguments of a function call, returning them along
+/// with the depth of the deepest argument
+fn parse_arguments(
+	stream: &mut TokenStream,
+	context: ParseContext,
+) -> Result<(Vec<Node>, usize), ParsingError>
+{
+	stream.consume_expecting(TokenType::OpenDelimiter)?;
+	let context = ParseContext {
+		expecting_separator: true,
+		..context.expect_close_paren()
+	};
+
+	let (first, mut depth) = parse_min_power(stream, 0, context)?;
+	let mut arguments = vec![first];
+	while let Some(Ok(Token::Separator)) = stream.peek()
+	{
+		stream.consume()?;
+		let (argument, argument_depth) = parse_min_power(stream, 0, context)?;
+		arguments.push(argument);
+		depth = depth.max(argument_depth);
+	}
+	stream.consume_expecting(TokenType::CloseDelimiter)?;
+
+	Ok((arguments, depth))
+}
+
+/// Parses the parenthesized table name of a roll on a table, after the `table` starting at `start`
+fn parse_table_roll(stream: &mut TokenStream, start: usize) -> Result<Node, ParsingError>
+{
+	stream.consume_expecting(TokenType::OpenDelimiter)?;
+	let name = match stream.expect(TokenType::Identifier)?
+	{
+		Token::Identifier(name) => name,
+		_ => unreachable!("expected token should be an identifier"),
+	};
+	stream.consume_expecting(TokenType::CloseDelimiter)?;
+
+	Ok(Node::Table {
+		name,
+		span: Span::new(start, stream.last_span().end),
+	})
+}
+
+/// Creates the node for a call to the function `name` with the given arguments
+fn parse_call(name: Box<str>, arguments: Vec<Node>, span: Span) -> Result<Node, ParsingError>
+{
+	if name.as_ref() == "if"
+	{
+		let [condition, if_true, if_false] =
+			<[Node; 3]>::try_from(arguments).map_err(|arguments| ArgumentCountError {
+				function: name,
+				expected: 3..=3,
+				found: arguments.len(),
+			})?;
+		return Ok(Node::Conditional {
+			condition: Box::new(condition),
+			if_true: Box::new(if_true),
+			if_false: Box::new(if_false),
+			span,
+		});
+	}
+
+	let function = name
+		.parse::<ListFunction>()
+		.map_err(|_| UnknownFunctionError(name.clone()))?;
+	if !function.arity().contains(&arguments.len())
+	{
+		return Err(ArgumentCountError {
+			function: name,
+			expected: function.arity(),
+			found: arguments.len(),
+		}
+		.into());
+	}
+
+	Ok(Node::Function {
+		function,
+		arguments: arguments.into(),
+		span,
+	})
+}
+
+/// Parses any [`DiceModifier`]s and comparison filter directly following a dice term, and wraps
+/// `dice` in the nodes they produce. `dice` is given and returned along with the depth of its tree
+fn parse_dice_suffix(
+	stream: &mut TokenStream,
+	dice: (Node, usize),
+	start: usize,
+	context: ParseContext,
+) -> Result<(Node, usize), ParsingError>
+{
+	let (mut dice, mut depth) = dice;
+	while let Some(Ok(Token::Modifier(modifier))) = stream.peek()
+	{
+		let modifier = *modifier;
+		stream.consume()?;
+		// concatenated rolls are read as one number rather than as dice, so nothing can modify them
+		if let Node::Modifier {
+			modifier: DiceModifier::Concatenate,
+			..
+		} = dice
 		{
-			let rhs = parse_min_power(stream, binding_power.right, context)?;
-			Node::Binary {
-				operator: op,
-				left: Box::new(lhs),
-				right: Box::new(rhs),
-			}
+			return Err(UnmatchedModifierError(modifier).into());
 		}
+
+		let takes_argument = modifier.takes_argument()
+			&& matches!(
+				stream.peek(),
+				Some(Ok(Token::Number(_) | Token::OpenDelimiter))
+			);
+		let argument = if takes_argument
+		{
+			let binding_power = stream.dialect().binding_power(OpToken::Dice);
+			let (argument, argument_depth) =
+				parse_min_power(stream, binding_power.right, context.expect_dice_suffix())?;
+			depth = depth.max(argument_depth);
+			Some(Box::new(argument))
+		}
+		else
+		{
+			None
+		};
+
+		depth = nest(depth)?;
+		dice = Node::Modifier {
+			modifier,
+			dice: Box::new(dice),
+			argument,
+			span: Span::new(start, stream.last_span().end),
+		};
+	}
+
+	// without filters, a comparison after a dice term is left to be parsed as comparing two values
+	if stream.dialect().comparisons() == ComparisonMode::Boolean
+	{
+		return Ok((dice, depth));
+	}
+	if let Some(Ok(Token::ComparisonOperator(comp_op))) = stream.peek()
+	{
+		// deref now so mut borrow is possible next line -morgan 2024-01-14
+		let comp_op = *comp_op;
+		stream.consume_expecting(TokenType::ComparisonOperator)?;
+		let (compare_to, compare_to_depth) =
+			parse_min_power(stream, CompOperator::BINDING_POWER.right, context)?;
+		depth = nest(depth.max(compare_to_depth))?;
+		dice = Node::ComparisonTernary {
+			comp_op,
+			dice: Box::new(dice),
+			compare_to: Box::new(compare_to),
+			span: Span::new(start, stream.last_span().end),
+		};
 	}
 
-	Ok(lhs)
+	Ok((dice, depth))
+}
+
+// indexing and tags bind as tightly as a dice operator does to its left-hand side, so `4d6[0]`
+// indexes the rolls of `4d6` and `2d6[fire]` tags them, rather than the number `6`
+const INDEX_BINDING_POWER: BindingPower = BindingPower::new(19, 19);
+
+/// An operator which can appear between two expressions
+#[derive(Debug, Clone)]
+enum InfixOperator
+{
+	Binary(BinaryOperator),
+	Comparison(CompOperator),
+	Logic(LogicOperator),
+	/// `[index]` after a list. Only its left binding power is used, as the index is enclosed in
+	/// brackets
+	Index,
+	/// `[tag]` after any expression, which has no right-hand side
+	Tag(Box<str>),
+	/// An opening parenthesis directly after an expression, which multiplies them
+	ImplicitMultiply,
+}
+impl InfixOperator
+{
+	fn binding_power(&self, dialect: &Dialect) -> BindingPower
+	{
+		match self
+		{
+			Self::Binary(op) => dialect.binding_power(op.token),
+			Self::ImplicitMultiply => dialect.binding_power(OpToken::Multiply),
+			Self::Comparison(_) => CompOperator::BINDING_POWER,
+			Self::Logic(op) => op.binding_power(),
+			Self::Index | Self::Tag(_) => INDEX_BINDING_POWER,
+		}
+	}
 }
 
 #[derive(Debug, Default, Copy, Clone)]
 struct ParseContext
 {
 	expecting_comparison: bool,
+	expecting_modifier: bool,
 	expecting_close_paren: bool,
+	expecting_repetition: bool,
+	expecting_statement_end: bool,
+	expecting_separator: bool,
+	expecting_close_bracket: bool,
+	/// How many calls to `parse_min_power` the current one is nested within
+	depth: usize,
 }
 impl ParseContext
 {
+	/// Used while parsing the inside of parentheses, where nothing outside them is expected
 	pub fn expect_close_paren(self) -> Self
 	{
 		Self {
 			expecting_close_paren: true,
-			..self
+			depth: self.depth,
+			..Self::default()
 		}
 	}
 
-	pub fn expect_comparison(self) -> Self
+	/// Used while parsing an index, where nothing outside its brackets is expected
+	pub fn expect_close_bracket(self) -> Self
+	{
+		Self {
+			expecting_close_bracket: true,
+			depth: self.depth,
+			..Self::default()
+		}
+	}
+
+	/// Used on entering `parse_min_power`, so that deeply nested input (eg. 100,000 `(`
+	/// characters) is rejected before it can overflow the stack
+	pub fn nested(self) -> Result<Self, ParsingError>
+	{
+		if self.depth >= NestingDepthError::MAX_DEPTH
+		{
+			Err(NestingDepthError.into())
+		}
+		else
+		{
+			Ok(Self {
+				depth: self.depth + 1,
+				..self
+			})
+		}
+	}
+
+	/// Used while parsing the right-hand side of a dice operator, where a modifier or comparison
+	/// belonging to the dice term may follow
+	pub fn expect_dice_suffix(self) -> Self
 	{
 		Self {
 			expecting_comparison: true,
+			expecting_modifier: true,
 			..self
 		}
 	}
@@ -214,6 +1404,7 @@ mod tests
 			operator: OpToken::Plus.into(),
 			left: Box::new(Node::Leaf(1.0)),
 			right: Box::new(Node::Leaf(2.0)),
+			span: Span::default(),
 		};
 		assert_eq!(expected, expect_tree("1+2"));
 	}
@@ -230,9 +1421,14 @@ mod tests
 					operator: OpToken::Plus.into(),
 					left: Box::new(Node::ComparisonTernary {
 						comp_op: CompOperator::GreaterThan,
-						dice_left: Box::new(Leaf(2.0)),
-						dice_right: Box::new(Leaf(6.0)),
+						dice: Box::new(Binary {
+							operator: OpToken::Dice.into(),
+							left: Box::new(Leaf(2.0)),
+							right: Box::new(Leaf(6.0)),
+							span: Span::default(),
+						}),
 						compare_to: Box::new(Leaf(3.0)),
+						span: Span::default(),
 					}),
 					right: Box::new(Binary {
 						operator: OpToken::Divide.into(),
@@ -242,22 +1438,30 @@ mod tests
 								operator: OpToken::Dice.into(),
 								left: Box::new(Leaf(8.0)),
 								right: Box::new(Leaf(6.0)),
+								span: Span::default(),
 							}),
 							right: Box::new(Binary {
 								operator: OpToken::Power.into(),
 								left: Box::new(Leaf(3.0)),
 								right: Box::new(Leaf(4.0)),
+								span: Span::default(),
 							}),
+							span: Span::default(),
 						}),
 						right: Box::new(Leaf(2.0)),
+						span: Span::default(),
 					}),
+					span: Span::default(),
 				}),
 				right: Box::new(Leaf(3.0)),
+				span: Span::default(),
 			}),
 			right: Box::new(Unary {
 				operator: OpToken::Minus.try_into().unwrap(),
 				argument: Box::new(Leaf(1.0)),
+				span: Span::default(),
 			}),
+			span: Span::default(),
 		};
 		assert_eq!(expected, expect_tree("(2d6 > 3) + 8d6 * 3^4 / 2 - 3 + -1"));
 	}
@@ -271,6 +1475,22 @@ mod tests
 		));
 	}
 
+	#[test]
+	fn leading_logic_operator()
+	{
+		for input in ["and", "or", "and 1", "or 1", "and.+", "highest(or*", "1 + (and 2)"]
+		{
+			assert!(
+				matches!(
+					expect_err_tree(input),
+					ParsingError::Tokenization(TokenizationError::UnexpectedToken(_))
+				),
+				"{input}"
+			);
+		}
+		expect_tree("not 1");
+	}
+
 	#[test]
 	fn unmatched_paren()
 	{
@@ -279,10 +1499,201 @@ mod tests
 	}
 
 	#[test]
-	fn unmatched_comparison()
+	fn value_comparison()
+	{
+		assert!(matches!(
+			expect_tree("5 > 2"),
+			Node::Comparison {
+				comp_op: CompOperator::GreaterThan,
+				..
+			}
+		));
+		assert_eq!(
+			expect_tree("2d6 + 5 > 9"),
+			Node::Comparison {
+				comp_op: CompOperator::GreaterThan,
+				left: Box::new(expect_tree("(2d6 + 5)")),
+				right: Box::new(Node::Leaf(9.0)),
+				span: Span::default(),
+			}
+		);
+		// directly after a dice term, the comparison is still a filter
+		assert!(matches!(
+			&expect_tree("2d6 > 3 and 1"),
+			Node::Logic {
+				operator: LogicOperator::And,
+				left,
+				..
+			} if matches!(**left, Node::ComparisonTernary { .. })
+		));
+		assert!(matches!(
+			expect_tree("not 1 or 2 == 2"),
+			Node::Logic {
+				operator: LogicOperator::Or,
+				..
+			}
+		));
+	}
+
+	#[test]
+	fn calls()
+	{
+		assert_eq!(
+			expect_tree("if(1d20 >= 15, 2d6, 0)"),
+			Node::Conditional {
+				condition: Box::new(expect_tree("1d20 >= 15")),
+				if_true: Box::new(expect_tree("2d6")),
+				if_false: Box::new(Node::Leaf(0.0)),
+				span: Span::default(),
+			}
+		);
+		assert!(matches!(
+			expect_err_tree("if(1, 2)"),
+			ParsingError::ArgumentCount(_)
+		));
+		assert!(matches!(
+			expect_err_tree("foo(1)"),
+			ParsingError::UnknownFunction(_)
+		));
+		expect_err_tree("if(1, 2, 3");
+		expect_err_tree("1, 2");
+
+		assert_eq!(
+			expect_tree("table(loot) + 1"),
+			Node::Binary {
+				operator: OpToken::Plus.into(),
+				left: Box::new(Node::Table {
+					name: "loot".into(),
+					span: Span::default(),
+				}),
+				right: Box::new(Node::Leaf(1.0)),
+				span: Span::default(),
+			}
+		);
+		expect_err_tree("table(1d6)");
+		expect_err_tree("table(loot, 2)");
+	}
+
+	#[test]
+	fn modifiers()
+	{
+		let expected = Node::Modifier {
+			modifier: DiceModifier::KeepHighest,
+			dice: Box::new(Node::Binary {
+				operator: OpToken::Dice.into(),
+				left: Box::new(Node::Leaf(4.0)),
+				right: Box::new(Node::Leaf(6.0)),
+				span: Span::default(),
+			}),
+			argument: Some(Box::new(Node::Leaf(3.0))),
+			span: Span::default(),
+		};
+		assert_eq!(expected, expect_tree("4d6kh3"));
+
+		let expected = Node::Modifier {
+			modifier: DiceModifier::Advantage,
+			dice: Box::new(Node::Unary {
+				operator: OpToken::Dice.try_into().unwrap(),
+				argument: Box::new(Node::Leaf(20.0)),
+				span: Span::default(),
+			}),
+			argument: None,
+			span: Span::default(),
+		};
+		assert_eq!(expected, expect_tree("d20adv"));
+
+		assert!(matches!(
+			expect_tree("2d20kh1 + 5"),
+			Node::Binary {
+				operator: BinaryOperator {
+					token: OpToken::Plus,
+					..
+				},
+				..
+			}
+		));
+		assert!(matches!(
+			expect_tree("4d6dl1 > 2"),
+			Node::ComparisonTernary { .. }
+		));
+	}
+
+	#[test]
+	fn unmatched_modifier()
 	{
-		expect_err_tree("5 > 2");
-		expect_err_tree("2d6 + 5 > 9");
+		assert!(matches!(
+			expect_err_tree("5 kh1"),
+			ParsingError::UnmatchedModifier(_)
+		));
+		assert!(matches!(
+			expect_err_tree("2d6 + 1 adv"),
+			ParsingError::UnmatchedModifier(_)
+		));
+	}
+
+	#[test]
+	fn lists()
+	{
+		assert_eq!(
+			expect_tree("highest(5d10, 2)"),
+			Node::Function {
+				function: ListFunction::Highest,
+				arguments: Box::new([expect_tree("5d10"), Node::Leaf(2.0)]),
+				span: Span::default(),
+			}
+		);
+		assert_eq!(
+			expect_tree("4d6[1 + 1]"),
+			Node::Index {
+				list: Box::new(expect_tree("4d6")),
+				index: Box::new(expect_tree("1 + 1")),
+				span: Span::default(),
+			}
+		);
+		assert_eq!(expect_tree("(4d6)[0]"), expect_tree("4d6[0]"));
+		assert!(matches!(
+			expect_tree("count(6d6 == 6)"),
+			Node::Function {
+				function: ListFunction::Count,
+				..
+			}
+		));
+		assert!(matches!(
+			expect_err_tree("sort(4d6, 2)"),
+			ParsingError::ArgumentCount(_)
+		));
+		expect_err_tree("4d6[1");
+		expect_err_tree("4d6]");
+	}
+
+	#[test]
+	fn tags()
+	{
+		let tagged = |node: Node, tag: &str| Node::Tagged {
+			node: Box::new(node),
+			tag: tag.into(),
+			span: Span::default(),
+		};
+		assert_eq!(
+			expect_tree("2d6[fire] + 3"),
+			Node::Binary {
+				operator: OpToken::Plus.into(),
+				left: Box::new(tagged(expect_tree("2d6"), "fire")),
+				right: Box::new(Node::Leaf(3.0)),
+				span: Span::default(),
+			}
+		);
+		assert_eq!(
+			expect_tree("(2d6 + 3)[cold iron]"),
+			tagged(expect_tree("2d6 + 3"), "cold iron")
+		);
+		assert_eq!(
+			expect_tree("4d6kh3[rolled]"),
+			tagged(expect_tree("4d6kh3"), "rolled")
+		);
+		// a variable index needs parentheses to not be read as a tag
+		assert!(matches!(expect_tree("4d6[(i)]"), Node::Index { .. }));
+		expect_err_tree("[fire] 2d6");
 	}
 
 	#[test]
@@ -292,11 +1703,74 @@ mod tests
 			operator: OpToken::Multiply.into(),
 			left: Box::new(Node::Leaf(2.0)),
 			right: Box::new(Node::Leaf(3.0)),
+			span: Span::default(),
 		};
 
 		assert_eq!(two_by_three, expect_tree("2 * 3"),);
-		assert_eq!(two_by_three, expect_tree("2(3))"),);
+		assert_eq!(two_by_three, expect_tree("2(3)"),);
 		assert_eq!(two_by_three, expect_tree("(2)(3)"),);
+		assert!(matches!(
+			expect_err_tree("2(3))"),
+			ParsingError::UnmatchedCloseDelimiter(_)
+		));
+	}
+
+	#[test]
+	fn spans()
+	{
+		let tree = expect_tree("(1d4)d6 + d20 ");
+		let Node::Binary { left, right, .. } = &tree
+		else
+		{
+			panic!("expected binary node")
+		};
+
+		assert_eq!(tree.span(), Some(Span::new(0, 13)));
+		assert_eq!(left.span(), Some(Span::new(0, 7)));
+		assert_eq!(right.span(), Some(Span::new(10, 13)));
+		if let Node::Binary { left, .. } = left.as_ref()
+		{
+			assert_eq!(left.span(), Some(Span::new(1, 4)));
+		}
+	}
+
+	#[test]
+	fn repetition()
+	{
+		let repeated = parse_repeated_from(&mut TokenStream::new("6x 4d6kh3")).unwrap();
+		assert_eq!(repeated.count, 6);
+		assert_eq!(repeated.tree, expect_tree("4d6kh3"));
+		assert_eq!(repeated.span, Span::new(3, 9));
+
+		let repeated = parse_repeated_from(&mut TokenStream::new("3#1d20+5")).unwrap();
+		assert_eq!(repeated.count, 3);
+		assert_eq!(repeated.tree, expect_tree("1d20+5"));
+
+		let repeated = parse_repeated_from(&mut TokenStream::new("2d6")).unwrap();
+		assert_eq!(repeated.count, 1);
+
+		assert!(matches!(
+			parse_repeated_from(&mut TokenStream::new("1.5x 2d6")),
+			Err(ParsingError::InvalidRepetitionCount(_))
+		));
+		assert!(matches!(
+			parse_repeated_from(&mut TokenStream::new("1+2x 2d6")),
+			Err(ParsingError::InvalidRepetitionCount(_))
+		));
+
+		let max = InvalidRepetitionCountError::MAX_COUNT;
+		let repeated = parse_repeated_from(&mut TokenStream::new(&format!("{max}x 1"))).unwrap();
+		assert_eq!(repeated.count, max);
+		assert!(matches!(
+			parse_repeated_from(&mut TokenStream::new(&format!("{}x 1", max + 1))),
+			Err(ParsingError::InvalidRepetitionCount(_))
+		));
+		assert!(matches!(
+			parse_repeated_from(&mut TokenStream::new("100000000x 1")),
+			Err(ParsingError::InvalidRepetitionCount(_))
+		));
+		expect_err_tree("6x 4d6");
+		expect_err_tree("4d6 x6");
 	}
 
 	fn expect_tree(input: &str) -> Node
@@ -309,6 +1783,102 @@ mod tests
 			.unwrap_or_else(|_| panic!("Unexpected successful parse of `{input}`"))
 	}
 
+	#[test]
+	fn statements()
+	{
+		let statements =
+			parse_statements_from(&mut TokenStream::new("attack: 1d20+7; 2x d6 # fire;")).unwrap();
+		assert_eq!(statements.len(), 2);
+		assert_eq!(statements[0].label.as_deref(), Some("attack"));
+		assert_eq!(statements[0].repeated.tree, expect_tree("1d20+7"));
+		assert_eq!(statements[0].repeated.span, Span::new(8, 14));
+		assert_eq!(statements[1].label, None);
+		assert_eq!(statements[1].repeated.count, 2);
+
+		let statements =
+			parse_statements_from(&mut TokenStream::new("damage: 2d6 + attack")).unwrap();
+		assert_eq!(
+			statements[0].repeated.tree,
+			Node::Binary {
+				operator: OpToken::Plus.into(),
+				left: Box::new(expect_tree("2d6")),
+				right: Box::new(Node::Variable {
+					name: "attack".into(),
+					span: Span::default(),
+				}),
+				span: Span::default(),
+			}
+		);
+
+		assert!(parse_statements_from(&mut TokenStream::new("1d20 attack: 5")).is_err());
+		for (input, label) in [
+			("d: 1", "d"),
+			("kh: 1", "kh"),
+			("if: 1", "if"),
+			("adv: 1", "adv"),
+			("and: 1", "and"),
+			("x: 1", "x"),
+			("table: 1", "table"),
+			("a: 1; D: 2", "D"),
+		]
+		{
+			assert!(
+				matches!(
+					parse_statements_from(&mut TokenStream::new(input)),
+					Err(ParsingError::ReservedLabel(ReservedLabelError(it))) if it.as_ref() == label
+				),
+				"{input}"
+			);
+		}
+		assert!(parse_statements_from(&mut TokenStream::new("attack:")).is_err());
+		expect_err_tree("1d20; 2d6");
+	}
+
+	#[test]
+	fn nesting_depth()
+	{
+		let max = NestingDepthError::MAX_DEPTH;
+		let too_deep = |input: &str| matches!(parse_str(input), Err(ParsingError::NestingDepth(_)));
+
+		// pathological input is rejected rather than overflowing the stack
+		assert!(too_deep(&"(".repeat(100_000)));
+		assert!(too_deep(&format!("{}1", "-".repeat(100_000))));
+		assert!(too_deep(&format!("{}1", "not ".repeat(100_000))));
+		assert!(too_deep(&format!("1{}", "^1".repeat(100_000))));
+		assert!(too_deep(&format!("{}1", "d ".repeat(100_000))));
+		assert!(too_deep(&format!("4d6{}", " kh".repeat(100_000))));
+		assert!(too_deep(&format!("{}1{}", "sum(".repeat(100_000), ")".repeat(100_000))));
+		assert!(too_deep(&format!("sort(4d6){}", "[0]".repeat(100_000))));
+
+		// right up to the limit is fine
+		expect_tree(&format!("{}1{}", "(".repeat(max - 1), ")".repeat(max - 1)));
+		expect_tree(&format!("{}1", "-".repeat(max - 1)));
+		assert!(too_deep(&format!("{}1{}", "(".repeat(max), ")".repeat(max))));
+		assert!(too_deep(&format!("{}1", "-".repeat(max))));
+
+		// chains of operators are not nested, however long they are, and are dropped, cloned, and
+		// compared in a loop rather than recursively
+		for chain in [
+			format!("1{}", "+1".repeat(100_000)),
+			"(1)".repeat(100_000),
+			format!("1{}", ">1".repeat(100_000)),
+			format!("1{}", "==1".repeat(100_000)),
+			format!("{}1", "1 and ".repeat(100_000)),
+			format!("1d6{}", " - 2 * 1d4 >= 1 or 1".repeat(5_000)),
+		]
+		{
+			let tree = expect_tree(&chain);
+			assert!(tree.clone() == tree);
+			drop(tree);
+		}
+		// including a chain which is only partly parsed
+		expect_err_tree(&format!("1{}+", "+1".repeat(100_000)));
+
+		// separate statements are each limited on their own
+		let statements = format!("{}1{};", "(".repeat(max - 1), ")".repeat(max - 1)).repeat(1_000);
+		assert!(parse_statements_from(&mut TokenStream::new(&statements)).is_ok());
+	}
+
 	fn parse_str(input: &str) -> Result<Node, ParsingError>
 	{
 		parse_tree_from(&mut TokenStream::new(input))
@@ -0,0 +1,509 @@
+//! A front end which reads [Roll20](https://roll20.net)'s inline roll syntax (eg.
+//! `[[2d20kh1 + @{str_mod}]]`) into an [`Expression`], so that macros written for Roll20 can be
+//! evaluated without being rewritten
+//!
+//! Along with arithmetic (`+`, `-`, `*`, `/`, `%`, and `**`), the following are supported:
+//! - Inline roll delimiters (`[[1d20]]`), which can be nested (eg. `[[ [[1d4]]d6 ]]`)
+//! - Attribute references (eg. `@{str_mod}` or `@{Valeros|str_mod}`), which are replaced with the
+//!   text the caller looks up for them before the expression is read, as in Roll20
+//! - Keeping and dropping (`k`, `kh`, `kl`, `d`, `dh`, `dl`)
+//! - Exploding (`!`) and compounding (`!!`) dice, rerolls (`r`, `ro`), critical success and
+//!   failure ranges (`cs`, `cf`), and sorting (`s`, `sa`, `sd`)
+//! - Counting successes (eg. `3d6>4` is the number of rolls of at least 4)
+//! - Grouped rolls (eg. `{4d6, 3d8}kh1` or `{3d6, 2d6}>10`)
+//! - Inline labels (eg. the `[fire]` in `2d6[fire]`), which are ignored
+//!
+//! As in Roll20, a compare point of `>` or `<` includes the value itself, so `3d6!>5` explodes on
+//! a 5 or a 6. Several rerolls together are one condition, so `1d6r1r2` rerolls until neither a 1
+//! nor a 2 is rolled. Roll20 syntax which cannot be evaluated the same way, such as fate dice (`4dF`),
+//! penetrating dice (`!p`), counting failures (`f`), matching (`m`), roll queries (`?{...}`), and
+//! math functions (eg. `floor`), produces an
+//! [`UnsupportedSyntaxError`][crate::error::UnsupportedSyntaxError] rather than a different result
+//! # Examples
+//! ```rust
+//! # fn main() -> Result<(), saikoro::error::ParsingError> {
+//! let attributes = |name: &str| match name
+//! {
+//!     "str_mod" => Some(String::from("3")),
+//!     _ => None,
+//! };
+//! let expression = saikoro::roll20::parse("[[2d20kh1 + @{str_mod}]]", attributes)?;
+//! let evaluation = expression.evaluate()?;
+//! assert!((4.0..=23.0).contains(&evaluation.value));
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+	error::{ParsingError, UnresolvedReferenceError},
+	inline_roll::{self, expected_at, ModifierSyntax, Syntax},
+	operator::{CompOperator, DiceModifier},
+	Expression,
+};
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+
+/// The most times references can be replaced in a row, so that attributes which refer to each
+/// other cannot be replaced forever
+const MAX_EXPANSION_PASSES: usize = 32;
+
+/// The longest the input can grow to while its references are replaced, in bytes, so that
+/// attributes which refer to themselves more than once cannot double its length on every pass
+const MAX_EXPANDED_LENGTH: usize = 100_000;
+
+/// Parses a Roll20 inline roll (eg. `[[2d20kh1 + @{str_mod}]]`) into an [`Expression`]. The
+/// enclosing `[[` and `]]` are optional. Each attribute reference is replaced with the text
+/// `attributes` returns for the name inside its braces (see [`expand_attributes`])
+///
+/// The spans of the expression's [`RollGroup`]s refer to the input after its references are
+/// replaced, which is the same as `input` if it has none
+/// # Errors
+/// An error variant will be returned if an attribute cannot be resolved, if the input is not
+/// valid Roll20 syntax, or if it uses Roll20 syntax which is not supported (see the
+/// [module documentation][self])
+///
+/// [`RollGroup`]: crate::evaluation::RollGroup
+pub fn parse<F>(input: &str, attributes: F) -> Result<Expression, ParsingError>
+where
+	F: FnMut(&str) -> Option<String>,
+{
+	let expanded = expand_attributes(input, attributes)?;
+	inline_roll::parse(&expanded, &SYNTAX)
+}
+
+/// Replaces every attribute reference in `input` (eg. `@{str_mod}` or `@{Valeros|str_mod}`) with
+/// the text `attributes` returns for the name inside its braces. As in Roll20, the replacement is
+/// textual, and references in the replaced text are replaced as well
+/// # Errors
+/// Returns an [`UnresolvedReferenceError`] if `attributes` returns [`None`] for a reference, or if
+/// references are still left after replacing them many times in a row (eg. an attribute which
+/// refers to itself) or the replaced text grows longer than 100,000 bytes, and an
+/// [`ExpectedSyntaxError`][crate::error::ExpectedSyntaxError] if a reference is missing its
+/// closing `}`
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// let attributes = |name: &str| match name
+/// {
+///     "level" => Some(String::from("5")),
+///     "proficiency" => Some(String::from("(2 + @{level} / 4)")),
+///     _ => None,
+/// };
+/// let expanded = saikoro::roll20::expand_attributes("1d20 + @{proficiency}", attributes)?;
+/// assert_eq!(expanded, "1d20 + (2 + 5 / 4)");
+/// # Ok(())
+/// # }
+/// ```
+pub fn expand_attributes<F>(input: &str, attributes: F) -> Result<String, ParsingError>
+where
+	F: FnMut(&str) -> Option<String>,
+{
+	let mut attributes = attributes;
+	let mut expanded = String::from(input);
+	for _ in 0..MAX_EXPANSION_PASSES
+	{
+		if !expanded.contains("@{")
+		{
+			return Ok(expanded);
+		}
+
+		let mut replaced = String::with_capacity(expanded.len());
+		let mut rest = expanded.as_str();
+		while let Some(start) = rest.find("@{")
+		{
+			let Some(length) = rest[start..].find('}')
+			else
+			{
+				let index = expanded.len();
+				return Err(expected_at(&expanded, index, "`}`"));
+			};
+			let name = &rest[start + 2..start + length];
+			let value = attributes(name).ok_or_else(|| UnresolvedReferenceError(name.into()))?;
+
+			replaced.push_str(&rest[..start]);
+			replaced.push_str(&value);
+			if replaced.len() > MAX_EXPANDED_LENGTH
+			{
+				return Err(UnresolvedReferenceError(name.into()).into());
+			}
+			rest = &rest[start + length + 1..];
+		}
+		replaced.push_str(rest);
+		expanded = replaced;
+	}
+
+	match expanded.find("@{")
+	{
+		Some(start) =>
+		{
+			let length = expanded[start..].find('}').unwrap_or(expanded.len() - start);
+			Err(UnresolvedReferenceError(expanded[start + 2..start + length].into()).into())
+		}
+		None => Ok(expanded),
+	}
+}
+
+/// Returns the byte ranges of every inline roll in a chat message (eg. the `[[1d20+5]]` in
+/// `I attack for [[1d20+5]]`), including their delimiters, so that each can be given to
+/// [`parse`] and replaced with its result. Inline rolls nested within another are part of the
+/// outer roll rather than returned separately, and a `[[` without a matching `]]` is left as text
+/// # Examples
+/// ```rust
+/// let message = "Longsword: [[1d20+7]] to hit, [[1d8[slashing]]] damage";
+/// let rolls = saikoro::roll20::inline_rolls(message);
+/// assert_eq!(&message[rolls[0].clone()], "[[1d20+7]]");
+/// assert_eq!(&message[rolls[1].clone()], "[[1d8[slashing]]]");
+/// ```
+pub fn inline_rolls(message: &str) -> Vec<Range<usize>>
+{
+	let mut rolls = Vec::new();
+	let mut open = Vec::new();
+	let mut label_depth = 0_usize;
+	let mut index = 0;
+	while let Some(rest) = message.get(index..).filter(|it| !it.is_empty())
+	{
+		if rest.starts_with("[[")
+		{
+			open.push(index);
+			index += 2;
+		}
+		// an inline label's `]` comes before the `]]` which closes the roll (eg. `[[1d6[fire]]]`)
+		else if rest.starts_with('[') && !open.is_empty()
+		{
+			label_depth += 1;
+			index += 1;
+		}
+		else if rest.starts_with(']') && label_depth > 0
+		{
+			label_depth -= 1;
+			index += 1;
+		}
+		else if rest.starts_with("]]") && !open.is_empty()
+		{
+			let start = open.pop().unwrap_or_default();
+			index += 2;
+			if open.is_empty()
+			{
+				rolls.push(start..index);
+			}
+		}
+		else
+		{
+			index += rest.chars().next().map_or(1, char::len_utf8);
+		}
+	}
+
+	rolls
+}
+
+const SYNTAX: Syntax = Syntax {
+	// `>` and `<` include the value compared with
+	comparisons: &[
+		(">", CompOperator::GreaterOrEqual),
+		("<", CompOperator::LessOrEqual),
+		("=", CompOperator::Equals),
+	],
+	dice_modifiers: &[
+		("!p", ModifierSyntax::Unsupported),
+		("!!", ModifierSyntax::Point(DiceModifier::Compound)),
+		("!", ModifierSyntax::Point(DiceModifier::Explode)),
+		("ro", ModifierSyntax::Point(DiceModifier::RerollOnce)),
+		("r", ModifierSyntax::Point(DiceModifier::Reroll)),
+		("cs", ModifierSyntax::Point(DiceModifier::CriticalSuccess)),
+		("cf", ModifierSyntax::Point(DiceModifier::CriticalFailure)),
+		("kh", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("kl", ModifierSyntax::Count(DiceModifier::KeepLowest)),
+		("k", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("dh", ModifierSyntax::Count(DiceModifier::DropHighest)),
+		("dl", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("d", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("sd", ModifierSyntax::Plain(DiceModifier::SortDescending)),
+		("sa", ModifierSyntax::Plain(DiceModifier::SortAscending)),
+		("s", ModifierSyntax::Plain(DiceModifier::SortAscending)),
+		("mt", ModifierSyntax::Unsupported),
+		("m", ModifierSyntax::Unsupported),
+		("f", ModifierSyntax::Unsupported),
+	],
+	group_modifiers: &[
+		("kh", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("kl", ModifierSyntax::Count(DiceModifier::KeepLowest)),
+		("k", ModifierSyntax::Count(DiceModifier::KeepHighest)),
+		("dh", ModifierSyntax::Count(DiceModifier::DropHighest)),
+		("dl", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("d", ModifierSyntax::Count(DiceModifier::DropLowest)),
+		("f", ModifierSyntax::Unsupported),
+	],
+	bare_successes: true,
+	roll20_order: true,
+	merged_rerolls: true,
+	inline_rolls: true,
+	percentile_dice: false,
+	single_term_groups: true,
+	tags: false,
+	unsupported_operands: &["?{", "%{", "&{"],
+};
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::test_helpers::RiggedRandom;
+	use std::string::ToString;
+
+	fn attributes(name: &str) -> Option<String>
+	{
+		match name
+		{
+			"str_mod" => Some("3".to_string()),
+			"Valeros|dex_mod" => Some("2".to_string()),
+			"attack" => Some("1d20 + @{str_mod}".to_string()),
+			"loop" => Some("@{loop}".to_string()),
+			"double" => Some("(@{double}+@{double})".to_string()),
+			_ => None,
+		}
+	}
+
+	fn roll(input: &str, rolls: &[u32]) -> f64
+	{
+		parse(input, attributes)
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new(rolls.iter().copied()))
+			.unwrap()
+			.value
+	}
+
+	#[test]
+	fn arithmetic()
+	{
+		assert_eq!(roll("[[1 + 2 * 3]]", &[]), 7.0);
+		assert_eq!(roll("[[(1 + 2) * 3]]", &[]), 9.0);
+		assert_eq!(roll("[[2 ** 3 ** 2]]", &[]), 512.0);
+		assert_eq!(roll("[[10 % 4 - -1]]", &[]), 3.0);
+		assert_eq!(roll("[[7 / 2]]", &[]), 3.5);
+	}
+
+	#[test]
+	fn dice()
+	{
+		assert_eq!(roll("[[3d6]]", &[1, 5, 6]), 12.0);
+		assert_eq!(roll("[[d20 + 5]]", &[14]), 19.0);
+		assert_eq!(roll("[[ [[1d4]]d6 ]]", &[2, 3, 4]), 7.0);
+		assert_eq!(roll("[[(1+1)d6]]", &[3, 4]), 7.0);
+	}
+
+	#[test]
+	fn attribute_references()
+	{
+		assert_eq!(roll("[[2d20kh1 + @{str_mod}]]", &[7, 15]), 18.0);
+		assert_eq!(roll("[[1d20 + @{Valeros|dex_mod}]]", &[10]), 12.0);
+		assert_eq!(roll("[[@{attack} + 1]]", &[10]), 14.0);
+		match parse("[[1d20 + @{wis_mod}]]", attributes)
+		{
+			Err(ParsingError::UnresolvedReference(error)) => assert_eq!(&*error.0, "wis_mod"),
+			other => panic!("expected wis_mod to be unresolved, found {other:?}"),
+		}
+		for input in ["[[@{loop}]]", "[[@{double}]]"]
+		{
+			assert!(matches!(
+				parse(input, attributes),
+				Err(ParsingError::UnresolvedReference(_))
+			));
+		}
+		assert!(matches!(
+			parse("[[1d20 + @{str_mod]]", attributes),
+			Err(ParsingError::ExpectedSyntax(_))
+		));
+	}
+
+	#[test]
+	fn keep_and_drop()
+	{
+		assert_eq!(roll("[[8d100k4]]", &[10, 90, 20, 80, 30, 70, 40, 60]), 300.0);
+		assert_eq!(roll("[[8d100d3]]", &[10, 90, 20, 80, 30, 70, 40, 60]), 340.0);
+		assert_eq!(roll("[[4d6kl2]]", &[3, 1, 6, 2]), 3.0);
+		assert_eq!(roll("[[4d6dh1]]", &[3, 1, 6, 2]), 6.0);
+		assert_eq!(roll("[[2d20kh1]]", &[4, 17]), 17.0);
+	}
+
+	#[test]
+	fn exploding()
+	{
+		// the 5 explodes into a 6, which explodes into a 1, and the 6 explodes into a 2
+		assert_eq!(roll("[[3d6!>5]]", &[5, 2, 6, 6, 1, 2]), 22.0);
+		assert_eq!(roll("[[3d6!]]", &[5, 2, 6, 3]), 16.0);
+		assert_eq!(roll("[[2d6!2]]", &[2, 4, 2, 1]), 9.0);
+
+		let evaluation = parse("[[3d6!!]]", attributes)
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new([6, 3, 2, 6, 4]))
+			.unwrap();
+		assert_eq!(evaluation.value, 21.0);
+		assert_eq!(evaluation.roll_groups[0].len(), 3);
+		assert_eq!(evaluation.roll_groups[0].iter().next().unwrap().original_value, 16);
+	}
+
+	#[test]
+	fn rerolls()
+	{
+		// the 1 is rerolled into a 2, which is rerolled again into a 4
+		assert_eq!(roll("[[2d6r<2]]", &[1, 5, 2, 4]), 9.0);
+		assert_eq!(roll("[[2d6ro<2]]", &[1, 5, 2]), 7.0);
+		assert_eq!(roll("[[2d6r]]", &[1, 5, 3]), 8.0);
+		// rerolls happen before keeping, whatever order they are written in
+		assert_eq!(roll("[[2d20kh1r1]]", &[1, 12, 20]), 20.0);
+		// a roll is rerolled while it satisfies any of the conditions, not just the last one
+		assert_eq!(roll("[[1d6r1r2]]", &[1, 2, 1, 5]), 5.0);
+		assert_eq!(roll("[[1d6r2r<1]]", &[2, 1, 3]), 3.0);
+		assert_eq!(roll("[[1d6ro1ro2]]", &[2, 1]), 1.0);
+		assert_eq!(roll("[[1d6r1r2r3r4r5]]", &[3, 2, 6]), 6.0);
+	}
+
+	#[test]
+	fn invalid_faces()
+	{
+		// dice without any faces are an error rather than a panic, however their faces are written
+		for input in ["[[1d0]]", "[[2d(1-7)]]", "[[1d[[0]]]]", "[[1d0r1]]"]
+		{
+			let expression = parse(input, attributes).unwrap();
+			assert!(
+				matches!(expression.evaluate(), Err(ParsingError::InvalidFaces(_))),
+				"`{input}`"
+			);
+			assert!(
+				matches!(expression.evaluate_value(), Err(ParsingError::InvalidFaces(_))),
+				"`{input}`"
+			);
+			assert!(
+				matches!(
+					expression.compile().evaluate_value(),
+					Err(ParsingError::InvalidFaces(_))
+				),
+				"`{input}`"
+			);
+		}
+	}
+
+	#[test]
+	fn critical_ranges()
+	{
+		let evaluation = parse("[[8d6cs>4cf<2]]", attributes)
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new([1, 2, 3, 4, 5, 6, 1, 4]))
+			.unwrap();
+		assert_eq!(evaluation.value, 26.0);
+
+		let group = &evaluation.roll_groups[0];
+		let successes = group
+			.iter()
+			.filter(|it| group.is_critical_success_roll(it))
+			.count();
+		let failures = group
+			.iter()
+			.filter(|it| group.is_critical_failure_roll(it))
+			.count();
+		assert_eq!(successes, 4);
+		assert_eq!(failures, 3);
+	}
+
+	#[test]
+	fn sorting()
+	{
+		let evaluation = parse("[[4d6sd]]", attributes)
+			.unwrap()
+			.eval_with_rand(&mut RiggedRandom::new([3, 1, 6, 2]))
+			.unwrap();
+		let rolls = evaluation.roll_groups[0]
+			.iter()
+			.map(|it| it.original_value)
+			.collect::<Vec<_>>();
+		assert_eq!(rolls, [6, 3, 2, 1]);
+		assert_eq!(roll("[[4d6s]]", &[3, 1, 6, 2]), 12.0);
+	}
+
+	#[test]
+	fn successes()
+	{
+		assert_eq!(roll("[[3d6>4]]", &[4, 3, 6]), 2.0);
+		assert_eq!(roll("[[3d6<3]]", &[4, 3, 1]), 2.0);
+		assert_eq!(roll("[[5d10=10]]", &[10, 3, 10, 9, 1]), 2.0);
+		assert_eq!(roll("[[3d6!>6>5]]", &[6, 5, 1, 3]), 2.0);
+	}
+
+	#[test]
+	fn groups()
+	{
+		assert_eq!(roll("[[{4d6, 3d8}kh1]]", &[1, 2, 3, 4, 8, 8, 8]), 24.0);
+		assert_eq!(roll("[[{4d6, 3d8}kl1]]", &[1, 2, 3, 4, 8, 8, 8]), 10.0);
+		assert_eq!(roll("[[{1d20, 1d20, 1d20}dl1]]", &[5, 12, 9]), 21.0);
+		assert_eq!(roll("[[{3d6, 2d6}>10]]", &[6, 5, 1, 3, 4]), 1.0);
+		assert_eq!(roll("[[{2d6, 5}]]", &[3, 4]), 12.0);
+		assert_eq!(roll("[[{4d6}kh3]]", &[3, 1, 6, 2]), 11.0);
+		assert_eq!(roll("[[{3d6 + 3}>12]]", &[3, 4, 5]), 1.0);
+	}
+
+	#[test]
+	fn labels()
+	{
+		assert_eq!(roll("[[2d6[fire] + 1d4 [cold] + 5[bonus]]]", &[3, 4, 2]), 14.0);
+	}
+
+	#[test]
+	fn unsupported()
+	{
+		for (input, syntax) in [
+			("[[4dF]]", "dF"),
+			("[[3d6!p]]", "!p"),
+			("[[3d6>4f1]]", "f"),
+			("[[4d6mt]]", "mt"),
+			("[[floor(1.5)]]", "floor"),
+			("[[1d20 + ?{Bonus|0}]]", "?{"),
+			("[[{1d6 + 1d8}kh1]]", "{1d6 + 1d8}kh1"),
+		]
+		{
+			match parse(input, attributes)
+			{
+				Err(ParsingError::UnsupportedSyntax(error)) => assert_eq!(&*error.syntax, syntax),
+				other => panic!("expected {syntax} in {input} to be unsupported, found {other:?}"),
+			}
+		}
+	}
+
+	#[test]
+	fn syntax_errors()
+	{
+		for (input, index) in [("[[1d20", 6), ("[[1d20 +]]", 8), ("[[2d]]", 4), ("[[1d20 3]]", 7)]
+		{
+			match parse(input, attributes)
+			{
+				Err(ParsingError::ExpectedSyntax(error)) =>
+				{
+					assert_eq!(error.index, index, "{input}");
+				}
+				other => panic!("expected a syntax error in {input}, found {other:?}"),
+			}
+		}
+	}
+
+	#[test]
+	fn spans()
+	{
+		let input = "[[1d20 + 2d6kh1]]";
+		let evaluation = parse(input, attributes).unwrap().evaluate().unwrap();
+		let spans = evaluation
+			.roll_groups
+			.iter()
+			.map(|it| &input[it.span().unwrap()])
+			.collect::<Vec<_>>();
+		assert_eq!(spans, ["1d20", "2d6kh1"]);
+	}
+
+	#[test]
+	fn message()
+	{
+		let message = "[[1d20]] and [[ [[1d4]]d6 ]] and [[2d6[fire]]] but not [[1d8";
+		let rolls = inline_rolls(message)
+			.into_iter()
+			.map(|it| &message[it])
+			.collect::<Vec<_>>();
+		assert_eq!(rolls, ["[[1d20]]", "[[ [[1d4]]d6 ]]", "[[2d6[fire]]]"]);
+	}
+}
@@ -0,0 +1,270 @@
+use crate::{
+	error::ParsingError,
+	evaluation::{LabelledEvaluation, Scope, Variables},
+	parsing,
+	table::Tables,
+	tokenization::TokenStream,
+	CriticalMode, Dialect, RangeRng, RepeatedExpression,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::str::FromStr;
+
+/// A parsed sequence of [`Statement`]s separated by `;`, such as `attack: 1d20+7; damage: 2d6+4`.
+/// Each statement may be given a label, which later statements can use as a variable holding its
+/// value (eg. `damage: 2d6 + attack`). Anything from a `#` to the end of the statement or line is
+/// a comment and is ignored (eg. `2d6 # fire damage`), except for a `#` directly after the
+/// repetition count of a statement (eg. `3#1d20`)
+/// # Examples
+/// ```rust
+/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+/// use saikoro::Script;
+///
+/// let script = Script::parse("attack: 1d20+7; damage: 2d6+4 # slashing")?;
+/// let results = script.evaluate()?;
+/// assert_eq!(results.len(), 2);
+/// assert_eq!(results[1].label.as_deref(), Some("damage"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Script
+{
+	statements: Box<[Statement]>,
+}
+impl Script
+{
+	/// Parses a string of statements separated by `;` into a [`Script`]. Empty statements (eg.
+	/// after a trailing `;`) are skipped
+	/// # Errors
+	/// An error variant will be returned if any statement is unable to be parsed
+	pub fn parse(input: &str) -> Result<Self, ParsingError>
+	{
+		Self::parse_with_dialect(input, Dialect::SAIKORO)
+	}
+
+	/// Parses a string of statements following the syntax rules of `dialect` rather than the
+	/// default ones
+	/// # Errors
+	/// An error variant will be returned if any statement is unable to be parsed
+	pub fn parse_with_dialect(input: &str, dialect: Dialect) -> Result<Self, ParsingError>
+	{
+		let mut stream = TokenStream::with_dialect(input, dialect);
+		let statements = parsing::parse_statements_from(&mut stream)?
+			.into_iter()
+			.map(|statement| Statement {
+				label: statement.label,
+				expression: RepeatedExpression::from(statement.repeated),
+			})
+			.collect();
+
+		Ok(Self { statements })
+	}
+
+	/// Returns the statements of the script in the order they are evaluated
+	pub fn statements(&self) -> &[Statement]
+	{
+		&self.statements
+	}
+
+	/// Evaluates every statement in order with [`rand::thread_rng`]
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error, including
+	/// if a statement refers to a label which is not given to any earlier statement
+	#[cfg(feature = "std")]
+	pub fn evaluate(&self) -> Result<Vec<LabelledEvaluation>, ParsingError>
+	{
+		self.eval_with_rand(&mut rand::thread_rng())
+	}
+
+	/// Evaluates every statement in order with the given [`RangeRng`]. The same [`RangeRng`] is
+	/// used for every statement
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error, including
+	/// if a statement refers to a label which is not given to any earlier statement
+	pub fn eval_with_rand<R>(&self, rand: &mut R) -> Result<Vec<LabelledEvaluation>, ParsingError>
+	where
+		R: RangeRng,
+	{
+		self.eval_with_optional_tables(rand, None)
+	}
+
+	/// Evaluates every statement in order with the given [`RangeRng`], rolling on `tables` for
+	/// each `table(name)` in a statement (see
+	/// [`Expression::eval_with_tables`][crate::Expression::eval_with_tables])
+	/// # Errors
+	/// An error variant will be returned if the evaluation function produces an error, including
+	/// if a statement refers to a label which is not given to any earlier statement, or rolls on a
+	/// table which is not in `tables`
+	pub fn eval_with_tables<R>(
+		&self,
+		rand: &mut R,
+		tables: &Tables,
+	) -> Result<Vec<LabelledEvaluation>, ParsingError>
+	where
+		R: RangeRng,
+	{
+		self.eval_with_optional_tables(rand, Some(tables))
+	}
+
+	fn eval_with_optional_tables<R>(
+		&self,
+		rand: &mut R,
+		tables: Option<&Tables>,
+	) -> Result<Vec<LabelledEvaluation>, ParsingError>
+	where
+		R: RangeRng,
+	{
+		let mut variables = Variables::new();
+		self.statements
+			.iter()
+			.map(|statement| {
+				let scope = Scope {
+					tables,
+					..Scope::new(&variables)
+				};
+				let evaluation = LabelledEvaluation {
+					label: statement.label.clone(),
+					evaluations: statement.expression.eval_in_scope(rand, &scope)?,
+				};
+				if let Some(label) = &evaluation.label
+				{
+					variables.insert(label.clone(), evaluation.value());
+				}
+				Ok(evaluation)
+			})
+			.collect()
+	}
+
+	/// Returns a copy with every statement transformed for rolling a critical hit (see
+	/// [`Expression::critical`][crate::Expression::critical])
+	#[must_use]
+	pub fn critical(&self, mode: CriticalMode) -> Self
+	{
+		Self {
+			statements: self
+				.statements
+				.iter()
+				.map(|statement| Statement {
+					label: statement.label.clone(),
+					expression: statement.expression.critical(mode),
+				})
+				.collect(),
+		}
+	}
+
+	/// Returns a copy with the given large pool threshold for every statement (see
+	/// [`Expression::with_large_pool_threshold`][crate::Expression::with_large_pool_threshold])
+	#[must_use]
+	pub fn with_large_pool_threshold(&self, threshold: u32) -> Self
+	{
+		Self {
+			statements: self
+				.statements
+				.iter()
+				.map(|statement| Statement {
+					label: statement.label.clone(),
+					expression: statement.expression.with_large_pool_threshold(threshold),
+				})
+				.collect(),
+		}
+	}
+}
+impl FromStr for Script
+{
+	type Err = ParsingError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Self::parse(s)
+	}
+}
+
+/// A single statement of a [`Script`], made up of an optional label and a [`RepeatedExpression`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement
+{
+	label: Option<Box<str>>,
+	expression: RepeatedExpression,
+}
+impl Statement
+{
+	/// Returns the label of the statement (eg. `attack` for `attack: 1d20+7`), if it has one
+	pub fn label(&self) -> Option<&str>
+	{
+		self.label.as_deref()
+	}
+
+	/// Returns the expression of the statement, along with its repetition count
+	pub fn expression(&self) -> &RepeatedExpression
+	{
+		&self.expression
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::{
+		error::ParsingError,
+		eval_script_with_rand,
+		test_helpers::{assert_approx_eq, RiggedRandom},
+	};
+
+	#[test]
+	fn shared_rng()
+	{
+		let results =
+			eval_script_with_rand("attack: 1d20+7; damage: 2d6+4", &mut RiggedRandom::new([13, 2, 5]))
+				.unwrap();
+		assert_eq!(results.len(), 2);
+		assert_eq!(results[0].label.as_deref(), Some("attack"));
+		assert_approx_eq!(20.0, results[0].value());
+		assert_eq!(results[1].label.as_deref(), Some("damage"));
+		assert_approx_eq!(11.0, results[1].value());
+	}
+
+	#[test]
+	fn variables()
+	{
+		let input = "base: 1d4; 2x d6 + base # twice; base * 2";
+		let results = eval_script_with_rand(input, &mut RiggedRandom::new([3, 1, 6])).unwrap();
+		assert_eq!(
+			results.iter().map(LabelledEvaluation::value).collect::<Vec<_>>(),
+			vec![3.0, 13.0, 6.0]
+		);
+		assert_eq!(
+			results[1]
+				.annotate(input)
+				.map(|it| it.to_string())
+				.collect::<Vec<_>>(),
+			vec!["d6 __[1]__ + base = 4", "d6 **[6]** + base = 9"]
+		);
+
+		assert!(matches!(
+			eval_script_with_rand("damage: attack; attack: 1", &mut RiggedRandom::new([])),
+			Err(ParsingError::UndefinedVariable(_))
+		));
+	}
+
+	#[test]
+	fn implicit_multiplication()
+	{
+		let input = "six: 2(3); 2(six) + 1; 3x 2(1d4)";
+		let results = eval_script_with_rand(input, &mut RiggedRandom::new([1, 2, 3])).unwrap();
+		assert_eq!(
+			results.iter().map(LabelledEvaluation::value).collect::<Vec<_>>(),
+			vec![6.0, 13.0, 12.0]
+		);
+	}
+
+	#[test]
+	fn critical()
+	{
+		let script = Script::parse("attack: 1d20; damage: 2d6").unwrap();
+		assert_eq!(
+			script.critical(CriticalMode::DoubleDice),
+			Script::parse("attack: 2d20; damage: 4d6").unwrap()
+		);
+	}
+}
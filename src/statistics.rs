@@ -1,4 +1,8 @@
-use crate::evaluation::{DiceEvaluation, RollGroup};
+use crate::{
+	evaluation::{DiceEvaluation, Roll, RollGroup},
+	math,
+};
+use alloc::{vec, vec::Vec};
 
 impl RollGroup
 {
@@ -7,6 +11,7 @@ impl RollGroup
 		DiceExpression {
 			count: self.len() as u32,
 			faces: self.faces,
+			concatenated: self.is_concatenated(),
 		}
 	}
 
@@ -14,7 +19,11 @@ impl RollGroup
 	/// a calculation that includes removed rolls, see [`mean_raw`][RollGroup::mean_raw]
 	pub fn mean(&self) -> f64
 	{
-		f64::from(self.total())
+		if self.is_sampled()
+		{
+			return self.total() / f64::from(self.len() as u32);
+		}
+		self.total()
 			/ f64::from(self.iter().filter(|it| !it.is_removed()).count() as u32)
 	}
 
@@ -22,12 +31,16 @@ impl RollGroup
 	/// calculation that excludes removed rolls, see [`mean`][RollGroup::mean]
 	pub fn mean_raw(&self) -> f64
 	{
-		f64::from(self.iter().map(|it| it.original_value).sum::<u32>())
-			/ f64::from(self.len() as u32)
+		if self.is_sampled()
+		{
+			return self.mean();
+		}
+		self.iter().map(Roll::modified_value).sum::<f64>() / f64::from(self.len() as u32)
 	}
 
 	/// Returns the mean (average) of all possible values the expression which produced the
-	/// [`RollGroup`]
+	/// [`RollGroup`]. The dice of a [concatenated][RollGroup::is_concatenated] group are
+	/// digits, so the mean of `2d6cat` is 38.5 rather than 7
 	/// # Examples
 	/// ```rust
 	/// # use saikoro::evaluation::{Roll, RollGroup};
@@ -50,7 +63,8 @@ impl RollGroup
 	}
 	/// Returns the standard deviation of a roll with with the number and type of dice of the
 	/// [`RollGroup`] from the mean. (to get that mean, see
-	/// [`population_mean`][RollGroup::population_mean])
+	/// [`population_mean`][RollGroup::population_mean]). As with the mean, the dice of a
+	/// [concatenated][RollGroup::is_concatenated] group are digits
 	/// # Examples
 	/// ```rust
 	/// # use saikoro::evaluation::{Roll, RollGroup};
@@ -79,7 +93,8 @@ impl RollGroup
 	///
 	/// As such, this is considered a mildly costly operation and, while it may not cause a large
 	/// performance hit, it is nonetheless recommended to store this result if will be reused
-	/// instead of performing the calculation multiple times
+	/// instead of performing the calculation multiple times. Concatenated groups are the
+	/// exception, as their digits are independent, so their standard deviation takes constant time
 	#[must_use]
 	pub fn population_stdev(&self) -> f64
 	{
@@ -111,18 +126,109 @@ impl RollGroup
 	pub fn z_score(&self) -> f64
 	{
 		let population = self.expression();
-		(f64::from(self.total()) - population.mean()) / population.stdev()
+		(self.total() - population.mean()) / population.stdev()
 	}
 
 	/// Returns whether or not all rolls, including removed rolls, are their maximum value
 	pub fn is_max_roll(&self) -> bool
 	{
-		self.iter().all(|roll| roll.original_value >= self.faces)
+		match self.sampled_total()
+		{
+			Some(total) => total >= f64::from(self.faces) * f64::from(self.len() as u32),
+			None => self.iter().all(|roll| roll.original_value >= self.faces),
+		}
 	}
 	/// Returns whether or not all rolls, including removed rolls, are their minimum value (1)
 	pub fn is_min_roll(&self) -> bool
 	{
-		self.iter().all(|roll| roll.original_value <= 1)
+		match self.sampled_total()
+		{
+			Some(total) => total <= f64::from(self.len() as u32),
+			None => self.iter().all(|roll| roll.original_value <= 1),
+		}
+	}
+
+	/// Returns whether or not every kept (i.e. not removed) [`Roll`] is its maximum value, such as
+	/// a natural 20 on a d20. Unlike [`is_max_roll`][RollGroup::is_max_roll], removed rolls are not
+	/// considered, so rolling with advantage is only a critical success if the kept die is.
+	/// Returns `false` if there are no kept rolls
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::evaluation::{Roll, RollGroup};
+	/// // 1d20adv where the kept die is a natural 20
+	/// let roll_group = RollGroup::new(20, [Roll::new(20), Roll::new(4).into_removed()]);
+	/// assert!(roll_group.is_critical_success());
+	/// assert!(!roll_group.is_max_roll());
+	/// ```
+	pub fn is_critical_success(&self) -> bool
+	{
+		// nothing is removed from a sampled group
+		if self.is_sampled()
+		{
+			return self.is_max_roll();
+		}
+		self.kept_rolls_all(|roll| self.is_critical_success_roll(roll))
+	}
+
+	/// Returns whether or not every kept (i.e. not removed) [`Roll`] is its minimum value (1), such
+	/// as a natural 1 on a d20. Unlike [`is_min_roll`][RollGroup::is_min_roll], removed rolls are
+	/// not considered. Returns `false` if there are no kept rolls
+	/// # Examples
+	/// ```rust
+	/// # use saikoro::evaluation::{Roll, RollGroup};
+	/// // 1d20adv where the higher die is kept, so the natural 1 does not count
+	/// let roll_group = RollGroup::new(20, [Roll::new(1).into_removed(), Roll::new(12)]);
+	/// assert!(!roll_group.is_critical_failure());
+	/// ```
+	pub fn is_critical_failure(&self) -> bool
+	{
+		if self.is_sampled()
+		{
+			return self.is_min_roll();
+		}
+		self.kept_rolls_all(|roll| self.is_critical_failure_roll(roll))
+	}
+
+	/// Returns whether or not the natural value of `roll` counts as a critical success, which is
+	/// when it is the maximum value of the group's dice, unless another range was given (eg. by
+	/// the `cs>19` in Roll20's `1d20cs>19`)
+	/// # Examples
+	/// ```rust
+	/// # fn main() -> Result<(), saikoro::error::ParsingError> {
+	/// # use saikoro::evaluation::Roll;
+	/// let evaluation = saikoro::roll20::parse("[[1d20cs>19]]", |_| None)?.evaluate()?;
+	/// let group = &evaluation.roll_groups[0];
+	/// assert!(group.is_critical_success_roll(&Roll::new(19)));
+	/// assert!(!group.is_critical_success_roll(&Roll::new(18)));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn is_critical_success_roll(&self, roll: &Roll) -> bool
+	{
+		match self.critical_success()
+		{
+			Some((comp_op, value)) => comp_op.compare(f64::from(roll.original_value), value),
+			None => roll.original_value >= self.faces,
+		}
+	}
+
+	/// Returns whether or not the natural value of `roll` counts as a critical failure, which is
+	/// when it is 1, unless another range was given (eg. by the `cf<2` in Roll20's `1d20cf<2`)
+	pub fn is_critical_failure_roll(&self, roll: &Roll) -> bool
+	{
+		match self.critical_failure()
+		{
+			Some((comp_op, value)) => comp_op.compare(f64::from(roll.original_value), value),
+			None => roll.original_value <= 1,
+		}
+	}
+
+	fn kept_rolls_all<F>(&self, predicate: F) -> bool
+	where
+		F: Fn(&Roll) -> bool,
+	{
+		let mut kept = self.iter().filter(|it| !it.is_removed()).peekable();
+		kept.peek().is_some() && kept.all(predicate)
 	}
 }
 
@@ -190,23 +296,55 @@ struct DiceExpression
 {
 	count: u32,
 	faces: u32,
+	/// Whether the dice are read as the digits of one number rather than summed
+	concatenated: bool,
 }
 impl DiceExpression
 {
 	fn mean(self) -> f64
 	{
+		if self.concatenated
+		{
+			// ten-sided digits make every number from 1 to 10^count equally likely
+			if self.faces == 10
+			{
+				return (math::powf(10.0, f64::from(self.count)) + 1.0) / 2.0;
+			}
+			return self.digit_weights().sum::<f64>() * f64::from(self.faces + 1) / 2.0;
+		}
 		f64::from(self.count) * f64::from(self.faces + 1) / 2.0
 	}
 	fn stdev(self) -> f64
 	{
+		if self.concatenated
+		{
+			// the variance of a uniform distribution over n values is (n^2 - 1) / 12, and each
+			// independent digit contributes its variance scaled by the square of its place value
+			let variance = if self.faces == 10
+			{
+				(math::powf(100.0, f64::from(self.count)) - 1.0) / 12.0
+			}
+			else
+			{
+				let digit_variance = (math::powf(f64::from(self.faces), 2.0) - 1.0) / 12.0;
+				self.digit_weights().map(|it| it * it).sum::<f64>() * digit_variance
+			};
+			return math::sqrt(variance);
+		}
 		let mean = self.mean();
 		let variance =
 			self.population_iter()
-				.map(|val| f64::powi(f64::from(val) - mean, 2))
+				.map(|val| math::powf(f64::from(val) - mean, 2.0))
 				.sum::<f64>() / f64::from(self.population_size());
 
-		f64::sqrt(variance)
+		math::sqrt(variance)
+	}
+	/// The place value of each digit of a concatenated roll (eg. 1 and 10 for `2d6cat`)
+	fn digit_weights(self) -> impl Iterator<Item = f64>
+	{
+		(0..self.count).map(|it| math::powf(10.0, f64::from(it)))
 	}
+
 	fn population_size(self) -> u32
 	{
 		self.faces.pow(self.count)
@@ -274,6 +412,29 @@ mod test
 		assert_approx_eq!(2.41522945769824, DiceExpression::new(2, 6).stdev());
 	}
 
+	#[test]
+	fn concatenated()
+	{
+		assert_approx_eq!(38.5, DiceExpression::concatenated(2, 6).mean());
+		assert_approx_eq!(50.5, DiceExpression::concatenated(2, 10).mean());
+		// the population of 1 to 100 for a percentile roll
+		assert_approx_eq!(28.8660700477221, DiceExpression::concatenated(2, 10).stdev(), 1e-12);
+
+		// every number made of two d6 digits, read as the tens and units
+		let population = (1..=6)
+			.flat_map(|tens| (1..=6).map(move |units| f64::from(tens * 10 + units)))
+			.collect::<Vec<_>>();
+		let variance = population
+			.iter()
+			.map(|it| (it - 38.5) * (it - 38.5))
+			.sum::<f64>() / 36.0;
+		assert_approx_eq!(
+			math::sqrt(variance),
+			DiceExpression::concatenated(2, 6).stdev(),
+			1e-12
+		);
+	}
+
 	#[test]
 	fn z_score()
 	{
@@ -293,8 +454,11 @@ mod test
 		];
 
 		let evaluation = DiceEvaluation {
-			value: groups.iter().map(|group| f64::from(group.total())).sum(),
+			value: math::sum(groups.iter().map(RollGroup::total)),
 			roll_groups: groups.into(),
+			dependencies: Box::new([]),
+			tag_subtotals: Box::new([]),
+			table_rolls: Box::new([]),
 		};
 
 		assert_approx_eq!(0.839475199836382, evaluation.mean_z_score());
@@ -304,7 +468,19 @@ mod test
 	{
 		pub fn new(count: u32, faces: u32) -> Self
 		{
-			Self { count, faces }
+			Self {
+				count,
+				faces,
+				concatenated: false,
+			}
+		}
+
+		pub fn concatenated(count: u32, faces: u32) -> Self
+		{
+			Self {
+				concatenated: true,
+				..Self::new(count, faces)
+			}
 		}
 	}
 }
@@ -0,0 +1,625 @@
+//! Random tables (eg. of encounters, loot, or wild magic effects) which expressions can roll on
+//! with `table(name)`
+//!
+//! Tables are defined in a plain text format, where each table starts with a `table` header
+//! followed by its rows. A header with a dice expression after a `:` (eg. `table encounter:
+//! 1d6`) starts a ranged table, whose rows are selected by the value of that expression, and
+//! start with the value (eg. `6:`) or inclusive range of values (eg. `1-3:`) they are selected
+//! by. The ranges of a ranged table cannot overlap, and must cover every value a sum of numbers and
+//! plain dice terms can roll (eg. 1 to 6 for `1d6`). Other expressions, such as ones with
+//! modifiers (eg. `2d6cat`), are only checked when rolled. A header without one (eg. `table
+//! loot`) starts a weighted table, whose rows start with a whole number weight, and are selected
+//! with probability in proportion to it. Blank lines and lines starting with `#` are ignored
+//!
+//! The entry of a row is text which may contain expressions in braces (eg. `{1d4+1} goblins`),
+//! which are evaluated whenever the row is selected and replaced by their values. An expression
+//! which is only a roll on another table (eg. `{table(loot)}`) is replaced by the outcome of that
+//! roll instead
+//! ```text
+//! # random encounters in the forest
+//! table encounter: 1d6
+//! 1-3: {1d4+1} goblins
+//! 4-5: a wandering merchant carrying {table(loot)}
+//! 6: nothing
+//!
+//! table loot
+//! 3: {2d6} silver pieces
+//! 1: a potion of healing
+//! ```
+//! # Examples
+//! ```rust
+//! # fn main() -> Result<(), saikoro::error::ParsingError> {
+//! use saikoro::{table::Tables, Expression};
+//!
+//! let tables = Tables::parse("table encounter: 1d6\n1-5: {1d4+1} goblins\n6: nothing")?;
+//! let roll = tables.roll("encounter")?;
+//! if roll.selection.value < 6.0
+//! {
+//!     assert!(roll.outcome.ends_with(" goblins"));
+//!     assert_eq!(roll.evaluations.len(), 1);
+//! }
+//! else
+//! {
+//!     assert_eq!(roll.outcome, "nothing");
+//! }
+//!
+//! // the value of a roll on a table is the value of the roll which selected its row
+//! let evaluation =
+//!     Expression::parse("table(encounter)")?.eval_with_tables(&mut rand::thread_rng(), &tables)?;
+//! assert_eq!(evaluation.value, evaluation.table_rolls[0].selection.value);
+//! # Ok(())
+//! # }
+//! ```
+use crate::{
+	error::{
+		MissingTableRowError, ParsingError, TableDepthError, TableSyntaxError, UnknownTableError,
+	},
+	evaluation::{DiceEvaluation, Scope},
+	tokenization::{Token, TokenStream},
+	Expression, RangeRng,
+};
+use alloc::{boxed::Box, format, string::String, vec::Vec};
+use core::{
+	fmt::{Display, Write},
+	ops::RangeInclusive,
+	str::FromStr,
+};
+
+/// A set of named tables, parsed from the format described in the [module documentation][self]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tables
+{
+	tables: Box<[Table]>,
+}
+impl Tables
+{
+	/// Parses a set of tables from the format described in the [module documentation][self]
+	/// # Errors
+	/// An error variant will be returned if the input does not follow the format, including if
+	/// any of the expressions in it are unable to be parsed
+	pub fn parse(input: &str) -> Result<Self, ParsingError>
+	{
+		let mut tables = Vec::new();
+		let mut current: Option<TableBuilder> = None;
+		for (index, line) in input.lines().enumerate()
+		{
+			let line_number = index + 1;
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#')
+			{
+				continue;
+			}
+
+			if let Some(header) = line
+				.strip_prefix("table")
+				.filter(|it| it.starts_with(char::is_whitespace))
+			{
+				if let Some(table) = current.take()
+				{
+					tables.push(table.finish()?);
+				}
+				let table = TableBuilder::new(header.trim(), line_number)
+					.map_err(|reason| TableSyntaxError {
+						line: line_number,
+						reason,
+					})?;
+				if tables.iter().any(|it: &Table| it.name == table.name)
+				{
+					return Err(TableSyntaxError {
+						line: line_number,
+						reason: format!("table `{}` is defined more than once", table.name).into(),
+					}
+					.into());
+				}
+				current = Some(table);
+			}
+			else
+			{
+				current
+					.as_mut()
+					.ok_or_else(|| "expected a `table` header before the first row".into())
+					.and_then(|table| table.push_row(line))
+					.map_err(|reason| TableSyntaxError {
+						line: line_number,
+						reason,
+					})?;
+			}
+		}
+		if let Some(table) = current
+		{
+			tables.push(table.finish()?);
+		}
+
+		Ok(Self {
+			tables: tables.into(),
+		})
+	}
+
+	/// Returns whether there is a table with the given name
+	pub fn contains(&self, name: &str) -> bool
+	{
+		self.get(name).is_some()
+	}
+
+	/// Returns the names of the tables, in the order they are defined
+	pub fn names(&self) -> impl Iterator<Item = &str>
+	{
+		self.tables.iter().map(|it| &*it.name)
+	}
+
+	/// Rolls on the table with the given name with [`rand::thread_rng`]
+	/// # Errors
+	/// An error variant will be returned if there is no table with the given name, or the
+	/// evaluation function produces an error
+	#[cfg(feature = "std")]
+	pub fn roll(&self, name: &str) -> Result<TableRoll, ParsingError>
+	{
+		self.roll_with_rand(name, &mut rand::thread_rng())
+	}
+
+	/// Rolls on the table with the given name with the given [`RangeRng`]
+	/// # Errors
+	/// An error variant will be returned if there is no table with the given name, or the
+	/// evaluation function produces an error
+	pub fn roll_with_rand<R>(&self, name: &str, rand: &mut R) -> Result<TableRoll, ParsingError>
+	where
+		R: RangeRng,
+	{
+		self.roll_in_scope(name, rand, &Scope::EMPTY.with_tables(self))
+	}
+
+	/// Rolls on the table with the given name, evaluating the expressions of its entry in `scope`
+	/// nested one table roll deeper
+	pub(crate) fn roll_in_scope<R>(
+		&self,
+		name: &str,
+		rand: &mut R,
+		scope: &Scope,
+	) -> Result<TableRoll, ParsingError>
+	where
+		R: RangeRng,
+	{
+		if scope.table_depth >= TableDepthError::MAX_DEPTH
+		{
+			return Err(TableDepthError.into());
+		}
+		let table = self.get(name).ok_or_else(|| UnknownTableError(name.into()))?;
+		let scope = Scope {
+			table_depth: scope.table_depth + 1,
+			..*scope
+		};
+
+		let selection = table.selector.eval_in_scope(rand, &scope)?;
+		let row = table
+			.rows
+			.iter()
+			.position(|row| row.selected_by(selection.value))
+			.ok_or_else(|| MissingTableRowError {
+				table: name.into(),
+				roll: selection.value,
+			})?;
+
+		let mut outcome = String::new();
+		let mut evaluations = Vec::new();
+		for segment in &table.rows[row].entry
+		{
+			match segment
+			{
+				Segment::Text(text) => outcome.push_str(text),
+				Segment::Expression(expression) =>
+				{
+					let evaluation = expression.eval_in_scope(rand, &scope)?;
+					match evaluation.table_rolls.first()
+					{
+						Some(table_roll) if expression.is_table_roll() =>
+						{
+							outcome.push_str(&table_roll.outcome);
+						}
+						_ => write!(outcome, "{}", evaluation.value)
+							.expect("writing to a String should not fail"),
+					}
+					evaluations.push(evaluation);
+				}
+			}
+		}
+
+		Ok(TableRoll {
+			table: table.name.clone(),
+			selection,
+			row,
+			outcome,
+			evaluations: evaluations.into(),
+		})
+	}
+
+	fn get(&self, name: &str) -> Option<&Table>
+	{
+		self.tables.iter().find(|it| &*it.name == name)
+	}
+}
+impl FromStr for Tables
+{
+	type Err = ParsingError;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err>
+	{
+		Self::parse(s)
+	}
+}
+
+/// The result of a roll on a table
+#[derive(Debug)]
+pub struct TableRoll
+{
+	/// The name of the table which was rolled on
+	pub table: Box<str>,
+	/// The roll which selected the row
+	pub selection: DiceEvaluation,
+	/// The index of the selected row, counting from 0 in the order the rows are defined
+	pub row: usize,
+	/// The entry of the selected row, with each expression in it replaced by its value (or the
+	/// outcome of its roll on another table)
+	pub outcome: String,
+	/// The evaluations of the expressions in the entry of the selected row, in order
+	pub evaluations: Box<[DiceEvaluation]>,
+}
+impl Display for TableRoll
+{
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
+	{
+		write!(f, "{} ({}): {}", self.table, self.selection.value, self.outcome)
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Table
+{
+	name: Box<str>,
+	/// The expression whose value selects a row
+	selector: Expression,
+	rows: Box<[Row]>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Row
+{
+	/// The values of the selecting roll which select this row. The rows of a weighted table are
+	/// given consecutive ranges as long as their weights, selected by a roll of the total weight
+	range: RangeInclusive<u32>,
+	entry: Box<[Segment]>,
+}
+impl Row
+{
+	fn selected_by(&self, value: f64) -> bool
+	{
+		f64::from(*self.range.start()) <= value && value <= f64::from(*self.range.end())
+	}
+}
+
+/// A part of the entry of a row
+#[derive(Debug, Clone, PartialEq)]
+enum Segment
+{
+	Text(Box<str>),
+	Expression(Expression),
+}
+
+/// A table which is still being parsed. Errors while parsing a single line are returned as only
+/// their reason, which [`Tables::parse`] adds the line number to
+struct TableBuilder
+{
+	name: Box<str>,
+	/// The line the header of the table is on
+	line: usize,
+	/// The expression whose value selects a row, or [`None`] for a weighted table
+	selector: Option<Expression>,
+	rows: Vec<Row>,
+}
+impl TableBuilder
+{
+	/// Starts a table from its header, after the `table`
+	fn new(header: &str, line: usize) -> Result<Self, Box<str>>
+	{
+		let (name, selector) = match header.split_once(':')
+		{
+			Some((name, selector)) =>
+			{
+				let selector = Expression::parse(selector).map_err(|it| expression_error(&it))?;
+				(name.trim(), Some(selector))
+			}
+			None => (header, None),
+		};
+		// the name must be read as a single identifier for `table(name)` to refer to it
+		let mut tokens = TokenStream::new(name);
+		if !matches!(
+			(tokens.next(), tokens.next()),
+			(Some(Ok(Token::Identifier(_))), None)
+		)
+		{
+			return Err(
+				format!("expected a table name made of letters and `_`, found `{name}`").into(),
+			);
+		}
+
+		Ok(Self {
+			name: name.into(),
+			line,
+			selector,
+			rows: Vec::new(),
+		})
+	}
+
+	/// Adds a row of the form `<value or range>: <entry>` to a ranged table, or `<weight>:
+	/// <entry>` to a weighted table
+	fn push_row(&mut self, line: &str) -> Result<(), Box<str>>
+	{
+		let (key, entry) = line
+			.split_once(':')
+			.ok_or("expected a row in the form `<roll>: <entry>`")?;
+		let key = key.trim();
+		let range = if self.selector.is_some()
+		{
+			let (start, end) = key.split_once('-').unwrap_or((key, key));
+			let range = parse_whole(start)?..=parse_whole(end)?;
+			if range.is_empty()
+			{
+				return Err(format!("the range `{key}` is empty").into());
+			}
+			if self
+				.rows
+				.iter()
+				.any(|row| row.range.start() <= range.end() && range.start() <= row.range.end())
+			{
+				return Err(format!("`{key}` overlaps the range of an earlier row").into());
+			}
+			range
+		}
+		else
+		{
+			let weight = parse_whole(key)?;
+			if weight == 0
+			{
+				return Err("weights must be greater than 0".into());
+			}
+			let start = self.rows.last().map_or(1, |row| row.range.end() + 1);
+			let end = (start - 1)
+				.checked_add(weight)
+				.ok_or("the total weight of the table is too large")?;
+			start..=end
+		};
+
+		self.rows.push(Row {
+			range,
+			entry: parse_entry(entry.trim())?,
+		});
+		Ok(())
+	}
+
+	fn finish(self) -> Result<Table, TableSyntaxError>
+	{
+		let total = match self.rows.last()
+		{
+			Some(last) => *last.range.end(),
+			None =>
+			{
+				return Err(TableSyntaxError {
+					line: self.line,
+					reason: format!("table `{}` has no rows", self.name).into(),
+				})
+			}
+		};
+		let selector = match self.selector
+		{
+			Some(selector) =>
+			{
+				if let Some(roll) = uncovered_roll(&self.rows, &selector)
+				{
+					return Err(TableSyntaxError {
+						line: self.line,
+						reason: format!("table `{}` has no row for a roll of {roll}", self.name)
+							.into(),
+					});
+				}
+				selector
+			}
+			None => Expression::parse(&format!("1d{total}"))
+				.expect("a dice term with a whole number of faces should be parsed"),
+		};
+
+		Ok(Table {
+			name: self.name,
+			selector,
+			rows: self.rows.into(),
+		})
+	}
+}
+
+/// Finds the lowest roll which selects none of the rows of a ranged table, out of the rolls its
+/// selector can make. Selectors whose bounds cannot be found without rolling them (eg. `2d6cat`)
+/// are not checked
+fn uncovered_roll(rows: &[Row], selector: &Expression) -> Option<f64>
+{
+	let (low, high) = selector.bounds()?;
+	let mut ranges = rows.iter().map(|row| row.range.clone()).collect::<Vec<_>>();
+	ranges.sort_by_key(|range| *range.start());
+
+	// the lowest roll which is not yet known to be covered
+	let mut next = low;
+	for range in ranges
+	{
+		if f64::from(*range.start()) > next
+		{
+			break;
+		}
+		next = next.max(f64::from(*range.end()) + 1.0);
+	}
+	(next <= high).then_some(next)
+}
+
+fn parse_whole(str: &str) -> Result<u32, Box<str>>
+{
+	str.trim()
+		.parse()
+		.map_err(|_| format!("expected a whole number, found `{}`", str.trim()).into())
+}
+
+/// Splits an entry into its text and the expressions in braces within it
+fn parse_entry(entry: &str) -> Result<Box<[Segment]>, Box<str>>
+{
+	let mut segments = Vec::new();
+	let mut rest = entry;
+	while let Some(open) = rest.find('{')
+	{
+		if open > 0
+		{
+			segments.push(Segment::Text(rest[..open].into()));
+		}
+		let close = rest[open..]
+			.find('}')
+			.ok_or("found `{` without a matching `}`")?
+			+ open;
+		let expression =
+			Expression::parse(&rest[open + 1..close]).map_err(|it| expression_error(&it))?;
+		segments.push(Segment::Expression(expression));
+		rest = &rest[close + 1..];
+	}
+	if !rest.is_empty()
+	{
+		segments.push(Segment::Text(rest.into()));
+	}
+
+	Ok(segments.into())
+}
+
+/// The reason for a [`TableSyntaxError`] caused by an expression which is unable to be parsed
+fn expression_error(error: &ParsingError) -> Box<str>
+{
+	format!("{error}").trim_end_matches('!').into()
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+	use crate::test_helpers::{assert_approx_eq, RiggedRandom};
+
+	const TABLES: &str = "\
+		# random encounters
+		table encounter: 1d6
+		1-3: {1d4 + 1} goblins
+		4-5: a merchant carrying {table(loot)}
+		6: nothing
+
+		table loot
+		3: {2d6} silver pieces
+		1: a potion of healing
+	";
+
+	#[test]
+	fn ranged()
+	{
+		let tables = Tables::parse(TABLES).unwrap();
+		assert_eq!(tables.names().collect::<Vec<_>>(), vec!["encounter", "loot"]);
+
+		let roll = tables
+			.roll_with_rand("encounter", &mut RiggedRandom::new([2, 3]))
+			.unwrap();
+		assert_approx_eq!(2.0, roll.selection.value);
+		assert_eq!(roll.row, 0);
+		assert_eq!(roll.outcome, "4 goblins");
+		assert_approx_eq!(4.0, roll.evaluations[0].value);
+		assert_eq!(roll.to_string(), "encounter (2): 4 goblins");
+
+		// rows can be in any order, as long as they cover every roll
+		let tables = Tables::parse("table shuffled: 1d4 + 1\n4-5: high\n2-3: low").unwrap();
+		let roll = tables
+			.roll_with_rand("shuffled", &mut RiggedRandom::new([4]))
+			.unwrap();
+		assert_eq!((roll.row, roll.outcome.as_str()), (0, "high"));
+		assert!(Tables::parse("table digits: 2d6cat\n11-16: a\n21-66: b").is_ok());
+	}
+
+	#[test]
+	fn weighted()
+	{
+		let tables = Tables::parse(TABLES).unwrap();
+		let roll = tables
+			.roll_with_rand("loot", &mut RiggedRandom::new([3, 5, 6]))
+			.unwrap();
+		assert_eq!(roll.row, 0);
+		assert_eq!(roll.outcome, "11 silver pieces");
+
+		let roll = tables
+			.roll_with_rand("loot", &mut RiggedRandom::new([4]))
+			.unwrap();
+		assert_eq!(roll.row, 1);
+		assert_eq!(roll.outcome, "a potion of healing");
+	}
+
+	#[test]
+	fn nested()
+	{
+		let tables = Tables::parse(TABLES).unwrap();
+		let evaluation = Expression::parse("table(encounter) + 10")
+			.unwrap()
+			.eval_with_tables(&mut RiggedRandom::new([5, 4]), &tables)
+			.unwrap();
+		assert_approx_eq!(15.0, evaluation.value);
+
+		let roll = &evaluation.table_rolls[0];
+		assert_eq!(roll.outcome, "a merchant carrying a potion of healing");
+		assert_eq!(roll.evaluations[0].table_rolls[0].table.as_ref(), "loot");
+		assert_eq!(
+			evaluation.to_string(),
+			"Total: 15 []; encounter (5): a merchant carrying a potion of healing"
+		);
+		assert_eq!(
+			evaluation.annotate("table(encounter) + 10").to_string(),
+			"table(encounter) + 10 = 15; encounter (5): a merchant carrying a potion of healing"
+		);
+	}
+
+	#[test]
+	fn errors()
+	{
+		for (input, line) in [
+			("1: orphan row", 1),
+			("table encounter: 1d6\n1-3: a\n3-4: b", 3),
+			("table encounter: 1d6\n4-2: a", 2),
+			("table loot\n0: nothing", 2),
+			("table loot\n1: {1d6", 2),
+			("table loot\n1: {1d}", 2),
+			("table kh: 1d6\n1: a", 1),
+			("table empty\n\ntable loot\n1: a", 1),
+			("table loot\n1: a\ntable loot\n1: b", 3),
+			("table encounter: 1d6\n1-2: a\n4-6: b", 1),
+			("table encounter: 1d8\n1-6: a", 1),
+			("table encounter: 1d6 - 1\n1-6: a", 1),
+		]
+		{
+			match Tables::parse(input)
+			{
+				Err(ParsingError::TableSyntax(error)) => assert_eq!(error.line, line, "{input}"),
+				other => panic!("expected a table syntax error for {input:?}, found {other:?}"),
+			}
+		}
+
+		// selectors with modifiers are only checked when rolled
+		let tables =
+			Tables::parse("table gap: 2d6kh1\n1-5: a\ntable again\n1: {table(again)}").unwrap();
+		assert!(matches!(
+			tables.roll_with_rand("gap", &mut RiggedRandom::new([6, 6])),
+			Err(ParsingError::MissingTableRow(_))
+		));
+		assert!(matches!(
+			tables.roll_with_rand("again", &mut RiggedRandom::new([1; 64])),
+			Err(ParsingError::TableDepth(_))
+		));
+		assert!(matches!(
+			Expression::parse("table(loot)")
+				.unwrap()
+				.eval_with_rand(&mut RiggedRandom::new([])),
+			Err(ParsingError::UnknownTable(_))
+		));
+	}
+}
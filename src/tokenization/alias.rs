@@ -0,0 +1,82 @@
+use alloc::{borrow::Cow, boxed::Box, string::String, vec::Vec};
+
+/// Returns the ASCII character which `char` is an alias of, if it is one. This covers the
+/// full-width forms typed by Japanese and Chinese input methods (eg. `２Ｄ６＋３`), and the
+/// typographic `×`, `÷`, and `−`
+pub fn ascii_alias(char: char) -> Option<char>
+{
+	match char
+	{
+		// the full-width forms of `!` through `~` are in the same order as their ASCII forms
+		'\u{FF01}'..='\u{FF5E}' => char::from_u32(u32::from(char) - 0xFF01 + 0x21),
+		'×' => Some('*'),
+		'÷' => Some('/'),
+		'−' => Some('-'),
+		_ => None,
+	}
+}
+
+/// An input string with every alias replaced by its ASCII character, so that it can be read
+/// byte-by-byte. Since the replacements are shorter than the characters they replace, byte
+/// indices into the normalized string are mapped back to the original input with
+/// [`original_index`][Normalized::original_index]
+pub struct Normalized<'a>
+{
+	pub str: Cow<'a, str>,
+	/// The index in the original input of each byte of `str`, plus its end. Empty if nothing was
+	/// replaced, in which case the indices are the same
+	offsets: Box<[usize]>,
+}
+impl<'a> Normalized<'a>
+{
+	pub fn new(input: &'a str) -> Self
+	{
+		if !input.chars().any(|it| ascii_alias(it).is_some())
+		{
+			return Self {
+				str: Cow::Borrowed(input),
+				offsets: Box::default(),
+			};
+		}
+
+		let mut str = String::with_capacity(input.len());
+		let mut offsets = Vec::with_capacity(input.len() + 1);
+		for (index, char) in input.char_indices()
+		{
+			let char = ascii_alias(char).unwrap_or(char);
+			str.push(char);
+			offsets.extend(core::iter::repeat_n(index, char.len_utf8()));
+		}
+		offsets.push(input.len());
+
+		Self {
+			str: Cow::Owned(str),
+			offsets: offsets.into_boxed_slice(),
+		}
+	}
+
+	/// Maps a byte index in the normalized string back to the original input
+	pub fn original_index(&self, index: usize) -> usize
+	{
+		self.offsets.get(index).copied().unwrap_or(index)
+	}
+}
+
+#[cfg(test)]
+mod tests
+{
+	use super::*;
+
+	#[test]
+	fn normalize()
+	{
+		let normalized = Normalized::new("２Ｄ６＋3 × 2");
+		assert_eq!(normalized.str, "2D6+3 * 2");
+		assert_eq!(normalized.original_index(1), 3);
+		assert_eq!(normalized.original_index(4), 12);
+		assert_eq!(normalized.original_index(6), 14);
+		assert_eq!(normalized.original_index(9), "２Ｄ６＋3 × 2".len());
+
+		assert!(matches!(Normalized::new("2d6 + é").str, Cow::Borrowed("2d6 + é")));
+	}
+}
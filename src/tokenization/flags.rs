@@ -1,14 +1,15 @@
 use super::TokenType;
 use crate::tokenization::TOKEN_TYPES;
-use std::{fmt::Display, ops};
+use alloc::boxed::Box;
+use core::{fmt::Display, ops};
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub struct TokenFlags(u8);
+pub struct TokenFlags(u16);
 impl TokenFlags
 {
 	pub fn has_set(self, token_type: TokenType) -> bool
 	{
-		let token_val = token_type as u8;
+		let token_val = token_type as u16;
 		self.0 & token_val == token_val
 	}
 }
@@ -25,19 +26,19 @@ impl ops::BitOr<TokenType> for TokenFlags
 	type Output = Self;
 	fn bitor(self, rhs: TokenType) -> Self::Output
 	{
-		Self(self.0 | rhs as u8)
+		Self(self.0 | rhs as u16)
 	}
 }
 impl From<TokenType> for TokenFlags
 {
 	fn from(value: TokenType) -> Self
 	{
-		Self(value as u8)
+		Self(value as u16)
 	}
 }
 impl Display for TokenFlags
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
 	{
 		let set_types = TOKEN_TYPES
 			.iter()
@@ -53,7 +54,7 @@ impl ops::BitOr<Self> for TokenType
 	type Output = TokenFlags;
 	fn bitor(self, rhs: Self) -> Self::Output
 	{
-		TokenFlags(self as u8 | rhs as u8)
+		TokenFlags(self as u16 | rhs as u16)
 	}
 }
 
@@ -1,21 +1,37 @@
+mod alias;
 mod flags;
 mod stream;
 
 pub use flags::*;
 pub use stream::*;
 
-use crate::operator::{CompOperator, OpToken};
-use lazy_regex::regex;
-use regex::Regex;
-use std::fmt::Display;
+pub(crate) use alias::ascii_alias;
 
+use crate::{
+	operator::{CompOperator, DiceModifier, LogicOperator, OpToken},
+	Dialect,
+};
+use alloc::boxed::Box;
+use core::{fmt::Display, ops::Range};
+
+/// Every [`TokenType`], in the order they are listed when displaying [`TokenFlags`]
 static TOKEN_TYPES: &[TokenType] = &[
 	TokenType::Number,
+	TokenType::Label,
+	TokenType::Identifier,
+	TokenType::Modifier,
+	TokenType::LogicOperator,
 	TokenType::Operator,
 	TokenType::ComparisonOperator,
 	TokenType::OpenDelimiter,
 	TokenType::CloseDelimiter,
 	TokenType::Whitespace,
+	TokenType::Repetition,
+	TokenType::StatementEnd,
+	TokenType::Separator,
+	TokenType::OpenBracket,
+	TokenType::CloseBracket,
+	TokenType::Tag,
 ];
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum TokenType
@@ -26,22 +42,19 @@ pub enum TokenType
 	OpenDelimiter = 1 << 3,
 	CloseDelimiter = 1 << 4,
 	Whitespace = 1 << 5,
+	Modifier = 1 << 6,
+	Repetition = 1 << 7,
+	Identifier = 1 << 8,
+	Label = 1 << 9,
+	StatementEnd = 1 << 10,
+	LogicOperator = 1 << 11,
+	Separator = 1 << 12,
+	OpenBracket = 1 << 13,
+	CloseBracket = 1 << 14,
+	Tag = 1 << 15,
 }
 impl TokenType
 {
-	pub fn regex(self) -> &'static Regex
-	{
-		match self
-		{
-			Self::Number => regex!(r"\d+(\.\d+)?"),
-			Self::Operator => regex!(r"[\+\-\*\/%^dD]"),
-			Self::ComparisonOperator => regex!(r"[=!<>]=|>|<"),
-			Self::OpenDelimiter => regex!(r"\("),
-			Self::CloseDelimiter => regex!(r"\)"),
-			Self::Whitespace => regex!(r"\s+"),
-		}
-	}
-
 	fn display_name(self) -> &'static str
 	{
 		match self
@@ -52,18 +65,50 @@ impl TokenType
 			Self::OpenDelimiter => "OpenDelim",
 			Self::CloseDelimiter => "CloseDelim",
 			Self::Whitespace => "Whitespace",
+			Self::Modifier => "Modifier",
+			Self::Repetition => "Repetition",
+			Self::Identifier => "Identifier",
+			Self::Label => "Label",
+			Self::StatementEnd => "StatementEnd",
+			Self::LogicOperator => "LogicOperator",
+			Self::Separator => "Separator",
+			Self::OpenBracket => "OpenBracket",
+			Self::CloseBracket => "CloseBracket",
+			Self::Tag => "Tag",
 		}
 	}
 }
 
 impl Display for TokenType
 {
-	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result
 	{
 		write!(f, "{}", self.display_name())
 	}
 }
 
+/// A range of byte indices into the input string that a token or expression was read from
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Span
+{
+	pub start: usize,
+	pub end: usize,
+}
+impl Span
+{
+	pub const fn new(start: usize, end: usize) -> Self
+	{
+		Self { start, end }
+	}
+}
+impl From<Span> for Range<usize>
+{
+	fn from(value: Span) -> Self
+	{
+		value.start..value.end
+	}
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum Token
 {
@@ -72,9 +117,46 @@ pub enum Token
 	ComparisonOperator(CompOperator),
 	OpenDelimiter,
 	CloseDelimiter,
+	Modifier(DiceModifier),
+	Repetition,
+	Identifier(Box<str>),
+	Label(Box<str>),
+	StatementEnd,
+	LogicOperator(LogicOperator),
+	Not,
+	Separator,
+	OpenBracket,
+	CloseBracket,
+	/// A word in square brackets which labels the expression before it (eg. the `fire` in
+	/// `2d6[fire]`)
+	Tag(Box<str>),
 }
 impl Token
 {
+	/// Converts a word matched by [`TokenType::Identifier`] into the token it represents. Words
+	/// which are not dice operators in `dialect`, [`DiceModifier`]s, logical operators, or `x` are
+	/// identifiers
+	pub(crate) fn from_word(word: &str, dialect: &Dialect) -> Self
+	{
+		if let Ok(modifier) = word.parse()
+		{
+			return Self::Modifier(modifier);
+		}
+		if let Ok(operator) = word.parse()
+		{
+			return Self::LogicOperator(operator);
+		}
+
+		match word
+		{
+			_ if dialect.is_dice_word(word) => Self::Operator(OpToken::Dice),
+			"not" => Self::Not,
+			"x" => Self::Repetition,
+			identifier => Self::Identifier(identifier.into()),
+		}
+	}
+
+
 	pub fn token_type(&self) -> TokenType
 	{
 		match self
@@ -84,6 +166,16 @@ impl Token
 			Self::ComparisonOperator(_) => TokenType::ComparisonOperator,
 			Self::OpenDelimiter => TokenType::OpenDelimiter,
 			Self::CloseDelimiter => TokenType::CloseDelimiter,
+			Self::Modifier(_) => TokenType::Modifier,
+			Self::Repetition => TokenType::Repetition,
+			Self::Identifier(_) => TokenType::Identifier,
+			Self::Label(_) => TokenType::Label,
+			Self::StatementEnd => TokenType::StatementEnd,
+			Self::LogicOperator(_) | Self::Not => TokenType::LogicOperator,
+			Self::Separator => TokenType::Separator,
+			Self::OpenBracket => TokenType::OpenBracket,
+			Self::CloseBracket => TokenType::CloseBracket,
+			Self::Tag(_) => TokenType::Tag,
 		}
 	}
 }
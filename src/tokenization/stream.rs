@@ -1,68 +1,214 @@
-use super::{Token, TokenFlags, TokenType, TOKEN_TYPES};
+use super::{alias::Normalized, Span, Token, TokenFlags};
 use crate::{
 	error::{TokenizationError, UnexpectedTokenError, UnknownTokenError},
-	operator::CompOperator,
+	Dialect,
 };
 
 struct BackingTokenStream<'a>
 {
-	str: &'a str,
+	/// The input as it was written, which spans and error indices refer to
+	source: &'a str,
+	/// The input with Unicode aliases replaced, which the cursor moves through
+	normalized: Normalized<'a>,
 	cursor_index: usize,
+	last_span: Span,
+	/// Whether no tokens other than a label have been read since the start of the statement
+	at_statement_start: bool,
+	/// The end of the number which began the current statement, if it is the only token read so
+	/// far. A `#` directly after it is a repetition (eg. `3#1d20`) rather than a comment
+	count_end: Option<usize>,
+	dialect: Dialect,
 }
 impl<'a> BackingTokenStream<'a>
 {
-	pub fn new(str: &'a str) -> Self
+	pub fn new(str: &'a str, dialect: Dialect) -> Self
 	{
 		Self {
-			str,
+			source: str,
+			normalized: Normalized::new(str),
 			cursor_index: 0,
+			last_span: Span::default(),
+			at_statement_start: true,
+			count_end: None,
+			dialect,
 		}
 	}
+
+	/// Moves the cursor past a token of `len` bytes, and returns it
+	fn emit(&mut self, token: Token, len: usize) -> Option<Result<Token, TokenizationError>>
+	{
+		let start = self.cursor_index;
+		self.cursor_index += len;
+
+		self.count_end = match token
+		{
+			Token::Number(_) if self.at_statement_start => Some(self.cursor_index),
+			_ => None,
+		};
+		self.at_statement_start = matches!(token, Token::Label(_) | Token::StatementEnd);
+		self.last_span = Span::new(
+			self.normalized.original_index(start),
+			self.normalized.original_index(self.cursor_index),
+		);
+		Some(Ok(token))
+	}
+
+	/// Moves the cursor to the end of a comment, which runs until the next `;` or line break
+	fn skip_comment(&mut self)
+	{
+		let str = &self.normalized.str;
+		self.cursor_index = str[self.cursor_index..]
+			.find([';', '\n'])
+			.map_or(str.len(), |offset| self.cursor_index + offset);
+	}
 }
 impl<'a> Iterator for BackingTokenStream<'a>
 {
 	type Item = Result<Token, TokenizationError>;
 	fn next(&mut self) -> Option<Self::Item>
 	{
-		if self.cursor_index >= self.str.len()
+		// whitespace and comments are skipped by looping rather than recursing, so long runs of
+		// them cannot overflow the stack
+		loop
 		{
-			return None;
-		}
+			let start = self.cursor_index;
+			let rest = &self.normalized.str[start..];
+			let first = *rest.as_bytes().first()?;
 
-		for token_type in TOKEN_TYPES
-		{
-			if let Some(mtch) = token_type.regex().find_at(self.str, self.cursor_index)
+			let (token, len) = match first
 			{
-				if mtch.start() != self.cursor_index
+				b'0'..=b'9' =>
 				{
+					let len = number_len(rest);
+					(Token::Number(rest[..len].parse().unwrap()), len)
+				}
+				b'A'..=b'Z' | b'a'..=b'z' | b'_' => word(rest, &self.dialect),
+				b'#' if self.count_end == Some(start) => (Token::Repetition, 1),
+				b'#' =>
+				{
+					self.skip_comment();
 					continue;
 				}
-
-				self.cursor_index += mtch.as_str().len();
-				return match token_type
+				_ if rest.starts_with(char::is_whitespace) =>
 				{
-					TokenType::Number => Some(Ok(Token::Number(mtch.as_str().parse().unwrap()))),
-					TokenType::Operator =>
+					self.cursor_index += rest
+						.find(|it: char| !it.is_whitespace())
+						.unwrap_or(rest.len());
+					continue;
+				}
+				_ => match tag(rest).or_else(|| symbol(rest))
+				{
+					Some(symbol) => symbol,
+					None =>
 					{
-						Some(Ok(Token::Operator(mtch.as_str().parse().unwrap())))
+						// step past the unknown character so that iterating cannot get stuck on it
+						let unknown_char = rest.chars().next().unwrap();
+						self.cursor_index += unknown_char.len_utf8();
+
+						let index = self.normalized.original_index(start);
+						return Some(Err(TokenizationError::from(UnknownTokenError {
+							unknown_char,
+							index,
+							char_index: self.source[..index].chars().count(),
+						})));
 					}
-					TokenType::ComparisonOperator => Some(Ok(Token::ComparisonOperator(
-						mtch.as_str().parse::<CompOperator>().unwrap(),
-					))),
-					TokenType::OpenDelimiter => Some(Ok(Token::OpenDelimiter)),
-					TokenType::CloseDelimiter => Some(Ok(Token::CloseDelimiter)),
-					TokenType::Whitespace => self.next(),
-				};
-			}
+				},
+			};
+
+			return self.emit(token, len);
 		}
+	}
+}
+
+/// Returns the length of the number at the start of `str`, which is a run of digits optionally
+/// followed by a `.` and another run of digits
+fn number_len(str: &str) -> usize
+{
+	let bytes = str.as_bytes();
+	let digits_from = |start: usize| {
+		bytes[start..]
+			.iter()
+			.position(|it| !it.is_ascii_digit())
+			.map_or(bytes.len(), |len| start + len)
+	};
 
-		Some(Err(TokenizationError::from(UnknownTokenError {
-			unknown_char: self.str.chars().nth(self.cursor_index).unwrap(),
-			index: self.cursor_index,
-		})))
+	let integer_end = digits_from(0);
+	match bytes.get(integer_end..integer_end + 2)
+	{
+		Some([b'.', second]) if second.is_ascii_digit() => digits_from(integer_end + 1),
+		_ => integer_end,
 	}
 }
 
+/// Reads the word at the start of `str`, returning the token it represents and its length. A
+/// word followed by a `:` is a label, so that the `attack` in `attack: 1d20` is not read as a
+/// variable. Otherwise, the token is decided by [`Token::from_word`]
+fn word(str: &str, dialect: &Dialect) -> (Token, usize)
+{
+	let word_len = str
+		.bytes()
+		.position(|it| !(it.is_ascii_alphabetic() || it == b'_'))
+		.unwrap_or(str.len());
+	let word = &str[..word_len];
+
+	let after = &str[word_len..];
+	let after_space = after.trim_start();
+	if after_space.starts_with(':')
+	{
+		let len = word_len + (after.len() - after_space.len()) + 1;
+		(Token::Label(word.into()), len)
+	}
+	else
+	{
+		(Token::from_word(word, dialect), word_len)
+	}
+}
+
+/// Reads the tag at the start of `str`, which is text in square brackets made of letters, digits,
+/// spaces, `_`, and `'` (eg. `[fire]` or `[cold iron]`), returning the token it represents and its
+/// length. Brackets around anything else are an index (eg. `[0]` or `[(i)]`), so a tag must start
+/// with a letter
+fn tag(str: &str) -> Option<(Token, usize)>
+{
+	let content = str.strip_prefix('[')?;
+	let len = content.find(']')?;
+	let text = content[..len].trim();
+	let is_tag = text.starts_with(char::is_alphabetic)
+		&& text
+			.chars()
+			.all(|it| it.is_alphanumeric() || matches!(it, ' ' | '_' | '\''));
+	// the length includes both brackets
+	is_tag.then(|| (Token::Tag(text.into()), len + 2))
+}
+
+/// Reads the operator or punctuation at the start of `str`, returning the token it represents and
+/// its length, or [`None`] if `str` does not start with one
+fn symbol(str: &str) -> Option<(Token, usize)>
+{
+	let bytes = str.as_bytes();
+	// every symbol is made of ASCII bytes, so slicing `str` by their length is always valid
+	Some(match (bytes[0], bytes.get(1))
+	{
+		(b'.', Some(b'+' | b'-' | b'*' | b'/')) => (Token::Operator(str[..2].parse().ok()?), 2),
+		(b'+' | b'-' | b'*' | b'/' | b'%' | b'^', _) =>
+		{
+			(Token::Operator(str[..1].parse().ok()?), 1)
+		}
+		(b'=' | b'!' | b'<' | b'>', Some(b'=')) =>
+		{
+			(Token::ComparisonOperator(str[..2].parse().ok()?), 2)
+		}
+		(b'<' | b'>', _) => (Token::ComparisonOperator(str[..1].parse().ok()?), 1),
+		(b'(', _) => (Token::OpenDelimiter, 1),
+		(b')', _) => (Token::CloseDelimiter, 1),
+		(b';', _) => (Token::StatementEnd, 1),
+		(b',', _) => (Token::Separator, 1),
+		(b'[', _) => (Token::OpenBracket, 1),
+		(b']', _) => (Token::CloseBracket, 1),
+		_ => return None,
+	})
+}
+
 // I was using Peekable<TokenStream> before,
 // but i want to be able to call methods on the underlying iterator too
 // also borrow checker means i have to wrap it in a new type lol
@@ -72,22 +218,59 @@ pub struct TokenStream<'a>
 {
 	token_stream: BackingTokenStream<'a>,
 	lookahead: Option<Option<Result<Token, TokenizationError>>>,
+	lookahead_span: Span,
+	consumed_span: Span,
 }
 impl<'a> TokenStream<'a>
 {
 	pub fn new(str: &'a str) -> Self
+	{
+		Self::with_dialect(str, Dialect::SAIKORO)
+	}
+
+	/// Creates a stream which reads `str` following the rules of `dialect`
+	pub fn with_dialect(str: &'a str, dialect: Dialect) -> Self
 	{
 		Self {
-			token_stream: BackingTokenStream::new(str),
+			token_stream: BackingTokenStream::new(str, dialect),
 			lookahead: None,
+			lookahead_span: Span::default(),
+			consumed_span: Span::default(),
 		}
 	}
 
+	/// Returns the [`Dialect`] the stream reads its input with
+	pub const fn dialect(&self) -> Dialect
+	{
+		self.token_stream.dialect
+	}
+
 	pub fn peek(&mut self) -> Option<&Result<Token, TokenizationError>>
 	{
-		self.lookahead
-			.get_or_insert_with(|| self.token_stream.next())
-			.as_ref()
+		if self.lookahead.is_none()
+		{
+			self.lookahead = Some(self.token_stream.next());
+			self.lookahead_span = self.token_stream.last_span;
+		}
+
+		self.lookahead.as_ref().and_then(Option::as_ref)
+	}
+
+	/// Returns the byte index at which the next token starts, or the length of the input if there
+	/// are no tokens left
+	pub fn next_start(&mut self) -> usize
+	{
+		match self.peek()
+		{
+			Some(_) => self.lookahead_span.start,
+			None => self.token_stream.source.len(),
+		}
+	}
+
+	/// Returns the [`Span`] of the most recently consumed token
+	pub fn last_span(&self) -> Span
+	{
+		self.consumed_span
 	}
 
 	pub fn expect<T>(&mut self, token_type: T) -> Result<Token, TokenizationError>
@@ -141,8 +324,17 @@ impl<'a> Iterator for TokenStream<'a>
 	{
 		match self.lookahead.take()
 		{
-			Some(it) => it,
-			None => self.token_stream.next(),
+			Some(it) =>
+			{
+				self.consumed_span = self.lookahead_span;
+				it
+			}
+			None =>
+			{
+				let next = self.token_stream.next();
+				self.consumed_span = self.token_stream.last_span;
+				next
+			}
 		}
 	}
 }
@@ -151,7 +343,10 @@ impl<'a> Iterator for TokenStream<'a>
 mod tests
 {
 	use super::*;
-	use crate::operator::OpToken;
+	use crate::{
+		operator::{DiceModifier, OpToken},
+		tokenization::TokenType,
+	};
 
 	#[test]
 	fn whitespace()
@@ -183,6 +378,180 @@ mod tests
 		);
 	}
 
+	#[test]
+	fn modifiers()
+	{
+		assert_eq!(
+			TokenStream::new("1d20dis")
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap(),
+			vec![
+				Token::Number(1.0),
+				Token::Operator(OpToken::Dice),
+				Token::Number(20.0),
+				Token::Modifier(DiceModifier::Disadvantage),
+			]
+		);
+		assert_eq!(
+			TokenStream::new("4d6dl1")
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap(),
+			vec![
+				Token::Number(4.0),
+				Token::Operator(OpToken::Dice),
+				Token::Number(6.0),
+				Token::Modifier(DiceModifier::DropLowest),
+				Token::Number(1.0),
+			]
+		);
+	}
+
+	#[test]
+	fn tags()
+	{
+		assert_eq!(
+			TokenStream::new("2d6[fire] + 1d8[ cold iron ]")
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap(),
+			vec![
+				Token::Number(2.0),
+				Token::Operator(OpToken::Dice),
+				Token::Number(6.0),
+				Token::Tag("fire".into()),
+				Token::Operator(OpToken::Plus),
+				Token::Number(1.0),
+				Token::Operator(OpToken::Dice),
+				Token::Number(8.0),
+				Token::Tag("cold iron".into()),
+			]
+		);
+		// anything other than a tag in brackets is an index
+		assert_eq!(
+			TokenStream::new("[i - 1]")
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap(),
+			vec![
+				Token::OpenBracket,
+				Token::Identifier("i".into()),
+				Token::Operator(OpToken::Minus),
+				Token::Number(1.0),
+				Token::CloseBracket,
+			]
+		);
+	}
+
+	#[test]
+	fn statements()
+	{
+		assert_eq!(
+			TokenStream::new("attack : d20x; dmg")
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap(),
+			vec![
+				Token::Label("attack".into()),
+				Token::Operator(OpToken::Dice),
+				Token::Number(20.0),
+				Token::Repetition,
+				Token::StatementEnd,
+				Token::Identifier("dmg".into()),
+			]
+		);
+	}
+
+	#[test]
+	fn comments()
+	{
+		let tokens = |input| {
+			TokenStream::new(input)
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap()
+		};
+		assert_eq!(
+			tokens("2d6 # fire damage; 1"),
+			vec![
+				Token::Number(2.0),
+				Token::Operator(OpToken::Dice),
+				Token::Number(6.0),
+				Token::StatementEnd,
+				Token::Number(1.0),
+			]
+		);
+		assert_eq!(
+			tokens("hit: 3#2 # 3 attacks"),
+			vec![
+				Token::Label("hit".into()),
+				Token::Number(3.0),
+				Token::Repetition,
+				Token::Number(2.0),
+			]
+		);
+		assert_eq!(tokens("3 #2"), vec![Token::Number(3.0)]);
+		assert_eq!(tokens("# only a comment"), vec![]);
+	}
+
+	#[test]
+	fn unknown_token()
+	{
+		// indices after a multibyte character differ in bytes and chars
+		let tokens = TokenStream::new("１ é$ 2").collect::<Vec<_>>();
+		assert_eq!(tokens.len(), 4);
+		assert!(matches!(
+			tokens[1],
+			Err(TokenizationError::UnknownToken(UnknownTokenError {
+				unknown_char: 'é',
+				index: 4,
+				char_index: 2
+			}))
+		));
+		assert!(matches!(
+			tokens[2],
+			Err(TokenizationError::UnknownToken(UnknownTokenError {
+				unknown_char: '$',
+				index: 6,
+				char_index: 3
+			}))
+		));
+		assert!(matches!(tokens[3], Ok(Token::Number(2.0))));
+	}
+
+	#[test]
+	fn unicode_aliases()
+	{
+		let tokens = |input| {
+			TokenStream::new(input)
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap()
+		};
+		assert_eq!(tokens("２Ｄ６＋３"), tokens("2D6+3"));
+		assert_eq!(tokens("１２ｄ２０ｋｈ１ × 2 ÷ 3 − 4"), tokens("12d20kh1 * 2 / 3 - 4"));
+		assert_eq!(tokens("ａｔｋ：1d20；２．５＞＝１"), tokens("atk:1d20;2.5>=1"));
+
+		// spans refer to the input as written
+		let mut stream = TokenStream::new("２Ｄ６ × 3");
+		stream.consume().unwrap();
+		assert_eq!(stream.last_span(), Span::new(0, 3));
+		stream.consume().unwrap();
+		assert_eq!(stream.last_span(), Span::new(3, 6));
+		assert_eq!(stream.next_start(), 6);
+		stream.consume().unwrap();
+		stream.consume().unwrap();
+		assert_eq!(stream.last_span(), Span::new(10, 12));
+		assert_eq!(stream.next_start(), 13);
+	}
+
+	#[test]
+	fn long_whitespace()
+	{
+		// skipping whitespace and comments should not recurse once per character
+		let input = format!("{}1 {}", " \t\n".repeat(100_000), "# note\n".repeat(100_000));
+		assert_eq!(
+			TokenStream::new(&input)
+				.collect::<Result<Vec<_>, _>>()
+				.unwrap(),
+			vec![Token::Number(1.0)]
+		);
+	}
+
 	#[test]
 	fn peek()
 	{
@@ -199,6 +568,21 @@ mod tests
 		);
 	}
 
+	#[test]
+	fn spans()
+	{
+		let mut stream = TokenStream::new(" 12 +  3");
+		assert_eq!(stream.next_start(), 1);
+		stream.consume().unwrap();
+		assert_eq!(stream.last_span(), Span::new(1, 3));
+		stream.consume().unwrap();
+		assert_eq!(stream.last_span(), Span::new(4, 5));
+		assert_eq!(stream.next_start(), 7);
+		stream.consume().unwrap();
+		assert_eq!(stream.last_span(), Span::new(7, 8));
+		assert_eq!(stream.next_start(), 8);
+	}
+
 	#[test]
 	fn consume_ok()
 	{